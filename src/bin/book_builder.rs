@@ -0,0 +1,78 @@
+// Builds the opening book of the built-in AI from recorded games, e.g. the games directory of the
+// server
+//
+//   book_builder --games games [--out assets/opening.book] [--plies 20] [--min-games 2]
+use std::{env, path::PathBuf, process};
+
+use rust_game::{
+    engine::book::{OpeningBook, DEFAULT_BOOK_PLIES},
+    logic::{ai::BOOK_PATH, record::GameRecord},
+};
+
+fn usage() -> ! {
+    eprintln!("usage: book_builder --games DIR [--out FILE] [--plies N] [--min-games N]");
+    process::exit(2);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+struct Args {
+    games: PathBuf,
+    out: PathBuf,
+    plies: usize,
+    min_games: u32,
+}
+
+fn parse_args() -> Args {
+    let mut games = None;
+    let mut out = PathBuf::from(BOOK_PATH);
+    let (mut plies, mut min_games) = (DEFAULT_BOOK_PLIES, 2);
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            usage();
+        };
+        match arg.as_str() {
+            "--games" => games = Some(PathBuf::from(value)),
+            "--out" => out = PathBuf::from(value),
+            "--plies" => plies = value.parse().unwrap_or_else(|_| usage()),
+            "--min-games" => min_games = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let Some(games) = games else {
+        usage();
+    };
+    Args {
+        games,
+        out,
+        plies,
+        min_games,
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    let records = GameRecord::load_dir(&args.games)
+        .unwrap_or_else(|err| fail(format!("{}: {}", args.games.display(), err)));
+    let book = OpeningBook::from_games(&records, args.plies, args.min_games);
+    if book.is_empty() {
+        fail(format!(
+            "no move in the games of {} was played in {} or more finished games",
+            args.games.display(),
+            args.min_games
+        ));
+    }
+    if let Err(err) = book.save(&args.out) {
+        fail(format!("{}: {}", args.out.display(), err));
+    }
+    println!(
+        "{} positions from {} games saved to {}",
+        book.len(),
+        records.len(),
+        args.out.display()
+    );
+}
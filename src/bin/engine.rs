@@ -0,0 +1,13 @@
+// The AI of the game as a UCI engine, for chess GUIs and tournament managers that support
+// variants, e.g.
+//   uci
+//   setoption name UCI_Variant value prochess
+//   position startpos moves e15e13
+//   go wtime 60000 btime 60000 winc 1000 binc 1000
+use std::io;
+
+use rust_game::engine::uci;
+
+fn main() {
+    uci::run(io::stdin().lock());
+}
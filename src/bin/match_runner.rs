@@ -0,0 +1,292 @@
+// Plays two AI configurations against each other to tell whether a change made the AI stronger.
+// Every opening is played twice with the colors swapped, the score is from the point of view of
+// the first player
+//
+//   match_runner --first builtin --second path/to/old/engine [--games 100] [--openings FILE]
+//       [--tc 10+0.1 | --movetime MS] [--concurrency 1] [--max-moves 200]
+//       [--sprt ELO0,ELO1] [--alpha 0.05] [--beta 0.05]
+use std::{
+    env, fs, process,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use rust_game::{
+    engine::matches::{
+        parse_openings, play_game, GameOutcome, MatchScore, Player, PlayerConfig, Sprt, TimeControl,
+    },
+    logic::board::BoardRecource,
+};
+
+struct MatchConfig {
+    players: [PlayerConfig; 2],
+    games: usize,
+    openings: Vec<BoardRecource>,
+    time_control: TimeControl,
+    concurrency: usize,
+    // in moves of both players, the game is a draw after that
+    max_moves: usize,
+    sprt: Option<Sprt>,
+}
+
+struct Report {
+    game: usize,
+    first_is_white: bool,
+    outcome: GameOutcome,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: match_runner --first PLAYER --second PLAYER [--games N] [--openings FILE] [--tc SECONDS+SECONDS | --movetime MS] [--concurrency N] [--max-moves N] [--sprt ELO0,ELO1] [--alpha A] [--beta B]"
+    );
+    eprintln!(
+        "  a player is builtin, builtin:style=S,depth=N,nodes=N,threads=N,eval=FILE or the path of a UCI engine"
+    );
+    eprintln!("  a style is alphabeta, mcts or mcts-random, MCTS counts its playouts as nodes");
+    process::exit(2);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn parse_args() -> MatchConfig {
+    let mut players = [None, None];
+    let mut games = 100;
+    let mut openings = vec![BoardRecource::new_game()];
+    let mut time_control = TimeControl::Clock {
+        base: Duration::from_secs(10),
+        increment: Duration::from_millis(100),
+    };
+    let mut concurrency = 1;
+    let mut max_moves = 200;
+    let mut sprt = None;
+    let mut error_rates = Sprt::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            usage();
+        };
+        match arg.as_str() {
+            "--first" | "--second" => {
+                let player = PlayerConfig::parse(&value).unwrap_or_else(|err| fail(err));
+                players[(arg == "--second") as usize] = Some(player);
+            }
+            "--games" => games = value.parse().unwrap_or_else(|_| usage()),
+            "--openings" => {
+                let text = fs::read_to_string(&value)
+                    .unwrap_or_else(|err| fail(format!("{}: {}", value, err)));
+                openings =
+                    parse_openings(&text).unwrap_or_else(|err| fail(format!("{}: {}", value, err)));
+                if openings.is_empty() {
+                    fail(format!("{}: no openings", value));
+                }
+            }
+            "--tc" => {
+                time_control = TimeControl::parse_clock(&value).unwrap_or_else(|err| fail(err));
+            }
+            "--movetime" => {
+                let millis = value.parse().unwrap_or_else(|_| usage());
+                time_control = TimeControl::MoveTime(Duration::from_millis(millis));
+            }
+            "--concurrency" => {
+                concurrency = value.parse::<usize>().unwrap_or_else(|_| usage()).max(1);
+            }
+            "--max-moves" => max_moves = value.parse().unwrap_or_else(|_| usage()),
+            "--sprt" => {
+                let (elo0, elo1) = value.split_once(',').unwrap_or_else(|| usage());
+                sprt = Some((
+                    elo0.parse().unwrap_or_else(|_| usage()),
+                    elo1.parse().unwrap_or_else(|_| usage()),
+                ));
+            }
+            "--alpha" => error_rates.alpha = value.parse().unwrap_or_else(|_| usage()),
+            "--beta" => error_rates.beta = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let [Some(first), Some(second)] = players else {
+        usage();
+    };
+    MatchConfig {
+        players: [first, second],
+        games,
+        openings,
+        time_control,
+        concurrency,
+        max_moves,
+        // the error rates may come before or after the bounds
+        sprt: sprt.map(|(elo0, elo1)| Sprt {
+            elo0,
+            elo1,
+            ..error_rates
+        }),
+    }
+}
+
+// starts the player, or restarts it when it didn't survive the last game
+fn ready_player(player: &mut Option<Player>, config: &PlayerConfig) -> Result<(), String> {
+    if let Some(started) = player.as_mut() {
+        if started.new_game().is_ok() {
+            return Ok(());
+        }
+    }
+    *player = Some(
+        config
+            .start()
+            .map_err(|err| format!("{} didn't start: {}", config, err))?,
+    );
+    Ok(())
+}
+
+// takes the next game until all are played or the match is stopped
+fn play_games(
+    config: &MatchConfig,
+    next_game: &AtomicUsize,
+    stop: &AtomicBool,
+    reports: Sender<Result<Report, String>>,
+) {
+    let mut players = [None, None];
+    loop {
+        let game = next_game.fetch_add(1, Ordering::Relaxed);
+        if game >= config.games || stop.load(Ordering::Relaxed) {
+            return;
+        }
+        for (player, player_config) in players.iter_mut().zip(&config.players) {
+            if let Err(err) = ready_player(player, player_config) {
+                let _ = reports.send(Err(err));
+                return;
+            }
+        }
+        let [Some(first), Some(second)] = &mut players else {
+            unreachable!("both players were started");
+        };
+        let first_is_white = game % 2 == 0;
+        let (white, black) = if first_is_white {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let opening = &config.openings[game / 2 % config.openings.len()];
+        let outcome = play_game(
+            white,
+            black,
+            opening,
+            config.time_control,
+            config.max_moves * 2,
+        );
+        let report = Report {
+            game,
+            first_is_white,
+            outcome,
+        };
+        if reports.send(Ok(report)).is_err() {
+            return;
+        }
+    }
+}
+
+fn print_summary(config: &MatchConfig, score: &MatchScore) {
+    println!();
+    println!(
+        "{} vs {}: {} [{:.3}] in {} games",
+        config.players[0],
+        config.players[1],
+        score,
+        score.score(),
+        score.games()
+    );
+    match score.elo() {
+        Some((elo, margin)) => println!("Elo difference: {:.1} +/- {:.1}", elo, margin),
+        None => println!("Elo difference: unknown, one player won every game"),
+    }
+    if let Some(sprt) = &config.sprt {
+        let llr = score.llr(sprt);
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.verdict(llr) {
+            Some(true) => "H1 accepted",
+            Some(false) => "H0 accepted",
+            None => "inconclusive",
+        };
+        println!(
+            "SPRT elo0 {} elo1 {} alpha {} beta {}: LLR {:.2} [{:.2}, {:.2}] {}",
+            sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta, llr, lower, upper, verdict
+        );
+    }
+}
+
+fn main() {
+    let config = Arc::new(parse_args());
+    println!(
+        "{} vs {}, {} games at {} from {} openings on {} threads",
+        config.players[0],
+        config.players[1],
+        config.games,
+        config.time_control,
+        config.openings.len(),
+        config.concurrency
+    );
+
+    let next_game = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, reports) = mpsc::channel();
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|_| {
+            let (config, next_game, stop) = (config.clone(), next_game.clone(), stop.clone());
+            let sender = sender.clone();
+            thread::spawn(move || play_games(&config, &next_game, &stop, sender))
+        })
+        .collect();
+    drop(sender);
+
+    let mut score = MatchScore::default();
+    let mut error = None;
+    // ends once every worker is done
+    for report in reports {
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                stop.store(true, Ordering::Relaxed);
+                error = Some(err);
+                continue;
+            }
+        };
+        score.add(report.outcome.result, report.first_is_white);
+        let (white, black) = if report.first_is_white {
+            (&config.players[0], &config.players[1])
+        } else {
+            (&config.players[1], &config.players[0])
+        };
+        println!(
+            "game {}/{}: {} vs {} {} ({}, {} moves)  {}",
+            report.game + 1,
+            config.games,
+            white,
+            black,
+            report.outcome.result.name(),
+            report.outcome.reason,
+            report.outcome.plies.div_ceil(2),
+            score
+        );
+        let decided = config
+            .sprt
+            .is_some_and(|sprt| sprt.verdict(score.llr(&sprt)).is_some());
+        if decided && !stop.swap(true, Ordering::Relaxed) {
+            println!("SPRT finished, waiting for the running games");
+        }
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    print_summary(&config, &score);
+    if let Some(err) = error {
+        fail(err);
+    }
+}
@@ -0,0 +1,121 @@
+// Finds puzzles in recorded games with the built-in AI, e.g. in the games directory of the server.
+// The ones that aren't in the puzzle file yet are added to it
+//
+//   puzzle_miner --games games [--out assets/puzzles.txt] [--movetime 500 | --depth N] [--threads 1]
+use std::{env, io, path::PathBuf, process, sync::atomic::AtomicBool, time::Duration};
+
+use rust_game::{
+    engine::{eval::EvalParams, miner::mine_game, search::SearchLimits, tt::TranspositionTable},
+    logic::{puzzle::PuzzleSet, puzzle_player::PUZZLES_PATH, record::GameRecord},
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: puzzle_miner --games DIR [--out FILE] [--movetime MS | --depth N] [--threads N]"
+    );
+    process::exit(2);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+struct Args {
+    games: PathBuf,
+    out: PathBuf,
+    limits: SearchLimits,
+    threads: usize,
+}
+
+fn parse_args() -> Args {
+    let mut games = None;
+    let mut out = PathBuf::from(PUZZLES_PATH);
+    let mut limits = SearchLimits {
+        time: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+    let mut threads = 1;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            usage();
+        };
+        match arg.as_str() {
+            "--games" => games = Some(PathBuf::from(value)),
+            "--out" => out = PathBuf::from(value),
+            "--movetime" => {
+                let millis = value.parse().unwrap_or_else(|_| usage());
+                limits = SearchLimits {
+                    time: Some(Duration::from_millis(millis)),
+                    ..Default::default()
+                };
+            }
+            "--depth" => {
+                limits = SearchLimits {
+                    depth: Some(value.parse().unwrap_or_else(|_| usage())),
+                    ..Default::default()
+                };
+            }
+            "--threads" => threads = value.parse::<usize>().unwrap_or_else(|_| usage()).max(1),
+            _ => usage(),
+        }
+    }
+    let Some(games) = games else {
+        usage();
+    };
+    Args {
+        games,
+        out,
+        limits,
+        threads,
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    let records = GameRecord::load_dir(&args.games)
+        .unwrap_or_else(|err| fail(format!("{}: {}", args.games.display(), err)));
+    let mut set = match PuzzleSet::load(&args.out) {
+        Ok(set) => set,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => PuzzleSet::default(),
+        Err(err) => fail(format!("{}: {}", args.out.display(), err)),
+    };
+
+    let (tt, eval) = (TranspositionTable::default(), EvalParams::default());
+    let mut added = 0;
+    for (i, record) in records.iter().enumerate() {
+        eprint!(
+            "\rmining game {}/{}, {} new puzzles",
+            i + 1,
+            records.len(),
+            added
+        );
+        let puzzles = mine_game(
+            &record.moves,
+            args.limits,
+            &tt,
+            &eval,
+            args.threads,
+            &AtomicBool::new(false),
+        );
+        for puzzle in puzzles {
+            if !set.contains(&puzzle.id) {
+                set.puzzles.push(puzzle);
+                added += 1;
+            }
+        }
+    }
+    eprintln!();
+
+    if let Err(err) = set.save(&args.out) {
+        fail(format!("{}: {}", args.out.display(), err));
+    }
+    println!(
+        "{} new puzzles from {} games, {} in {}",
+        added,
+        records.len(),
+        set.puzzles.len(),
+        args.out.display()
+    );
+}
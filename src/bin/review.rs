@@ -0,0 +1,129 @@
+// Reviews a recorded game with the built-in AI, every move gets a class by how much it lost and
+// each player an accuracy
+//
+//   review --game games/alice-bob.game [--movetime 500 | --depth N] [--threads 1]
+use std::{env, path::PathBuf, process, sync::atomic::AtomicBool, time::Duration};
+
+use rust_game::{
+    engine::{
+        eval::EvalParams,
+        review::{review_game, MoveClass},
+        search::SearchLimits,
+        tt::TranspositionTable,
+    },
+    logic::{board::move_name, record::GameRecord},
+};
+
+fn usage() -> ! {
+    eprintln!("usage: review --game FILE [--movetime MS | --depth N] [--threads N]");
+    process::exit(2);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+struct Args {
+    game: PathBuf,
+    limits: SearchLimits,
+    threads: usize,
+}
+
+fn parse_args() -> Args {
+    let mut game = None;
+    let mut limits = SearchLimits {
+        time: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+    let mut threads = 1;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            usage();
+        };
+        match arg.as_str() {
+            "--game" => game = Some(PathBuf::from(value)),
+            "--movetime" => {
+                let millis = value.parse().unwrap_or_else(|_| usage());
+                limits = SearchLimits {
+                    time: Some(Duration::from_millis(millis)),
+                    ..Default::default()
+                };
+            }
+            "--depth" => {
+                limits = SearchLimits {
+                    depth: Some(value.parse().unwrap_or_else(|_| usage())),
+                    ..Default::default()
+                };
+            }
+            "--threads" => threads = value.parse::<usize>().unwrap_or_else(|_| usage()).max(1),
+            _ => usage(),
+        }
+    }
+    let Some(game) = game else {
+        usage();
+    };
+    Args {
+        game,
+        limits,
+        threads,
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    let record = GameRecord::load(&args.game)
+        .unwrap_or_else(|err| fail(format!("{}: {}", args.game.display(), err)));
+    let (tt, eval) = (TranspositionTable::default(), EvalParams::default());
+    let review = review_game(
+        &record.moves,
+        args.limits,
+        &tt,
+        &eval,
+        args.threads,
+        &AtomicBool::new(false),
+        |done| eprint!("\rreviewed {}/{} positions", done, record.moves.len() + 1),
+    );
+    eprintln!();
+    if review.moves.len() < record.moves.len() {
+        eprintln!(
+            "move {} isn't legal, the rest of the game is left out",
+            review.moves.len() + 1
+        );
+    }
+
+    for (ply, mv) in review.moves.iter().enumerate() {
+        let (src, dst) = mv.mv;
+        let mut line = format!(
+            "{}{} {}{}",
+            ply / 2 + 1,
+            if ply % 2 == 0 { "." } else { "..." },
+            move_name(src, dst),
+            mv.class.symbol()
+        );
+        line += &format!("  {} ({:+})", mv.class.name(), -mv.loss());
+        if let Some((src, dst)) = mv.best {
+            line += &format!(", best was {}", move_name(src, dst));
+        }
+        println!("{}", line);
+    }
+    println!();
+    for (color, name) in [(true, "White"), (false, "Black")] {
+        let player = record.tag(name).unwrap_or(name);
+        let accuracy = review
+            .accuracy(color)
+            .map_or("-".to_string(), |accuracy| format!("{:.1}%", accuracy));
+        let counts: Vec<String> = MoveClass::ALL
+            .iter()
+            .map(|&class| format!("{} {}", review.count(color, class), class.name()))
+            .collect();
+        println!(
+            "{} ({}): accuracy {}, {}",
+            player,
+            name,
+            accuracy,
+            counts.join(", ")
+        );
+    }
+}
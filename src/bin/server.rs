@@ -0,0 +1,48 @@
+// A game server without a window, players join it from the network screen of the game
+//
+//   server [--port 7878] [--games-dir games] [--time 10] [--increment 5] [--grace 60]
+use std::{env, path::PathBuf, process, time::Duration};
+
+use rust_game::network::server::{GameServer, ServerConfig};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: server [--port PORT] [--games-dir DIR] [--time MINUTES] [--increment SECONDS] [--grace SECONDS]"
+    );
+    process::exit(2);
+}
+
+fn parse_args() -> ServerConfig {
+    let mut config = ServerConfig::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            usage();
+        };
+        match arg.as_str() {
+            "--port" => config.port = value.parse().unwrap_or_else(|_| usage()),
+            "--games-dir" => config.games_dir = PathBuf::from(value),
+            "--time" => {
+                let minutes: u64 = value.parse().unwrap_or_else(|_| usage());
+                config.base = Duration::from_secs(minutes * 60);
+            }
+            "--grace" => {
+                config.grace = Duration::from_secs(value.parse().unwrap_or_else(|_| usage()));
+            }
+            "--increment" => {
+                config.increment = Duration::from_secs(value.parse().unwrap_or_else(|_| usage()));
+            }
+            _ => usage(),
+        }
+    }
+    config
+}
+
+fn main() {
+    let config = parse_args();
+    let result = GameServer::bind(config).and_then(|mut server| server.run());
+    if let Err(err) = result {
+        eprintln!("server stopped: {}", err);
+        process::exit(1);
+    }
+}
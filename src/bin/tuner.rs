@@ -0,0 +1,88 @@
+// Tunes the parameters of the evaluation on recorded games, e.g. the games directory of the server
+//
+//   tuner --games games [--out assets/eval.params] [--params FILE] [--epochs 300] [--rate 1]
+//
+// The game plays with the file in the assets. Written somewhere else it's loaded with the EvalFile
+// option of the engine or `builtin:eval=FILE` in the match runner, to measure the tuned values
+// against the old ones
+use std::{env, path::PathBuf, process};
+
+use rust_game::{
+    engine::{
+        eval::EvalParams,
+        tuner::{load_corpus, tune, TuningOptions},
+    },
+    logic::ai::EVAL_PATH,
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: tuner --games DIR [--out FILE] [--params FILE] [--epochs N] [--rate CENTIPAWNS]"
+    );
+    process::exit(2);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+struct Args {
+    games: PathBuf,
+    out: PathBuf,
+    params: Option<PathBuf>,
+    options: TuningOptions,
+}
+
+fn parse_args() -> Args {
+    let (mut games, mut params) = (None, None);
+    let mut out = PathBuf::from(EVAL_PATH);
+    let mut options = TuningOptions::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            usage();
+        };
+        match arg.as_str() {
+            "--games" => games = Some(PathBuf::from(value)),
+            "--out" => out = PathBuf::from(value),
+            "--params" => params = Some(PathBuf::from(value)),
+            "--epochs" => options.epochs = value.parse().unwrap_or_else(|_| usage()),
+            "--rate" => options.learning_rate = value.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+    let Some(games) = games else {
+        usage();
+    };
+    Args {
+        games,
+        out,
+        params,
+        options,
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    let start = match &args.params {
+        // the error already names the file
+        Some(path) => EvalParams::load(path).unwrap_or_else(|err| fail(err.to_string())),
+        None => EvalParams::default(),
+    };
+    let positions = load_corpus(&args.games)
+        .unwrap_or_else(|err| fail(format!("{}: {}", args.games.display(), err)));
+    if positions.is_empty() {
+        fail(format!("no finished games in {}", args.games.display()));
+    }
+    println!("tuning on {} positions", positions.len());
+    let tuned = tune(&start, &positions, args.options, |epoch, error| {
+        if epoch % 10 == 0 || epoch == args.options.epochs {
+            println!("epoch {}: error {:.6}", epoch, error);
+        }
+    });
+    if let Err(err) = tuned.save(&args.out) {
+        fail(format!("{}: {}", args.out.display(), err));
+    }
+    println!("saved to {}", args.out.display());
+}
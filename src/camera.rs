@@ -0,0 +1,120 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::camera::ScalingMode,
+    window::PrimaryWindow,
+};
+
+use crate::states::{AppState, TextInputFocused};
+
+// the part of the world that is always visible when the camera isn't zoomed, the board and
+// everything around it fits in it so it's scaled to any window size
+const VIEW_WIDTH: f32 = 1280.0;
+const VIEW_HEIGHT: f32 = 940.0;
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 1.5;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_camera)
+            .add_systems(OnEnter(AppState::InGame), reset_camera)
+            .add_systems(
+                Update,
+                (zoom_camera, pan_camera, reset_camera_on_key).run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::AutoMin {
+        min_width: VIEW_WIDTH,
+        min_height: VIEW_HEIGHT,
+    };
+    commands.spawn(camera);
+}
+
+fn reset_camera(mut cameras: Query<(&mut Transform, &mut OrthographicProjection)>) {
+    for (mut transform, mut projection) in &mut cameras {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+        projection.scale = 1.0;
+    }
+}
+
+fn reset_camera_on_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    cameras: Query<(&mut Transform, &mut OrthographicProjection)>,
+) {
+    if keys.just_pressed(KeyCode::Home) && !text_input.0 {
+        reset_camera(cameras);
+    }
+}
+
+// zooms in and out around the cursor, so the square under it stays under it
+fn zoom_camera(
+    mut wheel: EventReader<MouseWheel>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(
+        &Camera,
+        &GlobalTransform,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+) {
+    let scroll: f32 = wheel
+        .read()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / 100.0,
+        })
+        .sum();
+    if scroll == 0.0 {
+        return;
+    }
+    let Ok((camera, camera_transform, mut transform, mut projection)) = cameras.get_single_mut()
+    else {
+        return;
+    };
+    let old_scale = projection.scale;
+    projection.scale = (old_scale * 0.9_f32.powf(scroll)).clamp(MIN_ZOOM, MAX_ZOOM);
+
+    let cursor = q_windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor));
+    if let Some(cursor) = cursor {
+        let center = transform.translation.truncate();
+        let new_center = cursor + (center - cursor) * (projection.scale / old_scale);
+        transform.translation.x = new_center.x;
+        transform.translation.y = new_center.y;
+    }
+}
+
+// drags the board around while the right button is held
+fn pan_camera(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Transform, &OrthographicProjection)>,
+) {
+    let delta: Vec2 = motion.read().map(|ev| ev.delta).sum();
+    if !mouse_buttons.pressed(MouseButton::Right) || delta == Vec2::ZERO {
+        return;
+    }
+    let Ok(window) = q_windows.get_single() else {
+        return;
+    };
+    let Ok((mut transform, projection)) = cameras.get_single_mut() else {
+        return;
+    };
+    // world units per window pixel, the window's y axis points down while the world's points up
+    let ratio = projection.area.width() / window.width();
+    transform.translation.x -= delta.x * ratio;
+    transform.translation.y += delta.y * ratio;
+}
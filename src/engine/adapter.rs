@@ -0,0 +1,171 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::logic::board::{parse_move, BoardRecource};
+
+use super::uci::{GoParams, VARIANT};
+
+// how long an engine may take to start up and to answer `isready`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// on top of the thinking time, before the engine counts as hung
+const MOVE_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+// how long an engine gets to quit on its own before it's killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn protocol_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Another program that plays the game over UCI, e.g. the engine binary of this crate or
+// Fairy-Stockfish with a variant file for the game. It has to support the variant through
+// `UCI_Variant`
+pub struct ExternalEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    // the name the engine gave itself
+    pub name: String,
+}
+
+impl ExternalEngine {
+    pub fn start(path: &Path) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        // the engine is read on its own thread, so a silent engine can't block the caller
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = ExternalEngine {
+            child,
+            stdin,
+            lines,
+            name: path.display().to_string(),
+        };
+
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        let mut supports_variant = false;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.starts_with("option name UCI_Variant ") {
+                supports_variant = line.split_whitespace().any(|word| word == VARIANT);
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        if !supports_variant {
+            return Err(protocol_error(format!(
+                "{} doesn't support the {} variant",
+                engine.name, VARIANT
+            )));
+        }
+        engine.send(&format!("setoption name UCI_Variant value {}", VARIANT))?;
+        engine.new_game()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} didn't answer in time", self.name),
+            ),
+            RecvTimeoutError::Disconnected => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} quit", self.name))
+            }
+        })
+    }
+
+    // waits until the engine handled everything sent to it
+    fn sync(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+
+    // the move the engine plays in this position after thinking for `think_time`, it isn't
+    // checked against the rules here
+    pub fn best_move(
+        &mut self,
+        board: &BoardRecource,
+        think_time: Duration,
+    ) -> io::Result<(usize, usize)> {
+        let params = GoParams {
+            movetime: Some(think_time),
+            ..GoParams::default()
+        };
+        self.go(board, &params)
+    }
+
+    // like `best_move`, with everything `go` can tell the engine, e.g. the clocks of a game
+    pub fn go(&mut self, board: &BoardRecource, params: &GoParams) -> io::Result<(usize, usize)> {
+        self.send(&format!("position fen {}", board.to_fen()))?;
+        self.send(&params.to_command())?;
+        // the engine can't think longer than its whole clock
+        let thinking = params
+            .movetime
+            .or(if board.turn {
+                params.wtime
+            } else {
+                params.btime
+            })
+            .unwrap_or_default();
+        let deadline = Instant::now() + thinking + MOVE_TIMEOUT_MARGIN;
+        loop {
+            let line = self.read_line(deadline)?;
+            let mut words = line.split_whitespace();
+            if words.next() != Some("bestmove") {
+                continue;
+            }
+            let word = words.next().unwrap_or("(none)");
+            return parse_move(word).ok_or_else(|| {
+                protocol_error(format!("{} played an unknown move: {}", self.name, word))
+            });
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::logic::board::BoardRecource;
+
+use super::{
+    eval::EvalParams,
+    search::{search, SearchInfo, SearchLimits},
+    tt::TranspositionTable,
+};
+
+// Searches the position that is looked at without a limit, on its own thread, until another one
+// is looked at. The lines it finds come in as the search gets deeper
+pub struct Analyzer {
+    tt: Arc<TranspositionTable>,
+    eval: Arc<EvalParams>,
+    threads: usize,
+    stop: Arc<AtomicBool>,
+    // every report comes with the number of the search it belongs to, the ones of searches that
+    // were already stopped are dropped
+    reports: Mutex<Receiver<(u64, SearchInfo)>>,
+    sender: Sender<(u64, SearchInfo)>,
+    searches: u64,
+}
+
+impl Analyzer {
+    pub fn new(threads: usize, eval: EvalParams) -> Self {
+        let (sender, reports) = mpsc::channel();
+        Analyzer {
+            tt: Arc::new(TranspositionTable::default()),
+            eval: Arc::new(eval),
+            threads,
+            stop: Arc::new(AtomicBool::new(true)),
+            reports: Mutex::new(reports),
+            sender,
+            searches: 0,
+        }
+    }
+
+    // stops the last search and starts one for `board` that shows `lines` moves
+    pub fn analyze(&mut self, board: &BoardRecource, lines: usize) {
+        self.stop();
+        self.searches += 1;
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+        let (board, tt, eval) = (board.clone(), self.tt.clone(), self.eval.clone());
+        let (threads, sender, id) = (self.threads, self.sender.clone(), self.searches);
+        let limits = SearchLimits {
+            lines: Some(lines),
+            ..Default::default()
+        };
+        // the thread isn't waited for, it ends soon after it was told to stop
+        thread::spawn(move || {
+            search(&board, limits, &tt, &eval, threads, &stop, |info| {
+                let _ = sender.send((id, info.clone()));
+            });
+        });
+    }
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    // what the current search found since the last call
+    pub fn poll(&self) -> Vec<SearchInfo> {
+        let reports = self.reports.lock().unwrap();
+        reports
+            .try_iter()
+            .filter(|(id, _)| *id == self.searches)
+            .map(|(_, info)| info)
+            .collect()
+    }
+}
+
+impl Drop for Analyzer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,274 @@
+use std::{cmp::Reverse, collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::logic::{
+    board::{move_name, parse_move, BoardRecource, GameResult},
+    record::GameRecord,
+};
+
+// The moves the AI knows for the first positions of a game, so it doesn't play the same opening
+// every time. Positions are looked up by their Zobrist hash and every move has a weight, how
+// likely it's picked. The book is written as text, a line per position:
+//   # opening book
+//   3f1c9a0e6b2d4875 e3e5 12 d2d4 4
+
+// how many of the first moves of every game go into a book
+pub const DEFAULT_BOOK_PLIES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub mv: (usize, usize),
+    pub weight: u32,
+}
+
+// how the AI picks between the book moves of a position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Variety {
+    // the book isn't used
+    Off,
+    // always the move with the highest weight
+    MainLine,
+    // the moves by their weights
+    #[default]
+    Weighted,
+    // every book move is as likely
+    Wide,
+}
+
+impl Variety {
+    pub const ALL: [Variety; 4] = [
+        Variety::Off,
+        Variety::MainLine,
+        Variety::Weighted,
+        Variety::Wide,
+    ];
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpeningBook {
+    positions: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub const EXTENSION: &'static str = "book";
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // Counts the moves of the first `plies` of every finished game. A move gets 2 for every game
+    // the side that played it won and 1 for a draw, moves from fewer than `min_games` games are
+    // left out, as are the ones that only lost
+    pub fn from_games(records: &[GameRecord], plies: usize, min_games: u32) -> Self {
+        // the weight and the number of games of every move, by position
+        let mut counts: HashMap<u64, HashMap<(usize, usize), (u32, u32)>> = HashMap::new();
+        for record in records {
+            let Some(result) = record.result() else {
+                continue;
+            };
+            let mut board = BoardRecource::new_game();
+            for &(src, dst) in record.moves.iter().take(plies) {
+                let hash = board.hash;
+                let points = match result {
+                    GameResult::Win(winner) if winner == board.turn => 2,
+                    GameResult::Win(_) => 0,
+                    GameResult::Draw => 1,
+                };
+                if board.make_move(src, dst).is_none() {
+                    break;
+                }
+                let count = counts
+                    .entry(hash)
+                    .or_default()
+                    .entry((src, dst))
+                    .or_default();
+                count.0 += points;
+                count.1 += 1;
+            }
+        }
+
+        let mut positions = HashMap::new();
+        for (hash, moves) in counts {
+            let mut moves: Vec<BookMove> = moves
+                .into_iter()
+                .filter(|&(_, (weight, games))| weight > 0 && games >= min_games)
+                .map(|(mv, (weight, _))| BookMove { mv, weight })
+                .collect();
+            if moves.is_empty() {
+                continue;
+            }
+            sort_moves(&mut moves);
+            positions.insert(hash, moves);
+        }
+        OpeningBook { positions }
+    }
+
+    // the book moves of the position, the heaviest first. Only the legal ones, in case another
+    // position has the same hash
+    pub fn moves(&self, board: &BoardRecource) -> Vec<BookMove> {
+        let Some(moves) = self.positions.get(&board.hash) else {
+            return Vec::new();
+        };
+        moves
+            .iter()
+            .copied()
+            .filter(|book_move| {
+                let (src, dst) = book_move.mv;
+                board.clone().make_move(src, dst).is_some()
+            })
+            .collect()
+    }
+
+    // a book move for the position picked by `variety`, `random` is any random number
+    pub fn pick(
+        &self,
+        board: &BoardRecource,
+        variety: Variety,
+        random: u64,
+    ) -> Option<(usize, usize)> {
+        let moves = self.moves(board);
+        if moves.is_empty() {
+            return None;
+        }
+        let index = match variety {
+            Variety::Off => return None,
+            Variety::MainLine => 0,
+            Variety::Wide => (random % moves.len() as u64) as usize,
+            Variety::Weighted => {
+                let total: u64 = moves.iter().map(|book_move| book_move.weight as u64).sum();
+                let mut pick = random % total.max(1);
+                moves
+                    .iter()
+                    .position(|book_move| {
+                        let weight = book_move.weight as u64;
+                        let picked = pick < weight;
+                        pick = pick.saturating_sub(weight);
+                        picked
+                    })
+                    .unwrap_or(0)
+            }
+        };
+        Some(moves[index].mv)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut hashes: Vec<&u64> = self.positions.keys().collect();
+        hashes.sort();
+        let mut text = String::from("# opening book, the position hash and its moves by weight\n");
+        for hash in hashes {
+            text += &format!("{:016x}", hash);
+            for book_move in &self.positions[hash] {
+                let (src, dst) = book_move.mv;
+                text += &format!(" {} {}", move_name(src, dst), book_move.weight);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut positions = HashMap::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let hash = words.next().unwrap_or_default();
+            let hash =
+                u64::from_str_radix(hash, 16).map_err(|_| format!("not a position: {}", hash))?;
+            let words: Vec<&str> = words.collect();
+            let mut moves = Vec::new();
+            for pair in words.chunks(2) {
+                let [mv, weight] = pair else {
+                    return Err(format!("a move without a weight: {}", line));
+                };
+                let mv = parse_move(mv).ok_or_else(|| format!("not a move: {}", mv))?;
+                let weight = weight
+                    .parse()
+                    .map_err(|_| format!("not a weight: {}", weight))?;
+                moves.push(BookMove { mv, weight });
+            }
+            // books written by hand can list the moves in any order
+            sort_moves(&mut moves);
+            positions.insert(hash, moves);
+        }
+        Ok(OpeningBook { positions })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        OpeningBook::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+// the heaviest first, the same order every time
+fn sort_moves(moves: &mut [BookMove]) {
+    moves.sort_by_key(|book_move| (Reverse(book_move.weight), book_move.mv));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::board::GameEndReason;
+
+    use super::*;
+
+    #[test]
+    fn parsed_moves_are_sorted_by_weight() {
+        let hash = BoardRecource::new_game().hash;
+        let book = OpeningBook::parse(&format!("{:016x} d2d4 4 e2e4 12 c2c4 4\n", hash)).unwrap();
+        let moves: Vec<(String, u32)> = book
+            .moves(&BoardRecource::new_game())
+            .iter()
+            .map(|book_move| (move_name(book_move.mv.0, book_move.mv.1), book_move.weight))
+            .collect();
+        let expected = [("e2e4", 12), ("c2c4", 4), ("d2d4", 4)];
+        assert_eq!(moves, expected.map(|(mv, weight)| (mv.to_string(), weight)));
+    }
+
+    #[test]
+    fn books_round_trip() {
+        let mut records = Vec::new();
+        for (moves, result) in [
+            (["e2e4", "e15e13"], GameResult::Win(true)),
+            (["e2e4", "d15d13"], GameResult::Draw),
+            (["d2d4", "e15e13"], GameResult::Win(false)),
+            (["c2c4", "e15e13"], GameResult::Win(true)),
+        ] {
+            let mut record = GameRecord::new("White", "Black");
+            for mv in moves {
+                let (src, dst) = parse_move(mv).unwrap();
+                record.push_move(src, dst);
+            }
+            record.set_result(result, GameEndReason::Checkmate);
+            records.push(record);
+        }
+        let book = OpeningBook::from_games(&records, DEFAULT_BOOK_PLIES, 1);
+        assert!(!book.is_empty());
+        assert_eq!(OpeningBook::parse(&book.to_text()).unwrap(), book);
+        assert_eq!(
+            OpeningBook::parse(&book.to_text()).unwrap().to_text(),
+            book.to_text()
+        );
+    }
+
+    #[test]
+    fn malformed_books_are_errors() {
+        assert!(OpeningBook::parse("# only a comment\n\n")
+            .unwrap()
+            .is_empty());
+        assert!(OpeningBook::parse("xyz e2e4 1").is_err());
+        assert!(OpeningBook::parse("00ff e2e4").is_err());
+        assert!(OpeningBook::parse("00ff e2e4 heavy").is_err());
+        assert!(OpeningBook::parse("00ff e99e5 1").is_err());
+    }
+}
@@ -0,0 +1,470 @@
+use std::{fs, io, path::Path};
+
+use crate::logic::{
+    board::{to_board_index, to_cord_index, BoardRecource},
+    piece::{MovementsRules, Piece, PieceTypes},
+    TILE_NUMBER,
+};
+
+// The evaluation is a sum of terms, each a parameter times how often its feature shows up on the
+// board: material, a piece-square table for every piece type, mobility, king safety and pawn
+// structure. Because it's linear the tuner can fit every parameter at once from recorded games,
+// see `tuner.rs`. The parameters are read from a text file like this one, groups that are left
+// out keep their built-in values:
+//   # the value of a pawn
+//   material.pawn 100
+//   psqt.knight
+//     0 0 0 ... 16 values per rank, rank 16 first, from white's point of view
+//   passed_pawn 0 0 2 4 ...
+
+const SQUARES: usize = TILE_NUMBER * TILE_NUMBER;
+const PIECE_TYPES: usize = PieceTypes::ShortRook as usize + 1;
+
+// where each group of parameters starts in `EvalParams::values`
+const MATERIAL: usize = 0;
+const PSQT: usize = MATERIAL + PIECE_TYPES;
+const MOBILITY: usize = PSQT + PIECE_TYPES * SQUARES;
+// for every own piece next to the king
+const KING_SHELTER: usize = MOBILITY + PIECE_TYPES;
+// for every enemy piece other than a pawn up to two squares from the king
+const KING_ATTACKERS: usize = KING_SHELTER + 1;
+const DOUBLED_PAWN: usize = KING_ATTACKERS + 1;
+const ISOLATED_PAWN: usize = DOUBLED_PAWN + 1;
+// by how many ranks the pawn advanced
+const PASSED_PAWN: usize = ISOLATED_PAWN + 1;
+pub const PARAM_COUNT: usize = PASSED_PAWN + TILE_NUMBER;
+
+// What a piece is worth in centipawns, roughly by how many squares it can reach. The start of
+// the tuned material values and what the search orders captures by
+pub fn piece_value(piece_type: PieceTypes) -> i32 {
+    match piece_type {
+        PieceTypes::Pawn => 100,
+        PieceTypes::ShortRook => 150,
+        PieceTypes::Jester => 250,
+        PieceTypes::Abbess => 250,
+        PieceTypes::Knight => 300,
+        PieceTypes::Bishop => 330,
+        PieceTypes::Rook => 500,
+        PieceTypes::GrandCommander => 600,
+        PieceTypes::Amazon => 700,
+        PieceTypes::Queen => 900,
+        // both kings are always on the board
+        PieceTypes::King => 0,
+    }
+}
+
+fn piece_name(piece_type: PieceTypes) -> String {
+    format!("{:?}", piece_type).to_lowercase()
+}
+
+// the name, the first index and the number of values of every group in the parameter file
+fn groups() -> Vec<(String, usize, usize)> {
+    let mut groups = Vec::new();
+    for &piece_type in PieceTypes::ALL {
+        let name = piece_name(piece_type);
+        let index = piece_type as usize;
+        groups.push((format!("material.{}", name), MATERIAL + index, 1));
+        groups.push((format!("psqt.{}", name), PSQT + index * SQUARES, SQUARES));
+        groups.push((format!("mobility.{}", name), MOBILITY + index, 1));
+    }
+    groups.push(("king_shelter".to_string(), KING_SHELTER, 1));
+    groups.push(("king_attackers".to_string(), KING_ATTACKERS, 1));
+    groups.push(("doubled_pawn".to_string(), DOUBLED_PAWN, 1));
+    groups.push(("isolated_pawn".to_string(), ISOLATED_PAWN, 1));
+    groups.push(("passed_pawn".to_string(), PASSED_PAWN, TILE_NUMBER));
+    groups
+}
+
+// how many ranks a piece of `color` on row `y` is away from its own back rank
+fn advancement(y: usize, color: bool) -> usize {
+    if color {
+        TILE_NUMBER - 1 - y
+    } else {
+        y
+    }
+}
+
+// how many files or ranks a line is away from the two in the middle of the board
+fn center_distance(line: usize) -> usize {
+    line.abs_diff(TILE_NUMBER / 2 - 1)
+        .min(line.abs_diff(TILE_NUMBER / 2))
+}
+
+// squares as white sees them, black's pieces use the same tables upside down
+fn relative_square(square: usize, color: bool) -> usize {
+    let (x, y) = to_cord_index(square);
+    to_board_index(x, TILE_NUMBER - 1 - advancement(y, color))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    values: Vec<i32>,
+}
+
+impl Default for EvalParams {
+    // hand-picked values to start tuning from
+    fn default() -> Self {
+        let mut values = vec![0; PARAM_COUNT];
+        for &piece_type in PieceTypes::ALL {
+            let index = piece_type as usize;
+            values[MATERIAL + index] = piece_value(piece_type);
+            for square in 0..SQUARES {
+                let (x, y) = to_cord_index(square);
+                let advanced = advancement(y, true) as i32;
+                // 0 on the edge up to 7 in the middle
+                let center = 7 - center_distance(x).max(center_distance(y)) as i32;
+                values[PSQT + index * SQUARES + square] = match piece_type {
+                    PieceTypes::Pawn => 3 * advanced,
+                    // the king hides behind its pieces
+                    PieceTypes::King => -5 * advanced,
+                    _ => 2 * center,
+                };
+            }
+            values[MOBILITY + index] = match piece_type {
+                PieceTypes::Pawn | PieceTypes::King => 0,
+                PieceTypes::Knight => 4,
+                _ => 2,
+            };
+        }
+        values[KING_SHELTER] = 6;
+        values[KING_ATTACKERS] = -8;
+        values[DOUBLED_PAWN] = -12;
+        values[ISOLATED_PAWN] = -8;
+        for advanced in 0..TILE_NUMBER {
+            values[PASSED_PAWN + advanced] = (advanced * advanced / 2) as i32;
+        }
+        EvalParams { values }
+    }
+}
+
+impl EvalParams {
+    pub const EXTENSION: &'static str = "params";
+
+    pub fn values(&self) -> &[i32] {
+        &self.values
+    }
+
+    pub fn from_values(values: Vec<i32>) -> Result<Self, String> {
+        if values.len() != PARAM_COUNT {
+            return Err(format!(
+                "expected {} parameters, found {}",
+                PARAM_COUNT,
+                values.len()
+            ));
+        }
+        Ok(EvalParams { values })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# evaluation parameters of the built-in AI, in centipawns\n");
+        for (name, start, len) in groups() {
+            let values = &self.values[start..start + len];
+            if len == SQUARES {
+                text += &format!("{}\n", name);
+                for rank in values.chunks(TILE_NUMBER) {
+                    let rank: Vec<String> = rank.iter().map(|value| value.to_string()).collect();
+                    text += &format!("  {}\n", rank.join(" "));
+                }
+            } else {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                text += &format!("{} {}\n", name, values.join(" "));
+            }
+        }
+        text
+    }
+
+    // the values of every group follow its name, over as many lines as needed
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let groups = groups();
+        let mut params = EvalParams::default();
+        let mut current: Option<(&str, usize, usize)> = None;
+        let mut read = 0;
+        let finish = |current: Option<(&str, usize, usize)>, read: usize| match current {
+            Some((name, _, len)) if read != len => {
+                Err(format!("{} needs {} values, found {}", name, len, read))
+            }
+            _ => Ok(()),
+        };
+        let words = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(str::split_whitespace);
+        for word in words {
+            if let Ok(value) = word.parse::<i32>() {
+                let Some((name, start, len)) = current else {
+                    return Err(format!("value {} before the first name", value));
+                };
+                if read == len {
+                    return Err(format!("{} needs {} values, found more", name, len));
+                }
+                params.values[start + read] = value;
+                read += 1;
+                continue;
+            }
+            finish(current, read)?;
+            let (name, start, len) = groups
+                .iter()
+                .find(|(name, _, _)| name == word)
+                .ok_or_else(|| format!("unknown parameter {}", word))?;
+            current = Some((name.as_str(), *start, *len));
+            read = 0;
+        }
+        finish(current, read)?;
+        Ok(params)
+    }
+
+    // the errors name the file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+        EvalParams::parse(&text).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+// How many squares the piece reaches, empty ones and ones with an enemy piece. It ignores
+// whether the moves leave the king in check, which is too slow to find out for every position
+// the search evaluates. Pawns are left out, the pawn terms cover them
+fn mobility(board: &BoardRecource, square: usize, piece: &Piece) -> i32 {
+    if piece.get_type() == PieceTypes::Pawn {
+        return 0;
+    }
+    let rules = piece.get_rules();
+    let movement = rules.movment_rules;
+    let (x, y) = to_cord_index(square);
+    let (x, y) = (x as i32, y as i32);
+    let target = |dx: i32, dy: i32| {
+        let (tx, ty) = (x + dx, y + dy);
+        let on_board =
+            (0..TILE_NUMBER as i32).contains(&tx) && (0..TILE_NUMBER as i32).contains(&ty);
+        on_board.then(|| board.grid[to_board_index(tx as usize, ty as usize)])
+    };
+
+    let mut count = 0;
+    // the other pieces that move like pawns, e.g. the Jester, step straight to empty squares and
+    // capture one square diagonally, backward too if their rules allow it
+    if movement.contains(MovementsRules::PAWN_MOVMENT) {
+        let color = piece.get_color();
+        let forward = if color { -1 } else { 1 };
+        let directions = if rules.multiple_direction_rule { 2 } else { 1 };
+        let reach = rules.max_distance.unwrap_or(1) - i32::from(piece.has_moved());
+        for dy in [forward, -forward].into_iter().take(directions) {
+            for distance in 1..=reach {
+                match target(0, dy * distance) {
+                    Some(None) => count += 1,
+                    Some(Some(_)) if rules.step_over_rule => {}
+                    _ => break,
+                }
+            }
+            for dx in [-1, 1] {
+                if matches!(target(dx, dy), Some(Some(other)) if other.get_color() != color) {
+                    count += 1;
+                }
+            }
+        }
+        return count;
+    }
+
+    let mut rays = Vec::new();
+    let reach = rules.max_distance.unwrap_or(TILE_NUMBER as i32);
+    if movement.contains(MovementsRules::HORIZONTAL_MOVMENT) {
+        rays.extend([((1, 0), reach), ((-1, 0), reach)]);
+    }
+    if movement.contains(MovementsRules::VERTICAL_MOVMENT) {
+        rays.extend([((0, 1), reach), ((0, -1), reach)]);
+    }
+    // diagonal moves have to stay below the distance, see `is_move_legal`
+    if movement.contains(MovementsRules::DIAGONAL_MOVMENT) {
+        let reach = rules.max_distance.map_or(reach, |distance| distance - 1);
+        rays.extend([(1, 1), (1, -1), (-1, 1), (-1, -1)].map(|step| (step, reach)));
+    }
+    for ((dx, dy), reach) in rays {
+        for distance in 1..=reach {
+            let Some(square) = target(dx * distance, dy * distance) else {
+                break;
+            };
+            match square {
+                None => count += 1,
+                Some(other) => {
+                    if other.get_color() != piece.get_color() {
+                        count += 1;
+                    }
+                    if !rules.step_over_rule {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    if movement.contains(MovementsRules::SHIFT_STEP_MOVMENT) {
+        for dx in -4..=4 {
+            for dy in -4..=4 {
+                let distance = dx * dx + dy * dy;
+                if distance == 0 || distance % 5 != 0 || distance > reach {
+                    continue;
+                }
+                match target(dx, dy) {
+                    Some(None) => count += 1,
+                    Some(Some(other)) if other.get_color() != piece.get_color() => count += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    count
+}
+
+// Calls `feature` with the index of every parameter the board uses and how often, positive for
+// white and negative for black. The evaluation is the sum of the parameters times these counts
+pub fn visit_features(board: &BoardRecource, mut feature: impl FnMut(usize, i32)) {
+    // the rows of the pawns on each file, by color
+    let mut pawns = [[0u16; TILE_NUMBER]; 2];
+    for (square, piece) in board.grid.iter().enumerate() {
+        if let Some(piece) = piece.filter(|piece| piece.get_type() == PieceTypes::Pawn) {
+            let (x, y) = to_cord_index(square);
+            pawns[piece.get_color() as usize][x] |= 1 << y;
+        }
+    }
+
+    for (square, piece) in board.grid.iter().enumerate() {
+        let Some(piece) = piece else {
+            continue;
+        };
+        let color = piece.get_color();
+        let sign = if color { 1 } else { -1 };
+        let index = piece.get_type() as usize;
+        feature(MATERIAL + index, sign);
+        feature(
+            PSQT + index * SQUARES + relative_square(square, color),
+            sign,
+        );
+        let reachable = mobility(board, square, piece);
+        if reachable > 0 {
+            feature(MOBILITY + index, sign * reachable);
+        }
+
+        let (x, y) = to_cord_index(square);
+        match piece.get_type() {
+            PieceTypes::King => {
+                let (mut shelter, mut attackers) = (0, 0);
+                for (other_square, other) in board.grid.iter().enumerate() {
+                    let Some(other) = other else {
+                        continue;
+                    };
+                    let (ox, oy) = to_cord_index(other_square);
+                    let distance = x.abs_diff(ox).max(y.abs_diff(oy));
+                    if other.get_color() == color && distance == 1 {
+                        shelter += 1;
+                    } else if other.get_color() != color
+                        && distance <= 2
+                        && other.get_type() != PieceTypes::Pawn
+                    {
+                        attackers += 1;
+                    }
+                }
+                feature(KING_SHELTER, sign * shelter);
+                feature(KING_ATTACKERS, sign * attackers);
+            }
+            PieceTypes::Pawn => {
+                let own = &pawns[color as usize];
+                let enemy = &pawns[!color as usize];
+                let neighbours = [x.wrapping_sub(1), x + 1]
+                    .into_iter()
+                    .filter(|&file| file < TILE_NUMBER);
+                // the next pawn on the file counts the doubled pawn once
+                let ahead_mask = if color {
+                    (1u16 << y) - 1
+                } else {
+                    !((1u32 << (y + 1)) - 1) as u16
+                };
+                if own[x] & ahead_mask != 0 {
+                    feature(DOUBLED_PAWN, sign);
+                }
+                if neighbours.clone().all(|file| own[file] == 0) {
+                    feature(ISOLATED_PAWN, sign);
+                }
+                let blocked = enemy[x] & ahead_mask != 0
+                    || neighbours
+                        .into_iter()
+                        .any(|file| enemy[file] & ahead_mask != 0);
+                if !blocked {
+                    feature(PASSED_PAWN + advancement(y, color), sign);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// the evaluation of the board in centipawns, from the point of view of the side to move
+pub fn evaluate(board: &BoardRecource, params: &EvalParams) -> i32 {
+    let mut white = 0;
+    visit_features(board, |index, count| white += params.values[index] * count);
+    if board.turn {
+        white
+    } else {
+        -white
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a group name followed by `count` values
+    fn values(text: &str, count: usize) -> String {
+        format!("{} {}", text, vec!["3"; count].join(" "))
+    }
+
+    #[test]
+    fn params_round_trip() {
+        let default = EvalParams::default();
+        assert_eq!(EvalParams::parse(&default.to_text()).unwrap(), default);
+
+        let values = (0..PARAM_COUNT as i32)
+            .map(|i| i * 37 % 301 - 150)
+            .collect();
+        let params = EvalParams::from_values(values).unwrap();
+        assert_eq!(EvalParams::parse(&params.to_text()).unwrap(), params);
+    }
+
+    #[test]
+    fn groups_need_all_their_values() {
+        for (name, _, len) in groups() {
+            assert!(EvalParams::parse(&values(&name, len)).is_ok(), "{}", name);
+            assert!(
+                EvalParams::parse(&values(&name, len - 1)).is_err(),
+                "{}",
+                name
+            );
+            assert!(
+                EvalParams::parse(&values(&name, len + 1)).is_err(),
+                "{}",
+                name
+            );
+        }
+        // the missing values aren't taken from the next group
+        let (first, _, _) = &groups()[0];
+        let (second, _, len) = &groups()[1];
+        let text = format!("{}\n{}", first, values(second, *len));
+        assert!(EvalParams::parse(&text).is_err());
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        assert!(EvalParams::parse("bishop_pair 30").is_err());
+        assert!(EvalParams::parse("12 king_shelter 6").is_err());
+        // a file with only some groups keeps the built-in values of the others
+        let params = EvalParams::parse("# tuned\nking_shelter 9\n").unwrap();
+        assert_eq!(params.values()[KING_SHELTER], 9);
+        assert_eq!(params.values()[DOUBLED_PAWN], -12);
+    }
+}
@@ -0,0 +1,535 @@
+use std::{
+    fmt, io,
+    path::PathBuf,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
+
+use crate::logic::board::{parse_move, BoardRecource, GameResult};
+
+use super::{
+    adapter::ExternalEngine,
+    eval::EvalParams,
+    mcts::mcts,
+    player::AiStyle,
+    search::{search, SearchLimits},
+    tt::TranspositionTable,
+    uci::GoParams,
+};
+
+// Games between two AI configurations, so changes to the AI can be measured against the version
+// before them. The match runner binary plays them and reports the score with the statistics in
+// here
+
+// how a player of a match finds its moves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerConfig {
+    // the AI of this build, optionally held to a depth or a number of nodes, which MCTS counts as
+    // playouts, and with the evaluation of a parameter file
+    BuiltIn {
+        style: AiStyle,
+        depth: Option<u32>,
+        nodes: Option<u64>,
+        threads: usize,
+        eval: Option<PathBuf>,
+    },
+    // any UCI engine that supports the variant, e.g. the engine binary of an older build
+    External(PathBuf),
+}
+
+impl PlayerConfig {
+    // `builtin`, `builtin:depth=4`, `builtin:nodes=20000,threads=4,eval=tuned.params`,
+    // `builtin:style=mcts` or the path of an engine
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let Some(rest) = spec.strip_prefix("builtin") else {
+            return Ok(PlayerConfig::External(PathBuf::from(spec)));
+        };
+        let (mut depth, mut nodes, mut threads, mut eval) = (None, None, 1, None);
+        let mut style = AiStyle::default();
+        let options = match rest.strip_prefix(':') {
+            Some(options) => options,
+            None if rest.is_empty() => "",
+            None => return Ok(PlayerConfig::External(PathBuf::from(spec))),
+        };
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let invalid = || format!("invalid option of {}: {}", spec, option);
+            let (name, value) = option.split_once('=').ok_or_else(invalid)?;
+            match name {
+                "depth" => depth = Some(value.parse().map_err(|_| invalid())?),
+                "nodes" => nodes = Some(value.parse().map_err(|_| invalid())?),
+                "threads" => threads = value.parse().map_err(|_| invalid())?,
+                "eval" => eval = Some(PathBuf::from(value)),
+                "style" => style = AiStyle::parse(value).ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(PlayerConfig::BuiltIn {
+            style,
+            depth,
+            nodes,
+            threads,
+            eval,
+        })
+    }
+
+    pub fn start(&self) -> io::Result<Player> {
+        Ok(match self {
+            PlayerConfig::BuiltIn {
+                style,
+                depth,
+                nodes,
+                threads,
+                eval,
+            } => Player::BuiltIn {
+                style: *style,
+                depth: *depth,
+                nodes: *nodes,
+                threads: *threads,
+                tt: TranspositionTable::default(),
+                eval: match eval {
+                    Some(path) => EvalParams::load(path)?,
+                    None => EvalParams::default(),
+                },
+            },
+            PlayerConfig::External(path) => Player::External(ExternalEngine::start(path)?),
+        })
+    }
+}
+
+impl fmt::Display for PlayerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerConfig::BuiltIn {
+                style,
+                depth,
+                nodes,
+                threads,
+                eval,
+            } => {
+                write!(f, "builtin")?;
+                let mut options = Vec::new();
+                if *style != AiStyle::default() {
+                    options.push(format!("style={}", style.name()));
+                }
+                if let Some(depth) = depth {
+                    options.push(format!("depth={}", depth));
+                }
+                if let Some(nodes) = nodes {
+                    options.push(format!("nodes={}", nodes));
+                }
+                if *threads > 1 {
+                    options.push(format!("threads={}", threads));
+                }
+                if let Some(eval) = eval {
+                    options.push(format!("eval={}", eval.display()));
+                }
+                if !options.is_empty() {
+                    write!(f, ":{}", options.join(","))?;
+                }
+                Ok(())
+            }
+            PlayerConfig::External(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// A started player, engines keep running from one game to the next
+pub enum Player {
+    BuiltIn {
+        style: AiStyle,
+        depth: Option<u32>,
+        nodes: Option<u64>,
+        threads: usize,
+        tt: TranspositionTable,
+        eval: EvalParams,
+    },
+    External(ExternalEngine),
+}
+
+impl Player {
+    pub fn new_game(&mut self) -> io::Result<()> {
+        match self {
+            Player::BuiltIn { tt, .. } => {
+                tt.clear();
+                Ok(())
+            }
+            Player::External(engine) => engine.new_game(),
+        }
+    }
+
+    // the move isn't checked against the rules here
+    pub fn choose_move(
+        &mut self,
+        board: &BoardRecource,
+        params: &GoParams,
+    ) -> io::Result<(usize, usize)> {
+        match self {
+            Player::BuiltIn {
+                style,
+                depth,
+                nodes,
+                threads,
+                tt,
+                eval,
+            } => {
+                let limits = params.limits(board.turn);
+                let limits = SearchLimits {
+                    depth: depth.or(limits.depth),
+                    nodes: nodes.or(limits.nodes),
+                    ..limits
+                };
+                let stop = AtomicBool::new(false);
+                let best = match style {
+                    AiStyle::AlphaBeta => search(board, limits, tt, eval, *threads, &stop, |_| {}),
+                    AiStyle::Mcts(playout) => mcts(board, limits, *playout, &stop, |_| {}),
+                };
+                best.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "no legal moves to search")
+                })
+            }
+            Player::External(engine) => engine.go(board, params),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    // both players get `base` for the game and `increment` after every move, running out of
+    // time loses
+    Clock { base: Duration, increment: Duration },
+    // a fixed time for every move
+    MoveTime(Duration),
+}
+
+impl TimeControl {
+    // seconds for the game and the increment, e.g. `10+0.1` or just `60`
+    pub fn parse_clock(text: &str) -> Result<Self, String> {
+        let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+        let seconds = |value: &str| {
+            value
+                .parse()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or_else(|| format!("invalid time control: {}", text))
+        };
+        Ok(TimeControl::Clock {
+            base: seconds(base)?,
+            increment: seconds(increment)?,
+        })
+    }
+
+    fn go_params(&self, clocks: [Duration; 2]) -> GoParams {
+        match *self {
+            TimeControl::Clock { increment, .. } => GoParams {
+                wtime: Some(clocks[1]),
+                btime: Some(clocks[0]),
+                winc: Some(increment),
+                binc: Some(increment),
+                ..GoParams::default()
+            },
+            TimeControl::MoveTime(time) => GoParams {
+                movetime: Some(time),
+                ..GoParams::default()
+            },
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeControl::Clock { base, increment } => {
+                write!(f, "{}+{}", base.as_secs_f64(), increment.as_secs_f64())
+            }
+            TimeControl::MoveTime(time) => write!(f, "{} ms per move", time.as_millis()),
+        }
+    }
+}
+
+// One position per line, either as FEN or as the moves played from the start position, e.g.
+// `e15e13 e2e4`. Empty lines and lines starting with `#` are skipped
+pub fn parse_openings(text: &str) -> Result<Vec<BoardRecource>, String> {
+    let mut openings = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let opening = if line.contains('/') {
+            BoardRecource::from_fen(line)
+        } else {
+            let mut board = BoardRecource::new_game();
+            line.split_whitespace()
+                .try_for_each(|word| {
+                    parse_move(word)
+                        .and_then(|(src, dst)| board.make_move(src, dst))
+                        .map(|_| ())
+                        .ok_or_else(|| format!("illegal move {}", word))
+                })
+                .map(|_| board)
+        };
+        openings.push(opening.map_err(|err| format!("line {}: {}", number + 1, err))?);
+    }
+    Ok(openings)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameOutcome {
+    pub result: GameResult,
+    pub reason: String,
+    pub plies: usize,
+}
+
+// Plays one game from `opening`, a game that reaches `max_plies` counts as a draw. A player that
+// fails to answer, plays an illegal move or runs out of time loses
+pub fn play_game(
+    white: &mut Player,
+    black: &mut Player,
+    opening: &BoardRecource,
+    time_control: TimeControl,
+    max_plies: usize,
+) -> GameOutcome {
+    let mut board = opening.clone();
+    // indexed by color, like `turn`
+    let mut clocks = match time_control {
+        TimeControl::Clock { base, .. } => [base; 2],
+        TimeControl::MoveTime(_) => [Duration::ZERO; 2],
+    };
+    for plies in 0.. {
+        let outcome = |result, reason: String| GameOutcome {
+            result,
+            reason,
+            plies,
+        };
+        if let Some((result, reason)) = board.game_status() {
+            return outcome(result, reason.name().to_string());
+        }
+        if plies >= max_plies {
+            return outcome(GameResult::Draw, "move limit".to_string());
+        }
+        let turn = board.turn;
+        let lost = GameResult::Win(!turn);
+        let player = if turn { &mut *white } else { &mut *black };
+
+        let start = Instant::now();
+        let chosen = player.choose_move(&board, &time_control.go_params(clocks));
+        let elapsed = start.elapsed();
+        let (src, dst) = match chosen {
+            Ok(mv) => mv,
+            Err(err) => return outcome(lost, err.to_string()),
+        };
+        if let TimeControl::Clock { increment, .. } = time_control {
+            let clock = &mut clocks[turn as usize];
+            if elapsed > *clock {
+                return outcome(lost, "timeout".to_string());
+            }
+            *clock = *clock - elapsed + increment;
+        }
+        if board.make_move(src, dst).is_none() {
+            return outcome(lost, "illegal move".to_string());
+        }
+    }
+    unreachable!("the game ends at the move limit")
+}
+
+// how likely a player scores against another one that is `elo` points weaker
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+// The results of a match from the point of view of its first player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn add(&mut self, result: GameResult, first_is_white: bool) {
+        match result {
+            GameResult::Win(color) if color == first_is_white => self.wins += 1,
+            GameResult::Win(_) => self.losses += 1,
+            GameResult::Draw => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // between 0 and 1, a draw is worth half a win
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    // of the result of a single game
+    fn variance(&self) -> f64 {
+        let games = self.games().max(1) as f64;
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+
+    // The Elo difference between the players with the margin of its 95% confidence interval.
+    // None while one player won every game, the difference can't be told then
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score();
+        if self.games() == 0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let bound = |score: f64| elo_from_score(score.clamp(1e-6, 1.0 - 1e-6));
+        let low = bound(score - 1.96 * deviation);
+        let high = bound(score + 1.96 * deviation);
+        Some((elo_from_score(score), (high - low) / 2.0))
+    }
+
+    // the log-likelihood ratio of the hypotheses of `sprt`, in the normal approximation of the
+    // game results
+    pub fn llr(&self, sprt: &Sprt) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0 || variance <= 0.0 {
+            return 0.0;
+        }
+        let score0 = expected_score(sprt.elo0);
+        let score1 = expected_score(sprt.elo1);
+        self.games() as f64 * (score1 - score0) * (2.0 * self.score() - score0 - score1)
+            / (2.0 * variance)
+    }
+}
+
+impl fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{} ={} -{}", self.wins, self.draws, self.losses)
+    }
+}
+
+// A sequential probability ratio test of whether the first player is `elo1` stronger (H1)
+// rather than just `elo0` (H0). `alpha` and `beta` are the chances of accepting the wrong one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    // the log-likelihood ratios at which H0 and H1 are accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    // Some(true) once H1 is accepted, Some(false) once H0 is, None while more games are needed
+    pub fn verdict(&self, llr: f64) -> Option<bool> {
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(true)
+        } else if llr <= lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn elo_of_known_scores() {
+        let even = MatchScore {
+            wins: 10,
+            draws: 5,
+            losses: 10,
+        };
+        let (elo, margin) = even.elo().unwrap();
+        assert!(close(elo, 0.0, 1e-9));
+        assert!(margin > 0.0);
+
+        let three_quarters = MatchScore {
+            wins: 15,
+            draws: 0,
+            losses: 5,
+        };
+        let (elo, margin) = three_quarters.elo().unwrap();
+        assert!(close(elo, 190.85, 0.01));
+        assert!(close(margin, 217.63, 0.01));
+
+        // the other player lost just as much
+        let (elo, _) = MatchScore {
+            wins: 5,
+            draws: 0,
+            losses: 15,
+        }
+        .elo()
+        .unwrap();
+        assert!(close(elo, -190.85, 0.01));
+    }
+
+    #[test]
+    fn no_elo_without_both_results() {
+        assert_eq!(MatchScore::default().elo(), None);
+        let all_wins = MatchScore {
+            wins: 8,
+            draws: 0,
+            losses: 0,
+        };
+        assert_eq!(all_wins.elo(), None);
+    }
+
+    #[test]
+    fn default_sprt_bounds() {
+        let (lower, upper) = Sprt::default().bounds();
+        assert!(close(lower, -2.944, 0.001));
+        assert!(close(upper, 2.944, 0.001));
+        assert_eq!(Sprt::default().verdict(3.0), Some(true));
+        assert_eq!(Sprt::default().verdict(-3.0), Some(false));
+        assert_eq!(Sprt::default().verdict(0.0), None);
+    }
+
+    #[test]
+    fn llr_of_known_scores() {
+        let sprt = Sprt::default();
+        assert_eq!(MatchScore::default().llr(&sprt), 0.0);
+        let ahead = MatchScore {
+            wins: 60,
+            draws: 0,
+            losses: 40,
+        };
+        assert!(close(ahead.llr(&sprt), 0.2890, 0.0001));
+        // an even score speaks for H0, the first player isn't stronger
+        let even = MatchScore {
+            wins: 50,
+            draws: 0,
+            losses: 50,
+        };
+        assert!(even.llr(&sprt) < 0.0);
+    }
+}
@@ -0,0 +1,315 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::logic::{board::BoardRecource, piece::PieceTypes};
+
+use super::{
+    eval::piece_value,
+    search::{Score, SearchInfo, SearchLimits, MATE},
+};
+
+// Monte Carlo Tree Search: instead of evaluating positions it plays many games out from them with
+// random moves and keeps to the moves that won the most of those playouts. It needs nothing but
+// the rules, so it plays new pieces and variants that the evaluation knows nothing about. The
+// tree grows towards the moves that did well so far, picked by UCT
+
+// how much UCT tries the moves that were played less, the usual square root of two
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+// a playout that didn't end by then is a draw, guided playouts go by the material instead
+const MAX_PLAYOUT_PLIES: usize = 200;
+// the tree stops growing here, so a long search can't use up the memory
+const MAX_NODES: usize = 1_000_000;
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+// how the moves of a playout are picked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Playout {
+    // every move is as likely
+    Random,
+    // captures are likelier the more the captured piece is worth, and playouts that didn't end go
+    // to the side with more material
+    #[default]
+    Guided,
+}
+
+// xorshift, plenty for picking moves
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // a state of zero would stay zero
+        Rng(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+struct Node {
+    // the move from the parent to here, the root has none
+    mv: Option<(usize, usize)>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // the legal moves that don't have a child yet
+    untried: Vec<(usize, usize)>,
+    // the end of the game if the side to move has no legal moves, for the side that played `mv`
+    terminal: Option<f64>,
+    visits: u32,
+    // of the side that played `mv`, a won playout counts 1 and a draw a half
+    wins: f64,
+}
+
+impl Node {
+    fn new(mv: Option<(usize, usize)>, parent: Option<usize>, board: &BoardRecource) -> Self {
+        let untried = board.legal_moves();
+        let terminal = untried.is_empty().then(|| {
+            if board.is_in_check(board.turn) {
+                1.0
+            } else {
+                0.5
+            }
+        });
+        Node {
+            mv,
+            parent,
+            children: Vec::new(),
+            untried,
+            terminal,
+            visits: 0,
+            wins: 0.0,
+        }
+    }
+
+    fn win_rate(&self) -> f64 {
+        self.wins / self.visits.max(1) as f64
+    }
+}
+
+// the winning chance of white by the material, for playouts that didn't end
+fn material_chance(board: &BoardRecource) -> f64 {
+    let material: i32 = board
+        .grid
+        .iter()
+        .flatten()
+        .map(|piece| {
+            let value = piece_value(piece.get_type());
+            if piece.get_color() {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum();
+    1.0 / (1.0 + 10f64.powf(-material as f64 / 400.0))
+}
+
+fn guided_move(board: &BoardRecource, moves: &[(usize, usize)], rng: &mut Rng) -> (usize, usize) {
+    let weights: Vec<u64> = moves
+        .iter()
+        .map(|&(_, dst)| match board.grid[dst] {
+            Some(target) => 1 + piece_value(target.get_type()) as u64 / 50,
+            None => 1,
+        })
+        .collect();
+    let mut pick = rng.next_u64() % weights.iter().sum::<u64>();
+    for (&mv, weight) in moves.iter().zip(weights) {
+        if pick < weight {
+            return mv;
+        }
+        pick -= weight;
+    }
+    moves[moves.len() - 1]
+}
+
+// Plays the game out from `board`, the result is 1 if white wins, 0 if black wins and a half for
+// a draw. The moves may leave the own king in check, the game ends once a king is taken
+fn playout(board: &mut BoardRecource, style: Playout, rng: &mut Rng) -> f64 {
+    for _ in 0..MAX_PLAYOUT_PLIES {
+        let moves = board.pseudo_legal_moves();
+        if moves.is_empty() {
+            return 0.5;
+        }
+        let takes_king = moves.iter().any(|&(_, dst)| {
+            board.grid[dst].is_some_and(|piece| piece.get_type() == PieceTypes::King)
+        });
+        if takes_king {
+            return if board.turn { 1.0 } else { 0.0 };
+        }
+        let (src, dst) = match style {
+            Playout::Random => moves[rng.below(moves.len())],
+            Playout::Guided => guided_move(board, &moves, rng),
+        };
+        board.make(src, dst);
+    }
+    match style {
+        Playout::Random => 0.5,
+        Playout::Guided => material_chance(board),
+    }
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    // the child with the best UCT value, every child has been visited once
+    fn select_child(&self, node: usize) -> usize {
+        let parent_visits = (self.nodes[node].visits.max(1) as f64).ln();
+        let uct = |child: usize| {
+            let child = &self.nodes[child];
+            let visits = child.visits.max(1) as f64;
+            child.win_rate() + EXPLORATION * (parent_visits / visits).sqrt()
+        };
+        self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+            .expect("only nodes with children are selected from")
+    }
+
+    fn most_visited_child(&self, node: usize) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|&child| self.nodes[child].visits)
+    }
+
+    // One iteration: down the tree by UCT, a new node for an untried move and a playout from it.
+    // Its result counts for every node on the way
+    fn iterate(&mut self, root: &BoardRecource, style: Playout, rng: &mut Rng) {
+        let mut board = root.clone();
+        let mut node = 0;
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select_child(node);
+            let (src, dst) = self.nodes[node].mv.expect("children have a move");
+            board.make(src, dst);
+        }
+        if self.nodes[node].terminal.is_none() && self.nodes.len() < MAX_NODES {
+            let untried = &mut self.nodes[node].untried;
+            let (src, dst) = untried.swap_remove(rng.below(untried.len()));
+            board.make(src, dst);
+            let child = Node::new(Some((src, dst)), Some(node), &board);
+            self.nodes.push(child);
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+            node = child;
+        }
+        // for the side that played the move to `node`, the side not to move there
+        let mut result = match self.nodes[node].terminal {
+            Some(result) => result,
+            None => {
+                let mover = !board.turn;
+                let white = playout(&mut board, style, rng);
+                if mover {
+                    white
+                } else {
+                    1.0 - white
+                }
+            }
+        };
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.wins += result;
+            result = 1.0 - result;
+            current = node.parent;
+        }
+    }
+
+    // the most visited moves from the root on
+    fn principal_variation(&self) -> Vec<(usize, usize)> {
+        let mut pv = Vec::new();
+        let mut node = 0;
+        while let Some(child) = self.most_visited_child(node) {
+            if self.nodes[child].visits == 0 {
+                break;
+            }
+            pv.extend(self.nodes[child].mv);
+            node = child;
+        }
+        pv
+    }
+
+    fn info(&self, start: Instant) -> Option<SearchInfo> {
+        let best = &self.nodes[self.most_visited_child(0)?];
+        let score = if best.terminal == Some(1.0) {
+            Score::Mate(1)
+        } else {
+            // the winning chance as centipawns, like the evaluation counts them
+            let chance = best.win_rate().clamp(0.001, 0.999);
+            let centipawns = 400.0 * (chance / (1.0 - chance)).log10();
+            Score::Centipawns((centipawns.round() as i32).clamp(-MATE / 2, MATE / 2))
+        };
+        let pv = self.principal_variation();
+        Some(SearchInfo {
+            line: 0,
+            depth: pv.len() as u32,
+            score,
+            nodes: self.nodes[0].visits as u64,
+            time: start.elapsed(),
+            pv,
+        })
+    }
+}
+
+// Searches with MCTS until a limit is hit or `stop` is set, the node limit counts playouts and
+// the depth limit and the lines aren't used. `report` is called every second and at the end.
+// Returns the move that was played out the most, None if the side to move has no legal moves. The
+// same position and limits always give the same playouts
+pub fn mcts(
+    board: &BoardRecource,
+    limits: SearchLimits,
+    style: Playout,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&SearchInfo),
+) -> Option<(usize, usize)> {
+    let start = Instant::now();
+    let mut tree = Tree {
+        nodes: vec![Node::new(None, None, board)],
+    };
+    // nothing to think about
+    if tree.nodes[0].untried.len() <= 1 {
+        return tree.nodes[0].untried.first().copied();
+    }
+    let mut rng = Rng::new(board.hash);
+    let mut next_report = REPORT_INTERVAL;
+    loop {
+        let playouts = tree.nodes[0].visits as u64;
+        let done = stop.load(Ordering::Relaxed)
+            || limits.nodes.is_some_and(|nodes| playouts >= nodes)
+            || limits.time.is_some_and(|time| start.elapsed() >= time)
+            || tree.nodes.len() >= MAX_NODES;
+        if done {
+            break;
+        }
+        tree.iterate(board, style, &mut rng);
+        if start.elapsed() >= next_report {
+            next_report += REPORT_INTERVAL;
+            report(&tree.info(start).expect("the root has been played out"));
+        }
+    }
+    let info = tree.info(start);
+    if let Some(info) = &info {
+        report(info);
+    }
+    match info {
+        Some(info) => info.pv.first().copied(),
+        // stopped before the first playout
+        None => tree.nodes[0].untried.first().copied(),
+    }
+}
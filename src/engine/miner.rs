@@ -0,0 +1,211 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::logic::{
+    board::BoardRecource,
+    puzzle::{piece_theme, Puzzle},
+};
+
+use super::{
+    eval::EvalParams,
+    search::{search, Score, SearchInfo, SearchLimits},
+    tt::TranspositionTable,
+};
+
+// Finds puzzles in recorded games: positions right after a mistake where the side to move has a
+// forced mate or a single move that wins a lot. Every position is searched for its two best
+// moves, a puzzle needs a best move that the second best doesn't come close to
+
+// longer mates are too hard to see without an engine
+const MAX_MATE_MOVES: i32 = 3;
+// how much the move of an advantage puzzle has to win, in centipawns
+const WINNING_SCORE: i32 = 300;
+// how far the second best move has to be behind it
+const UNIQUE_MARGIN: i32 = 200;
+// a player who was doing at least this well made a mistake if the opponent can win after their
+// move
+const EVEN_SCORE: i32 = -100;
+// mates are worth more than any material, the sooner the better
+const MATE_VALUE: i32 = 100_000;
+
+fn value(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(value) => value,
+        Score::Mate(moves) if moves > 0 => MATE_VALUE - moves,
+        Score::Mate(moves) => -MATE_VALUE - moves,
+    }
+}
+
+fn is_mate(score: Score) -> bool {
+    matches!(score, Score::Mate(moves) if moves > 0)
+}
+
+// the deepest report of the best line and of the second best one, if there is a second move
+fn best_lines(
+    board: &BoardRecource,
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+) -> Vec<SearchInfo> {
+    let limits = SearchLimits {
+        lines: Some(2),
+        ..limits
+    };
+    let mut lines = Vec::new();
+    search(board, limits, tt, eval, threads, stop, |info| {
+        // the best line of a new depth comes first, the second one of the depth before doesn't
+        // belong to it
+        lines.truncate(info.line);
+        lines.push(info.clone());
+    });
+    lines
+}
+
+// every move of the solver but the last has to be the only one that mates, the last one may be
+// any mate
+#[allow(clippy::too_many_arguments)]
+fn only_way_to_mate(
+    board: &BoardRecource,
+    first_lines: &[SearchInfo],
+    solution: &[(usize, usize)],
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+) -> bool {
+    let mut board = board.clone();
+    for (ply, &(src, dst)) in solution.iter().enumerate() {
+        if ply + 1 == solution.len() {
+            break;
+        }
+        if ply % 2 == 0 {
+            let second = if ply == 0 {
+                first_lines.get(1).map(|line| line.score)
+            } else {
+                best_lines(&board, limits, tt, eval, threads, stop)
+                    .get(1)
+                    .map(|line| line.score)
+            };
+            if second.is_some_and(is_mate) {
+                return false;
+            }
+        }
+        if board.make_move(src, dst).is_none() {
+            return false;
+        }
+    }
+    true
+}
+
+// A rough guess until players tried the puzzle: longer mates are harder and so are quiet first
+// moves, they are easier to overlook than captures and checks. Every other check is one more move
+// that looks like it could work
+fn guess_rating(board: &BoardRecource, solution: &[(usize, usize)], mate: Option<i32>) -> u32 {
+    let mut rating = match mate {
+        Some(moves) => 600 + 400 * moves as u32,
+        None => 1200,
+    };
+    let gives_check = |(src, dst): (usize, usize)| {
+        let mut after = board.clone();
+        after.make_move(src, dst);
+        after.is_in_check(after.turn)
+    };
+    let first = solution[0];
+    if board.grid[first.1].is_none() && !gives_check(first) {
+        rating += 200;
+    }
+    let checks = board
+        .legal_moves()
+        .into_iter()
+        .filter(|&mv| mv != first && gives_check(mv))
+        .count();
+    rating + 25 * checks.min(8) as u32
+}
+
+#[allow(clippy::too_many_arguments)]
+fn puzzle_at(
+    board: &BoardRecource,
+    lines: &[SearchInfo],
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+) -> Option<Puzzle> {
+    let best = lines.first()?;
+    // a forced move isn't much of a puzzle
+    let second = lines.get(1)?;
+    let (solution, mate) = match best.score {
+        Score::Mate(moves) if (1..=MAX_MATE_MOVES).contains(&moves) => {
+            let plies = (moves * 2 - 1) as usize;
+            let solution = best.pv.get(..plies)?.to_vec();
+            if !only_way_to_mate(board, lines, &solution, limits, tt, eval, threads, stop) {
+                return None;
+            }
+            (solution, Some(moves))
+        }
+        Score::Centipawns(score)
+            if score >= WINNING_SCORE && score - value(second.score) >= UNIQUE_MARGIN =>
+        {
+            (vec![*best.pv.first()?], None)
+        }
+        _ => return None,
+    };
+
+    let (src, _) = solution[0];
+    let piece = board.grid[src]?;
+    let themes = vec![
+        mate.map_or("advantage".to_string(), |moves| format!("mateIn{}", moves)),
+        piece_theme(piece.get_type()).to_string(),
+    ];
+    let rating = guess_rating(board, &solution, mate);
+    // the hash keeps the id the same when the position is mined again from another game
+    let id = format!("{:016x}", board.hash);
+    Puzzle::new(&id, &board.to_fen(), solution, rating, themes).ok()
+}
+
+// Searches every position of the game with `limits` and returns the puzzles found in it. When
+// `stop` is set only the ones found until then are returned
+pub fn mine_game(
+    moves: &[(usize, usize)],
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+) -> Vec<Puzzle> {
+    let mut puzzles = Vec::new();
+    let mut board = BoardRecource::new_game();
+    // the score of the player who made the last move, from before it
+    let mut before_last_move = None;
+    for ply in 0..=moves.len() {
+        if board.game_status().is_some() {
+            break;
+        }
+        let lines = best_lines(&board, limits, tt, eval, threads, stop);
+        // a search that was stopped halfway can't be trusted
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let Some(best) = lines.first() else {
+            break;
+        };
+        let after_mistake = before_last_move.is_some_and(|score| score >= EVEN_SCORE);
+        if after_mistake {
+            if let Some(puzzle) = puzzle_at(&board, &lines, limits, tt, eval, threads, stop) {
+                puzzles.push(puzzle);
+            }
+        }
+        before_last_move = Some(value(best.score));
+
+        let Some(&(src, dst)) = moves.get(ply) else {
+            break;
+        };
+        if board.make_move(src, dst).is_none() {
+            break;
+        }
+    }
+    puzzles
+}
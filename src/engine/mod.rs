@@ -0,0 +1,15 @@
+// The computer player, kept apart from Bevy so it can run in the game, in the engine binary for
+// other GUIs and in tools that pit engines against each other
+pub mod adapter;
+pub mod analyzer;
+pub mod book;
+pub mod eval;
+pub mod matches;
+pub mod mcts;
+pub mod miner;
+pub mod player;
+pub mod review;
+pub mod search;
+pub mod tt;
+pub mod tuner;
+pub mod uci;
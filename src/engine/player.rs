@@ -0,0 +1,187 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::logic::board::{get_best_next_move, BoardRecource};
+
+use super::{
+    adapter::ExternalEngine,
+    book::{OpeningBook, Variety},
+    eval::EvalParams,
+    mcts::{mcts, Playout},
+    search::SearchLimits,
+    tt::TranspositionTable,
+};
+
+// how the built-in AI thinks about its moves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AiStyle {
+    // the alpha-beta search with the tuned evaluation
+    #[default]
+    AlphaBeta,
+    // needs nothing but the rules, for pieces and variants the evaluation wasn't tuned for
+    Mcts(Playout),
+}
+
+impl AiStyle {
+    pub const ALL: [AiStyle; 3] = [
+        AiStyle::AlphaBeta,
+        AiStyle::Mcts(Playout::Guided),
+        AiStyle::Mcts(Playout::Random),
+    ];
+
+    // as the UCI option and the match runner take it
+    pub fn name(self) -> &'static str {
+        match self {
+            AiStyle::AlphaBeta => "alphabeta",
+            AiStyle::Mcts(Playout::Guided) => "mcts",
+            AiStyle::Mcts(Playout::Random) => "mcts-random",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        AiStyle::ALL
+            .into_iter()
+            .find(|style| style.name().eq_ignore_ascii_case(name))
+    }
+}
+
+// How the built-in AI plays, engines have their own settings
+#[derive(Debug, Clone)]
+pub struct BuiltInAi {
+    pub style: AiStyle,
+    // MCTS always thinks on a single thread
+    pub threads: usize,
+    // the first moves come from here as long as the position is in it
+    pub book: Option<OpeningBook>,
+    pub variety: Variety,
+    // what the alpha-beta search thinks of a position
+    pub eval: EvalParams,
+}
+
+// What the computer decided, `engine_error` is set when the external engine failed and the
+// built-in AI took over from then on
+#[derive(Debug, Clone)]
+pub struct ComputerMove {
+    pub mv: Option<(usize, usize)>,
+    pub engine_error: Option<String>,
+}
+
+// Thinks about the moves of the computer on its own thread, with an external engine if one is
+// set up and with the built-in AI otherwise
+pub struct ComputerPlayer {
+    requests: Sender<BoardRecource>,
+    moves: Mutex<Receiver<ComputerMove>>,
+    thinking: bool,
+}
+
+impl ComputerPlayer {
+    pub fn start(engine_path: Option<PathBuf>, think_time: Duration, built_in: BuiltInAi) -> Self {
+        let (requests, to_think) = mpsc::channel();
+        let (sender, moves) = mpsc::channel();
+        thread::spawn(move || think(engine_path, think_time, built_in, to_think, sender));
+        ComputerPlayer {
+            requests,
+            moves: Mutex::new(moves),
+            thinking: false,
+        }
+    }
+
+    pub fn is_thinking(&self) -> bool {
+        self.thinking
+    }
+
+    pub fn request_move(&mut self, board: &BoardRecource) {
+        self.thinking = self.requests.send(board.clone()).is_ok();
+    }
+
+    // the move once the computer is done thinking
+    pub fn poll(&mut self) -> Option<ComputerMove> {
+        let computer_move = self.moves.lock().unwrap().try_recv().ok()?;
+        self.thinking = false;
+        Some(computer_move)
+    }
+}
+
+// the nanoseconds of the clock, random enough to pick a book move
+fn random_number() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos() as u64)
+}
+
+fn think(
+    engine_path: Option<PathBuf>,
+    think_time: Duration,
+    built_in: BuiltInAi,
+    requests: Receiver<BoardRecource>,
+    moves: Sender<ComputerMove>,
+) {
+    let mut engine_error = None;
+    let mut engine = engine_path.and_then(|path| match ExternalEngine::start(&path) {
+        Ok(engine) => Some(engine),
+        Err(err) => {
+            engine_error = Some(format!("{}: {}", path.display(), err));
+            None
+        }
+    });
+    let tt = TranspositionTable::default();
+    let built_in_move = |board: &BoardRecource| {
+        let book_move = built_in
+            .book
+            .as_ref()
+            .and_then(|book| book.pick(board, built_in.variety, random_number()));
+        if book_move.is_some() {
+            return book_move;
+        }
+        match built_in.style {
+            AiStyle::AlphaBeta => {
+                get_best_next_move(board, think_time, &tt, &built_in.eval, built_in.threads)
+            }
+            AiStyle::Mcts(playout) => {
+                let limits = SearchLimits {
+                    time: Some(think_time),
+                    ..Default::default()
+                };
+                mcts(board, limits, playout, &AtomicBool::new(false), |_| {})
+            }
+        }
+    };
+    // ends once the game is over and the player is dropped
+    for board in requests {
+        let from_engine = engine
+            .as_mut()
+            .map(|engine| engine.best_move(&board, think_time));
+        let mv = match from_engine {
+            Some(Ok((src, dst))) if board.clone().make_move(src, dst).is_some() => Some((src, dst)),
+            Some(result) => {
+                let name = engine
+                    .take()
+                    .map(|engine| engine.name.clone())
+                    .unwrap_or_default();
+                engine_error = Some(match result {
+                    Ok(_) => format!("{} played an illegal move", name),
+                    Err(err) => err.to_string(),
+                });
+                built_in_move(&board)
+            }
+            None => built_in_move(&board),
+        };
+        let computer_move = ComputerMove {
+            mv,
+            engine_error: engine_error.take(),
+        };
+        if moves.send(computer_move).is_err() {
+            break;
+        }
+    }
+}
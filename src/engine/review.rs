@@ -0,0 +1,288 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::logic::board::{BoardRecource, GameResult};
+
+use super::{
+    eval::EvalParams,
+    search::{search, Score, SearchLimits},
+    tt::TranspositionTable,
+};
+
+// Goes through a finished game with the AI and tells how much every move lost against the best
+// one and how accurately each player played
+
+// scores are capped, a forced mate counts as this much, so a single blunder into a mate doesn't
+// decide the accuracy of a whole game
+const MAX_SCORE: i32 = 2000;
+
+// how good a move was by how much of the evaluation it lost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoveClass {
+    // the move the AI would have played
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+    pub const ALL: [MoveClass; 5] = [
+        MoveClass::Best,
+        MoveClass::Good,
+        MoveClass::Inaccuracy,
+        MoveClass::Mistake,
+        MoveClass::Blunder,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MoveClass::Best => "best",
+            MoveClass::Good => "good",
+            MoveClass::Inaccuracy => "inaccuracy",
+            MoveClass::Mistake => "mistake",
+            MoveClass::Blunder => "blunder",
+        }
+    }
+
+    // written after the move, the same way as in chess notation
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            MoveClass::Best | MoveClass::Good => "",
+            MoveClass::Inaccuracy => "?!",
+            MoveClass::Mistake => "?",
+            MoveClass::Blunder => "??",
+        }
+    }
+
+    // the loss is in centipawns
+    fn from_loss(loss: i32) -> Self {
+        match loss {
+            ..=0 => MoveClass::Best,
+            1..=50 => MoveClass::Good,
+            51..=100 => MoveClass::Inaccuracy,
+            101..=300 => MoveClass::Mistake,
+            _ => MoveClass::Blunder,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveReview {
+    pub mv: (usize, usize),
+    // what the AI would have played instead, None if it was this move
+    pub best: Option<(usize, usize)>,
+    // the score before the move and after it, both for the player who made it
+    pub before: i32,
+    pub after: i32,
+    pub class: MoveClass,
+    // from 0 to 100, how much of the chance to win was kept
+    pub accuracy: f32,
+}
+
+impl MoveReview {
+    pub fn loss(&self) -> i32 {
+        (self.before - self.after).max(0)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameReview {
+    // by ply, white's moves first
+    pub moves: Vec<MoveReview>,
+}
+
+impl GameReview {
+    fn player_moves(&self, color: bool) -> impl Iterator<Item = &MoveReview> {
+        // the first move is white's
+        self.moves
+            .iter()
+            .enumerate()
+            .filter(move |(ply, _)| (ply % 2 == 0) == color)
+            .map(|(_, review)| review)
+    }
+
+    // the average accuracy of the player's moves, None before they moved
+    pub fn accuracy(&self, color: bool) -> Option<f32> {
+        let accuracies: Vec<f32> = self
+            .player_moves(color)
+            .map(|review| review.accuracy)
+            .collect();
+        if accuracies.is_empty() {
+            return None;
+        }
+        Some(accuracies.iter().sum::<f32>() / accuracies.len() as f32)
+    }
+
+    pub fn count(&self, color: bool, class: MoveClass) -> usize {
+        self.player_moves(color)
+            .filter(|review| review.class == class)
+            .count()
+    }
+}
+
+// the chance to win in percent for a score, the same curve as the accuracy of chess sites uses
+fn win_chance(score: i32) -> f32 {
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * score as f32).exp()) - 1.0)
+}
+
+fn accuracy(before: i32, after: i32) -> f32 {
+    let lost = (win_chance(before) - win_chance(after)).max(0.0);
+    (103.1668 * (-0.04354 * lost).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+fn capped(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(value) => value.clamp(-MAX_SCORE, MAX_SCORE),
+        Score::Mate(moves) if moves > 0 => MAX_SCORE,
+        Score::Mate(_) => -MAX_SCORE,
+    }
+}
+
+// the score for the side to move and the move the AI would play, None at the end of the game
+fn evaluate_position(
+    board: &BoardRecource,
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+) -> (i32, Option<(usize, usize)>) {
+    if let Some((result, _)) = board.game_status() {
+        let score = match result {
+            GameResult::Win(winner) if winner == board.turn => MAX_SCORE,
+            GameResult::Win(_) => -MAX_SCORE,
+            GameResult::Draw => 0,
+        };
+        return (score, None);
+    }
+    let mut score = 0;
+    let best = search(board, limits, tt, eval, threads, stop, |info| {
+        if info.line == 0 {
+            score = capped(info.score);
+        }
+    });
+    (score, best)
+}
+
+// Searches every position of the game with `limits`, `progress` is called with the number of
+// positions done. When `stop` is set only the moves reviewed until then are returned
+pub fn review_game(
+    moves: &[(usize, usize)],
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+    mut progress: impl FnMut(usize),
+) -> GameReview {
+    let mut board = BoardRecource::new_game();
+    let mut positions = vec![evaluate_position(&board, limits, tt, eval, threads, stop)];
+    progress(1);
+    for &(src, dst) in moves {
+        if stop.load(Ordering::Relaxed) || board.make_move(src, dst).is_none() {
+            break;
+        }
+        positions.push(evaluate_position(&board, limits, tt, eval, threads, stop));
+        progress(positions.len());
+    }
+    // a search that was stopped halfway has no score worth showing
+    if stop.load(Ordering::Relaxed) {
+        positions.pop();
+    }
+
+    let moves = positions
+        .windows(2)
+        .zip(moves)
+        .map(|(pair, &mv)| {
+            let [(before, best), (after, _)] = [pair[0], pair[1]];
+            // the score after the move is the opponent's
+            let after = -after;
+            let class = if best == Some(mv) {
+                MoveClass::Best
+            } else {
+                MoveClass::from_loss(before - after)
+            };
+            MoveReview {
+                mv,
+                best: best.filter(|&best| best != mv),
+                before,
+                after,
+                class,
+                accuracy: accuracy(before, after),
+            }
+        })
+        .collect();
+    GameReview { moves }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(moves: &[(usize, usize)]) -> GameReview {
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let tt = TranspositionTable::new(1);
+        let stop = AtomicBool::new(false);
+        review_game(moves, limits, &tt, &EvalParams::default(), 1, &stop, |_| {})
+    }
+
+    #[test]
+    fn classes_by_loss() {
+        for (loss, class) in [
+            (-20, MoveClass::Best),
+            (0, MoveClass::Best),
+            (1, MoveClass::Good),
+            (50, MoveClass::Good),
+            (51, MoveClass::Inaccuracy),
+            (100, MoveClass::Inaccuracy),
+            (101, MoveClass::Mistake),
+            (300, MoveClass::Mistake),
+            (301, MoveClass::Blunder),
+            (2 * MAX_SCORE, MoveClass::Blunder),
+        ] {
+            assert_eq!(MoveClass::from_loss(loss), class, "{}", loss);
+        }
+    }
+
+    #[test]
+    fn accuracy_is_a_percentage() {
+        let scores = [
+            i32::MIN,
+            -MAX_SCORE,
+            -300,
+            -1,
+            0,
+            1,
+            50,
+            300,
+            MAX_SCORE,
+            i32::MAX,
+        ];
+        for before in scores {
+            for after in scores {
+                let accuracy = accuracy(before, after);
+                assert!((0.0..=100.0).contains(&accuracy), "{} {}", before, after);
+            }
+        }
+        // keeping the score is about as accurate as it gets, losing a won game isn't
+        assert!(accuracy(120, 120) > 99.0);
+        assert!(accuracy(MAX_SCORE, -MAX_SCORE) < 1.0);
+        assert!(accuracy(0, -50) > accuracy(0, -300));
+    }
+
+    #[test]
+    fn the_best_move_is_best() {
+        let mv = BoardRecource::new_game().legal_moves()[0];
+        // what the AI would play at the start, whether it's this move or another one
+        let best = review(&[mv]).moves[0].best.unwrap_or(mv);
+
+        let reviewed = review(&[best]).moves;
+        assert_eq!(reviewed.len(), 1);
+        assert_eq!(reviewed[0].class, MoveClass::Best);
+        assert_eq!(reviewed[0].best, None);
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::logic::board::BoardRecource;
+
+use super::{
+    eval::{evaluate, piece_value, EvalParams},
+    tt::{Bound, TranspositionTable, TtEntry},
+};
+
+// A score above this is a forced mate, the distance to it is what's left to `MATE`
+pub const MATE: i32 = 1_000_000;
+const INFINITY: i32 = MATE + 1;
+pub const MAX_DEPTH: u32 = 64;
+pub const MAX_THREADS: usize = 64;
+pub const MAX_LINES: usize = 16;
+// how often the clock and the stop flag are looked at, in nodes
+const CHECK_INTERVAL: u64 = 256;
+
+// When the search has to give an answer, no limit means it searches until it's stopped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    // how many of the best moves get a line of their own, one if unset. Every line costs a
+    // search of its own, so this is for showing the alternatives and not for playing
+    pub lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    // in moves, negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    pub fn from_value(value: i32) -> Self {
+        if value.abs() < MATE - MAX_DEPTH as i32 {
+            return Score::Centipawns(value);
+        }
+        let plies = MATE - value.abs();
+        let moves = (plies + 1) / 2;
+        Score::Mate(if value > 0 { moves } else { -moves })
+    }
+}
+
+// What the search found so far, reported for every line after every finished depth
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchInfo {
+    // 0 for the best move, 1 for the second best and so on
+    pub line: usize,
+    pub depth: u32,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    // the best move first, followed by the answers both sides are expected to play
+    pub pv: Vec<(usize, usize)>,
+}
+
+impl SearchInfo {
+    pub fn nodes_per_second(&self) -> u64 {
+        let millis = self.time.as_millis().max(1) as u64;
+        self.nodes * 1000 / millis
+    }
+}
+
+// Mate scores count the plies from the root, the table stores them from the position itself so
+// they stay right when the position is reached on another path
+fn score_to_tt(score: i32, ply: u32) -> i32 {
+    if score >= MATE - MAX_DEPTH as i32 {
+        score + ply as i32
+    } else if score <= -(MATE - MAX_DEPTH as i32) {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: u32) -> i32 {
+    if score >= MATE - MAX_DEPTH as i32 {
+        score - ply as i32
+    } else if score <= -(MATE - MAX_DEPTH as i32) {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+struct Searcher<'a> {
+    limits: SearchLimits,
+    stop: &'a AtomicBool,
+    tt: &'a TranspositionTable,
+    eval: &'a EvalParams,
+    start: Instant,
+    // of this thread, they are added to `all_nodes` every `CHECK_INTERVAL` nodes
+    nodes: u64,
+    // of every thread of the search
+    all_nodes: &'a AtomicU64,
+    // the best move of the last depth, searched first at the root
+    root_hint: Option<(usize, usize)>,
+    // the moves of the lines that were already found at this depth, left out at the root
+    root_excluded: Vec<(usize, usize)>,
+    // set once a limit was hit, every score after that is thrown away
+    aborted: bool,
+    // the hashes of the positions on the way from the root to the current one
+    path: Vec<u64>,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(
+        limits: SearchLimits,
+        stop: &'a AtomicBool,
+        tt: &'a TranspositionTable,
+        eval: &'a EvalParams,
+        all_nodes: &'a AtomicU64,
+    ) -> Self {
+        Searcher {
+            limits,
+            stop,
+            tt,
+            eval,
+            start: Instant::now(),
+            nodes: 0,
+            all_nodes,
+            root_hint: None,
+            root_excluded: Vec::new(),
+            aborted: false,
+            path: Vec::new(),
+        }
+    }
+
+    fn should_abort(&mut self) -> bool {
+        if self.nodes % CHECK_INTERVAL == 0 {
+            let all_nodes =
+                self.all_nodes.fetch_add(CHECK_INTERVAL, Ordering::Relaxed) + CHECK_INTERVAL;
+            self.aborted = self.aborted
+                || self.stop.load(Ordering::Relaxed)
+                || self.limits.nodes.is_some_and(|nodes| all_nodes >= nodes)
+                || self
+                    .limits
+                    .time
+                    .is_some_and(|time| self.start.elapsed() >= time);
+        }
+        self.aborted
+    }
+
+    fn searched_nodes(&self) -> u64 {
+        self.all_nodes.load(Ordering::Relaxed) + self.nodes % CHECK_INTERVAL
+    }
+
+    // Searches deeper and deeper from `first_depth` on until a limit is hit, `report` is called
+    // after every finished depth. Returns the best move of the last finished depth
+    fn iterate(
+        &mut self,
+        board: &BoardRecource,
+        first_depth: u32,
+        mut report: impl FnMut(&SearchInfo),
+    ) -> Option<(usize, usize)> {
+        let mut root = board.clone();
+        let mut best = None;
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let lines = self.limits.lines.unwrap_or(1).clamp(1, MAX_LINES);
+        let mut pv = Vec::new();
+        'depths: for depth in first_depth.min(max_depth)..=max_depth {
+            self.root_excluded.clear();
+            let mut best_score = 0;
+            for line in 0..lines {
+                let score = self.negamax(&mut root, depth, 0, -INFINITY, INFINITY, &mut pv);
+                // a depth that wasn't finished may have missed the best move
+                if self.aborted {
+                    break 'depths;
+                }
+                // no moves left for this line, at the first one there are no legal moves at all
+                if pv.is_empty() {
+                    if line == 0 {
+                        break 'depths;
+                    }
+                    break;
+                }
+                if line == 0 {
+                    best = pv.first().copied();
+                    self.root_hint = best;
+                    best_score = score;
+                }
+                self.root_excluded.push(pv[0]);
+                report(&SearchInfo {
+                    line,
+                    depth,
+                    score: Score::from_value(score),
+                    nodes: self.searched_nodes(),
+                    time: self.start.elapsed(),
+                    pv: pv.clone(),
+                });
+            }
+            // a mate can't get any better, and the next depth would take longer than all the
+            // ones before it together
+            let half_time_used = self
+                .limits
+                .time
+                .is_some_and(|time| self.start.elapsed() * 2 >= time);
+            if best_score.abs() >= MATE - MAX_DEPTH as i32 || half_time_used {
+                break;
+            }
+        }
+        self.root_excluded.clear();
+        best
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut BoardRecource,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<(usize, usize)>,
+    ) -> i32 {
+        self.nodes += 1;
+        pv.clear();
+        // the root can't be given up, there would be no move at all
+        if self.should_abort() && ply > 0 {
+            return 0;
+        }
+        // a position that was on the way here already can be repeated forever
+        if self.path.contains(&board.hash) {
+            return 0;
+        }
+        if depth == 0 {
+            return evaluate(board, self.eval);
+        }
+        let entry = self.tt.probe(board.hash);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_tt(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                return score;
+            }
+        }
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if board.is_in_check(board.turn) {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+        if ply == 0 {
+            moves.retain(|mv| !self.root_excluded.contains(mv));
+            if moves.is_empty() {
+                return -INFINITY;
+            }
+        }
+        order_moves(board, &mut moves);
+        // the best move of the last time this position was searched goes first
+        let hint = if ply == 0 { self.root_hint } else { None };
+        if let Some(hint) = hint
+            .or(entry.and_then(|entry| entry.best_move))
+            .and_then(|hint| moves.iter().position(|&mv| mv == hint))
+        {
+            moves[..=hint].rotate_right(1);
+        }
+
+        let original_alpha = alpha;
+        let mut child_pv = Vec::new();
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        self.path.push(board.hash);
+        for (src, dst) in moves {
+            let undo = board.make(src, dst);
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            board.unmake(undo);
+            if self.aborted {
+                break;
+            }
+            if score > best {
+                best = score;
+                best_move = Some((src, dst));
+                pv.clear();
+                pv.push((src, dst));
+                pv.extend_from_slice(&child_pv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        self.path.pop();
+        if self.aborted {
+            return 0;
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let entry = TtEntry {
+            best_move,
+            score: score_to_tt(best, ply),
+            depth,
+            bound,
+        };
+        // the root without the moves of the other lines isn't the real position
+        if ply > 0 || self.root_excluded.is_empty() {
+            self.tt.store(board.hash, entry);
+        }
+        best
+    }
+}
+
+// captures of valuable pieces first, they are the moves most likely to cut the search short
+fn order_moves(board: &BoardRecource, moves: &mut [(usize, usize)]) {
+    moves.sort_by_cached_key(|&(src, dst)| match (board.grid[src], board.grid[dst]) {
+        (Some(piece), Some(target)) => {
+            -(piece_value(target.get_type()) * 16 - piece_value(piece.get_type()) / 16)
+        }
+        _ => 0,
+    });
+}
+
+// Searches deeper and deeper until a limit is hit or `stop` is set, `report` is called after
+// every finished depth. Positions are evaluated with `eval`, what was found is kept in `tt` for the
+// next searches. Returns None if the side to move has no legal moves.
+//
+// With more than one thread this is a Lazy SMP search: the other threads search the same position
+// at the same time and share what they find through `tt`, which sends the main thread to the
+// best moves faster. Only the main thread decides on the move and reports. A single thread
+// searches the same way every time, for the same position, limits and table
+pub fn search(
+    board: &BoardRecource,
+    limits: SearchLimits,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+    stop: &AtomicBool,
+    report: impl FnMut(&SearchInfo),
+) -> Option<(usize, usize)> {
+    tt.new_search();
+    let all_nodes = AtomicU64::new(0);
+    // set once the main thread is done, the helpers stop with it
+    let done = AtomicBool::new(false);
+    let best = thread::scope(|scope| {
+        for helper in 1..threads.clamp(1, MAX_THREADS) {
+            let (done, all_nodes) = (&done, &all_nodes);
+            scope.spawn(move || {
+                let mut searcher =
+                    Searcher::new(SearchLimits::default(), done, tt, eval, all_nodes);
+                // every other helper starts a depth ahead, so the threads don't all search the
+                // same depth at the same time
+                searcher.iterate(board, 1 + helper as u32 % 2, |_| {});
+            });
+        }
+        let mut searcher = Searcher::new(limits, stop, tt, eval, &all_nodes);
+        let best = searcher.iterate(board, 1, report);
+        done.store(true, Ordering::Relaxed);
+        best
+    });
+    // stopped before the first depth was done, any legal move is better than none
+    best.or_else(|| board.legal_moves().first().copied())
+}
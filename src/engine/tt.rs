@@ -0,0 +1,250 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+// Remembers what the search found out about positions it already visited, keyed by their
+// Zobrist hash. The table has a fixed size and needs no locks: an entry is two atomic words and
+// its key is stored XORed with its data, so an entry torn by two threads writing at once just
+// doesn't match any position anymore
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 4096;
+// entries of the same bucket compete for a position, the hash picks the bucket
+const BUCKET_SIZE: usize = 2;
+// generations wrap around after this many searches
+const GENERATIONS: u8 = 32;
+// how many depths an entry of an older search is worth less per search it is old
+const AGE_PENALTY: i32 = 4;
+
+// what the score of an entry says about the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the real score is at least this high, the search was cut off by a good move
+    Lower,
+    // the real score is at most this high, no move reached alpha
+    Upper,
+}
+
+impl Bound {
+    fn bits(self) -> u64 {
+        match self {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        }
+    }
+
+    fn from_bits(bits: u64) -> Self {
+        match bits {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<(usize, usize)>,
+    pub score: i32,
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+// the data word of an entry, from the lowest bits up: the squares of the best move (8 bits each),
+// whether there is one, the bound (2 bits), the generation (5 bits), the depth (8 bits) and the
+// score (32 bits)
+fn pack(entry: &TtEntry, generation: u8) -> u64 {
+    let (src, dst, has_move) = match entry.best_move {
+        Some((src, dst)) => (src as u64 & 0xff, dst as u64 & 0xff, 1),
+        None => (0, 0, 0),
+    };
+    src | (dst << 8)
+        | (has_move << 16)
+        | (entry.bound.bits() << 17)
+        | (((generation % GENERATIONS) as u64) << 19)
+        | ((entry.depth.min(u8::MAX as u32) as u64) << 24)
+        | ((entry.score as u32 as u64) << 32)
+}
+
+fn unpack(data: u64) -> (TtEntry, u8) {
+    let best_move =
+        ((data >> 16) & 1 == 1).then_some(((data & 0xff) as usize, ((data >> 8) & 0xff) as usize));
+    let entry = TtEntry {
+        best_move,
+        score: (data >> 32) as u32 as i32,
+        depth: ((data >> 24) & 0xff) as u32,
+        bound: Bound::from_bits((data >> 17) & 0b11),
+    };
+    (entry, ((data >> 19) & 0b1_1111) as u8)
+}
+
+#[derive(Default)]
+struct Slot {
+    // the hash of the position XOR `data`, both are zero while the slot is empty
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        (self.key.load(Ordering::Relaxed) ^ data, data)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.key.load(Ordering::Relaxed) == 0 && self.data.load(Ordering::Relaxed) == 0
+    }
+}
+
+pub struct TranspositionTable {
+    slots: Box<[Slot]>,
+    // of the current search, entries of older searches are replaced first
+    generation: AtomicU8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl TranspositionTable {
+    // as many buckets as fit in `megabytes`, rounded down to a power of two
+    pub fn new(megabytes: usize) -> Self {
+        let bytes = megabytes.clamp(1, MAX_HASH_MB) * 1024 * 1024;
+        let buckets = bytes / (BUCKET_SIZE * std::mem::size_of::<Slot>());
+        let buckets = 1 << buckets.max(1).ilog2();
+        TranspositionTable {
+            slots: (0..buckets * BUCKET_SIZE)
+                .map(|_| Slot::default())
+                .collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    // forgets every position, e.g. for a new game
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    // called at the start of every search, so the entries of the last one age
+    pub fn new_search(&self) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.generation
+            .store((generation + 1) % GENERATIONS, Ordering::Relaxed);
+    }
+
+    fn bucket(&self, hash: u64) -> &[Slot] {
+        let buckets = self.slots.len() / BUCKET_SIZE;
+        let start = (hash as usize & (buckets - 1)) * BUCKET_SIZE;
+        &self.slots[start..start + BUCKET_SIZE]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        self.bucket(hash)
+            .iter()
+            .map(Slot::load)
+            .find(|&(key, data)| key == hash && data != 0)
+            .map(|(_, data)| unpack(data).0)
+    }
+
+    // Replaces the entry of the same position if there is one, otherwise the one of the bucket
+    // that is worth the least: entries of older searches first, then the shallower ones
+    pub fn store(&self, hash: u64, mut entry: TtEntry) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let bucket = self.bucket(hash);
+        let worth = |slot: &Slot| {
+            if slot.is_empty() {
+                return i32::MIN;
+            }
+            let (old, old_generation) = unpack(slot.load().1);
+            let age = (generation + GENERATIONS - old_generation) % GENERATIONS;
+            old.depth as i32 - AGE_PENALTY * age as i32
+        };
+        let same_position = bucket.iter().find(|slot| slot.load().0 == hash);
+        let slot = match same_position {
+            Some(slot) => {
+                // a search that was cut off before trying a move still knows the old best one
+                if entry.best_move.is_none() {
+                    entry.best_move = unpack(slot.load().1).0.best_move;
+                }
+                slot
+            }
+            None => bucket
+                .iter()
+                .min_by_key(|slot| worth(slot))
+                .expect("buckets aren't empty"),
+        };
+        let data = pack(&entry, generation);
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRIES: [TtEntry; 3] = [
+        TtEntry {
+            best_move: Some((0, 255)),
+            score: -123_456,
+            depth: 7,
+            bound: Bound::Lower,
+        },
+        TtEntry {
+            best_move: None,
+            score: i32::MAX,
+            depth: 255,
+            bound: Bound::Exact,
+        },
+        TtEntry {
+            best_move: Some((17, 34)),
+            score: i32::MIN,
+            depth: 0,
+            bound: Bound::Upper,
+        },
+    ];
+
+    #[test]
+    fn entries_survive_packing() {
+        for entry in ENTRIES {
+            for generation in [0, 5, GENERATIONS - 1] {
+                assert_eq!(unpack(pack(&entry, generation)), (entry, generation));
+            }
+        }
+        // deeper searches than fit are kept as the deepest that fits
+        let deep = TtEntry {
+            depth: 300,
+            ..ENTRIES[0]
+        };
+        assert_eq!(unpack(pack(&deep, 0)).0.depth, 255);
+    }
+
+    #[test]
+    fn stored_entries_are_found() {
+        let tt = TranspositionTable::new(1);
+        let hash = 0x1234_5678_9abc_def0;
+        assert_eq!(tt.probe(hash), None);
+        tt.store(hash, ENTRIES[0]);
+        assert_eq!(tt.probe(hash), Some(ENTRIES[0]));
+        assert_eq!(tt.probe(hash ^ (1 << 63)), None);
+
+        // a new entry without a move keeps the old move of the position
+        let no_move = TtEntry {
+            best_move: None,
+            ..ENTRIES[2]
+        };
+        tt.store(hash, no_move);
+        let found = tt.probe(hash).unwrap();
+        assert_eq!(found.best_move, ENTRIES[0].best_move);
+        assert_eq!(found.bound, Bound::Upper);
+
+        tt.clear();
+        assert_eq!(tt.probe(hash), None);
+    }
+}
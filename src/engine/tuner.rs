@@ -0,0 +1,216 @@
+use std::{io, path::Path};
+
+use crate::logic::{
+    board::{BoardRecource, GameResult},
+    record::GameRecord,
+};
+
+use super::eval::{visit_features, EvalParams, PARAM_COUNT};
+
+// Texel tuning: the evaluation of a position, squashed into a winning chance, should predict
+// how the game it comes from ended. The parameters that make the smallest squared error over
+// many positions of recorded games are the tuned ones. The evaluation is linear, so the features
+// of every position are collected once and the error and its gradient come straight from them
+
+// the first moves of every game are skipped, they come from the openings the players know
+const SKIPPED_PLIES: usize = 8;
+
+// A position of a recorded game with the result of the game, 1 for a white win, 0.5 for a draw
+// and 0 for a black win
+pub struct TrainingPosition {
+    // the parameters the position uses and how often, for white minus for black
+    features: Vec<(u32, i32)>,
+    result: f64,
+}
+
+impl TrainingPosition {
+    pub fn new(board: &BoardRecource, result: GameResult) -> Self {
+        let mut features: Vec<(u32, i32)> = Vec::new();
+        visit_features(board, |index, count| features.push((index as u32, count)));
+        features.sort_unstable_by_key(|&(index, _)| index);
+        // the same parameter for both colors mostly cancels out, e.g. the kings
+        let mut merged: Vec<(u32, i32)> = Vec::with_capacity(features.len());
+        for (index, count) in features {
+            match merged.last_mut() {
+                Some((last, total)) if *last == index => *total += count,
+                _ => merged.push((index, count)),
+            }
+        }
+        merged.retain(|&(_, count)| count != 0);
+        let result = match result {
+            GameResult::Win(true) => 1.0,
+            GameResult::Win(false) => 0.0,
+            GameResult::Draw => 0.5,
+        };
+        TrainingPosition {
+            features: merged,
+            result,
+        }
+    }
+
+    // from white's point of view, like the features
+    fn evaluate(&self, params: &[f64]) -> f64 {
+        self.features
+            .iter()
+            .map(|&(index, count)| params[index as usize] * count as f64)
+            .sum()
+    }
+}
+
+// The positions of a finished game worth learning from: not in the opening and not in check or
+// right after a capture, where the evaluation can't know how the exchange ends
+pub fn positions_of_game(record: &GameRecord) -> Vec<TrainingPosition> {
+    let Some(result) = record.result() else {
+        return Vec::new();
+    };
+    let mut positions = Vec::new();
+    let mut board = BoardRecource::new_game();
+    for (ply, &(src, dst)) in record.moves.iter().enumerate() {
+        let capture = board.grid[dst].is_some();
+        if board.make_move(src, dst).is_none() {
+            break;
+        }
+        if ply + 1 >= SKIPPED_PLIES && !capture && !board.is_in_check(board.turn) {
+            positions.push(TrainingPosition::new(&board, result));
+        }
+    }
+    positions
+}
+
+// the positions of every recorded game in `dir`, e.g. the games directory of the server
+pub fn load_corpus(dir: &Path) -> io::Result<Vec<TrainingPosition>> {
+    let records = GameRecord::load_dir(dir)?;
+    Ok(records.iter().flat_map(positions_of_game).collect())
+}
+
+// the winning chance of white for an evaluation in centipawns, `k` scales it to the corpus
+fn win_chance(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+// the mean squared error of the predicted results
+pub fn error(params: &[f64], positions: &[TrainingPosition], k: f64) -> f64 {
+    let total: f64 = positions
+        .iter()
+        .map(|position| (position.result - win_chance(position.evaluate(params), k)).powi(2))
+        .sum();
+    total / positions.len().max(1) as f64
+}
+
+// the scale of the winning chance that fits the untuned evaluation best, found by a ternary
+// search since the error has a single minimum in it
+pub fn find_k(params: &[f64], positions: &[TrainingPosition]) -> f64 {
+    let (mut low, mut high) = (0.01, 10.0);
+    for _ in 0..100 {
+        let first = low + (high - low) / 3.0;
+        let second = high - (high - low) / 3.0;
+        if error(params, positions, first) < error(params, positions, second) {
+            high = second;
+        } else {
+            low = first;
+        }
+    }
+    (low + high) / 2.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningOptions {
+    pub epochs: usize,
+    // in centipawns per step
+    pub learning_rate: f64,
+}
+
+impl Default for TuningOptions {
+    fn default() -> Self {
+        TuningOptions {
+            epochs: 300,
+            learning_rate: 1.0,
+        }
+    }
+}
+
+// Lowers the error of `start` with Adam gradient descent over the whole corpus, `report` is
+// called with the error after every epoch
+pub fn tune(
+    start: &EvalParams,
+    positions: &[TrainingPosition],
+    options: TuningOptions,
+    mut report: impl FnMut(usize, f64),
+) -> EvalParams {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    let mut params: Vec<f64> = start.values().iter().map(|&value| value as f64).collect();
+    let k = find_k(&params, positions);
+    let mut momentum = vec![0.0; PARAM_COUNT];
+    let mut velocity = vec![0.0; PARAM_COUNT];
+    let scale = k * 10f64.ln() / 400.0;
+    for epoch in 1..=options.epochs {
+        let mut gradient = vec![0.0; PARAM_COUNT];
+        for position in positions {
+            let chance = win_chance(position.evaluate(&params), k);
+            // the derivative of the squared error by the evaluation
+            let slope = -2.0 * (position.result - chance) * chance * (1.0 - chance) * scale;
+            for &(index, count) in &position.features {
+                gradient[index as usize] += slope * count as f64;
+            }
+        }
+        let count = positions.len().max(1) as f64;
+        for (index, gradient) in gradient.iter().enumerate() {
+            let gradient = gradient / count;
+            momentum[index] = BETA1 * momentum[index] + (1.0 - BETA1) * gradient;
+            velocity[index] = BETA2 * velocity[index] + (1.0 - BETA2) * gradient * gradient;
+            let momentum = momentum[index] / (1.0 - BETA1.powi(epoch as i32));
+            let velocity = velocity[index] / (1.0 - BETA2.powi(epoch as i32));
+            params[index] -= options.learning_rate * momentum / (velocity.sqrt() + EPSILON);
+        }
+        report(epoch, error(&params, positions, k));
+    }
+    let values = params.iter().map(|value| value.round() as i32).collect();
+    EvalParams::from_values(values).expect("the tuner keeps the number of parameters")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::TILE_NUMBER;
+
+    use super::*;
+
+    // the start position without the piece on `square`
+    fn without(square: usize) -> BoardRecource {
+        let mut board = BoardRecource::new_game();
+        board.grid[square] = None;
+        board
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        // a missing piece loses only some of the games, which the built-in values can't know
+        let mut positions = Vec::new();
+        for x in [0, 1, 2, 4] {
+            let black = without(x);
+            positions.push(TrainingPosition::new(&black, GameResult::Win(true)));
+            positions.push(TrainingPosition::new(&black, GameResult::Draw));
+            let white = without(TILE_NUMBER * (TILE_NUMBER - 1) + x);
+            positions.push(TrainingPosition::new(&white, GameResult::Win(false)));
+            positions.push(TrainingPosition::new(&white, GameResult::Draw));
+        }
+        let start = EvalParams::default();
+        let values = |params: &EvalParams| -> Vec<f64> {
+            params.values().iter().map(|&value| value as f64).collect()
+        };
+        let k = find_k(&values(&start), &positions);
+        let before = error(&values(&start), &positions, k);
+
+        let options = TuningOptions {
+            epochs: 30,
+            learning_rate: 2.0,
+        };
+        let mut errors = Vec::new();
+        let tuned = tune(&start, &positions, options, |_, error| errors.push(error));
+        assert_eq!(errors.len(), options.epochs);
+        assert!(errors[options.epochs - 1] < before);
+        assert!(error(&values(&tuned), &positions, k) < before);
+    }
+}
@@ -0,0 +1,567 @@
+use std::{
+    io::BufRead,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::logic::{
+    board::{move_name, parse_move, BoardRecource},
+    fen::START_FEN,
+};
+
+use super::{
+    eval::EvalParams,
+    mcts::mcts,
+    player::AiStyle,
+    search::{search, Score, SearchInfo, SearchLimits, MAX_LINES, MAX_THREADS},
+    tt::{TranspositionTable, DEFAULT_HASH_MB, MAX_HASH_MB},
+};
+
+pub const ENGINE_NAME: &str = "Pro Chess";
+pub const ENGINE_AUTHOR: &str = "the Pro Chess developers";
+// the name GUIs use for the rules of this game, like `UCI_Variant` in Fairy-Stockfish
+pub const VARIANT: &str = "prochess";
+// kept free of the time of every move, for the answer to reach the GUI
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+// how many moves the remaining time is split over when the GUI doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// The commands of the UCI protocol the engine understands, everything else is ignored as the
+// protocol asks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    // `ucci` starts the same handshake for GUIs of the UCCI family
+    Uci {
+        ucci: bool,
+    },
+    IsReady,
+    SetOption {
+        name: String,
+        value: String,
+    },
+    NewGame,
+    // the start position if no FEN is given, followed by the moves played from it
+    Position {
+        fen: Option<String>,
+        moves: Vec<String>,
+    },
+    Go(GoParams),
+    Stop,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GoParams {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub movetime: Option<Duration>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub infinite: bool,
+}
+
+impl GoParams {
+    // the `go` command that asks for this search, the opposite of `parse_go`
+    pub fn to_command(&self) -> String {
+        let mut command = "go".to_string();
+        let durations = [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+            ("movetime", self.movetime),
+        ];
+        for (name, duration) in durations {
+            if let Some(duration) = duration {
+                command += &format!(" {} {}", name, duration.as_millis());
+            }
+        }
+        if let Some(movestogo) = self.movestogo {
+            command += &format!(" movestogo {}", movestogo);
+        }
+        if let Some(depth) = self.depth {
+            command += &format!(" depth {}", depth);
+        }
+        if let Some(nodes) = self.nodes {
+            command += &format!(" nodes {}", nodes);
+        }
+        if self.infinite {
+            command += " infinite";
+        }
+        command
+    }
+
+    // how long to think about the move of `turn`
+    pub fn limits(&self, turn: bool) -> SearchLimits {
+        let (time, increment) = if turn {
+            (self.wtime, self.winc)
+        } else {
+            (self.btime, self.binc)
+        };
+        let budget = self.movetime.or_else(|| {
+            time.map(|time| {
+                let left = time.saturating_sub(MOVE_OVERHEAD);
+                let moves = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                (left / moves + increment.unwrap_or_default() * 3 / 4).min(left / 2)
+            })
+        });
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            time: if self.infinite { None } else { budget },
+            lines: None,
+        }
+    }
+}
+
+fn parse_go(words: &[&str]) -> GoParams {
+    let mut params = GoParams::default();
+    let mut words = words.iter();
+    while let Some(&word) = words.next() {
+        if word == "infinite" {
+            params.infinite = true;
+            continue;
+        }
+        let Some(value) = words.next() else {
+            break;
+        };
+        let millis = value.parse().ok().map(Duration::from_millis);
+        match word {
+            "wtime" => params.wtime = millis,
+            "btime" => params.btime = millis,
+            "winc" => params.winc = millis,
+            "binc" => params.binc = millis,
+            "movetime" => params.movetime = millis,
+            "movestogo" => params.movestogo = value.parse().ok(),
+            "depth" => params.depth = value.parse().ok(),
+            "nodes" => params.nodes = value.parse().ok(),
+            _ => {}
+        }
+    }
+    params
+}
+
+pub fn parse_command(line: &str) -> Option<Command> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (&first, rest) = words.split_first()?;
+    let command = match first {
+        "uci" => Command::Uci { ucci: false },
+        "ucci" => Command::Uci { ucci: true },
+        "isready" => Command::IsReady,
+        "ucinewgame" => Command::NewGame,
+        "stop" => Command::Stop,
+        "quit" => Command::Quit,
+        "go" => Command::Go(parse_go(rest)),
+        // setoption name <name> [value <value>], both can hold spaces
+        "setoption" => {
+            let rest = rest.join(" ");
+            let rest = rest.strip_prefix("name ")?;
+            let (name, value) = rest.split_once(" value ").unwrap_or((rest, ""));
+            Command::SetOption {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            }
+        }
+        "position" => {
+            let moves_at = rest.iter().position(|&word| word == "moves");
+            let (setup, moves) = rest.split_at(moves_at.unwrap_or(rest.len()));
+            let fen = match setup.split_first() {
+                Some((&"startpos", _)) => None,
+                Some((&"fen", fen)) => Some(fen.join(" ")),
+                _ => return None,
+            };
+            Command::Position {
+                fen,
+                moves: moves.iter().skip(1).map(|word| word.to_string()).collect(),
+            }
+        }
+        _ => return None,
+    };
+    Some(command)
+}
+
+fn info_line(info: &SearchInfo) -> String {
+    let score = match info.score {
+        Score::Centipawns(cp) => format!("cp {}", cp),
+        Score::Mate(moves) => format!("mate {}", moves),
+    };
+    let pv: Vec<String> = info
+        .pv
+        .iter()
+        .map(|&(src, dst)| move_name(src, dst))
+        .collect();
+    format!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        info.line + 1,
+        score,
+        info.nodes,
+        info.nodes_per_second(),
+        info.time.as_millis(),
+        pv.join(" ")
+    )
+}
+
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+// Keeps the position between commands and searches on another thread, so `stop` can be read
+// while it thinks. Everything is answered on stdout
+pub struct Engine {
+    board: BoardRecource,
+    search: Option<RunningSearch>,
+    // shared with the running search, its size is set with the `Hash` option
+    tt: Arc<TranspositionTable>,
+    // loaded from the `EvalFile` option, the built-in values without one
+    eval: Arc<EvalParams>,
+    // set with the `Threads` option, one by default so the engine plays the same every time
+    threads: usize,
+    // set with the `Style` option
+    style: AiStyle,
+    // set with the `MultiPV` option
+    lines: usize,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine {
+            board: BoardRecource::new_game(),
+            search: None,
+            tt: Arc::new(TranspositionTable::new(DEFAULT_HASH_MB)),
+            eval: Arc::new(EvalParams::default()),
+            threads: 1,
+            style: AiStyle::default(),
+            lines: 1,
+        }
+    }
+}
+
+impl Engine {
+    // returns false once the engine should quit
+    pub fn handle(&mut self, command: Command) -> bool {
+        match command {
+            Command::Uci { ucci } => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!(
+                    "option name UCI_Variant type combo default {} var {}",
+                    VARIANT, VARIANT
+                );
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_HASH_MB, MAX_HASH_MB
+                );
+                println!(
+                    "option name Threads type spin default 1 min 1 max {}",
+                    MAX_THREADS
+                );
+                println!(
+                    "option name MultiPV type spin default 1 min 1 max {}",
+                    MAX_LINES
+                );
+                println!("option name EvalFile type string default <empty>");
+                let styles: Vec<String> = AiStyle::ALL
+                    .iter()
+                    .map(|style| format!("var {}", style.name()))
+                    .collect();
+                println!(
+                    "option name Style type combo default {} {}",
+                    AiStyle::default().name(),
+                    styles.join(" ")
+                );
+                println!("{}", if ucci { "ucciok" } else { "uciok" });
+            }
+            Command::IsReady => println!("readyok"),
+            Command::SetOption { name, value } => self.set_option(&name, &value),
+            Command::NewGame => {
+                self.stop();
+                self.board = BoardRecource::new_game();
+                self.tt.clear();
+            }
+            Command::Position { fen, moves } => self.set_position(fen.as_deref(), &moves),
+            Command::Go(params) => self.go(params),
+            Command::Stop => self.stop(),
+            Command::Quit => {
+                self.stop();
+                return false;
+            }
+        }
+        true
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        if name.eq_ignore_ascii_case("UCI_Variant") {
+            if value == VARIANT {
+                // the same announcement as Fairy-Stockfish, so GUIs can set up the board
+                println!(
+                    "info string variant {} files 16 ranks 16 startpos {}",
+                    VARIANT, START_FEN
+                );
+            } else {
+                println!("info string unsupported variant {}", value);
+            }
+        } else if name.eq_ignore_ascii_case("Hash") {
+            match value.parse() {
+                Ok(megabytes) => {
+                    self.stop();
+                    self.tt = Arc::new(TranspositionTable::new(megabytes));
+                }
+                Err(_) => println!("info string invalid hash size {}", value),
+            }
+        } else if name.eq_ignore_ascii_case("Threads") {
+            match value.parse::<usize>() {
+                Ok(threads) => self.threads = threads.clamp(1, MAX_THREADS),
+                Err(_) => println!("info string invalid thread count {}", value),
+            }
+        } else if name.eq_ignore_ascii_case("MultiPV") {
+            match value.parse::<usize>() {
+                Ok(lines) => self.lines = lines.clamp(1, MAX_LINES),
+                Err(_) => println!("info string invalid line count {}", value),
+            }
+        } else if name.eq_ignore_ascii_case("Style") {
+            match AiStyle::parse(value) {
+                Some(style) => self.style = style,
+                None => println!("info string unknown style {}", value),
+            }
+        } else if name.eq_ignore_ascii_case("EvalFile") {
+            let eval = match value {
+                "" | "<empty>" => Ok(EvalParams::default()),
+                path => EvalParams::load(Path::new(path)),
+            };
+            match eval {
+                Ok(eval) => {
+                    self.stop();
+                    self.eval = Arc::new(eval);
+                }
+                Err(err) => println!("info string invalid eval file {}", err),
+            }
+        } else {
+            println!("info string unknown option {}", name);
+        }
+    }
+
+    fn set_position(&mut self, fen: Option<&str>, moves: &[String]) {
+        let board = match fen {
+            None => Ok(BoardRecource::new_game()),
+            Some(fen) => BoardRecource::from_fen(fen),
+        };
+        let mut board = match board {
+            Ok(board) => board,
+            Err(err) => {
+                println!("info string invalid position: {}", err);
+                return;
+            }
+        };
+        for word in moves {
+            let legal =
+                parse_move(word).is_some_and(|(src, dst)| board.make_move(src, dst).is_some());
+            if !legal {
+                println!("info string illegal move {}", word);
+                break;
+            }
+        }
+        self.board = board;
+    }
+
+    fn go(&mut self, params: GoParams) {
+        self.stop();
+        let stop = Arc::new(AtomicBool::new(false));
+        let board = self.board.clone();
+        let limits = SearchLimits {
+            lines: Some(self.lines),
+            ..params.limits(board.turn)
+        };
+        let threads = self.threads;
+        let style = self.style;
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            let tt = self.tt.clone();
+            let eval = self.eval.clone();
+            move || {
+                let report = |info: &SearchInfo| println!("{}", info_line(info));
+                let best = match style {
+                    AiStyle::AlphaBeta => {
+                        search(&board, limits, &tt, &eval, threads, &stop, report)
+                    }
+                    AiStyle::Mcts(playout) => mcts(&board, limits, playout, &stop, report),
+                };
+                match best {
+                    Some((src, dst)) => println!("bestmove {}", move_name(src, dst)),
+                    None => println!("bestmove (none)"),
+                }
+            }
+        });
+        self.search = Some(RunningSearch { stop, thread });
+    }
+
+    // waits for the running search, which still answers with its best move
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            let _ = search.thread.join();
+        }
+    }
+}
+
+// reads commands until `quit` or the end of the input
+pub fn run(input: impl BufRead) {
+    let mut engine = Engine::default();
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if let Some(command) = parse_command(&line) {
+            if !engine.handle(command) {
+                return;
+            }
+        }
+    }
+    engine.stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(millis: u64) -> Option<Duration> {
+        Some(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn position_commands() {
+        assert_eq!(
+            parse_command("position startpos moves e3e5 e14e12"),
+            Some(Command::Position {
+                fen: None,
+                moves: vec!["e3e5".to_string(), "e14e12".to_string()],
+            })
+        );
+        assert_eq!(
+            parse_command("position startpos"),
+            Some(Command::Position {
+                fen: None,
+                moves: Vec::new(),
+            })
+        );
+        assert_eq!(
+            parse_command(&format!("position fen {} moves e3e5", START_FEN)),
+            Some(Command::Position {
+                fen: Some(START_FEN.to_string()),
+                moves: vec!["e3e5".to_string()],
+            })
+        );
+        assert_eq!(parse_command("position e3e5"), None);
+    }
+
+    #[test]
+    fn option_names_and_values_can_hold_spaces() {
+        assert_eq!(
+            parse_command("setoption name Eval File value my eval.txt"),
+            Some(Command::SetOption {
+                name: "Eval File".to_string(),
+                value: "my eval.txt".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_command("setoption name Clear Hash"),
+            Some(Command::SetOption {
+                name: "Clear Hash".to_string(),
+                value: String::new(),
+            })
+        );
+        assert_eq!(parse_command("setoption Hash value 64"), None);
+    }
+
+    #[test]
+    fn go_with_clocks() {
+        assert_eq!(
+            parse_command("go wtime 60000 btime 55000 winc 1000 binc 2000 movestogo 20"),
+            Some(Command::Go(GoParams {
+                wtime: millis(60000),
+                btime: millis(55000),
+                winc: millis(1000),
+                binc: millis(2000),
+                movestogo: Some(20),
+                ..GoParams::default()
+            }))
+        );
+        assert_eq!(
+            parse_command("go infinite"),
+            Some(Command::Go(GoParams {
+                infinite: true,
+                ..GoParams::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn go_commands_round_trip() {
+        for params in [
+            GoParams::default(),
+            GoParams {
+                wtime: millis(300000),
+                btime: millis(299950),
+                winc: millis(3000),
+                binc: millis(3000),
+                movestogo: Some(12),
+                ..GoParams::default()
+            },
+            GoParams {
+                movetime: millis(1500),
+                depth: Some(9),
+                nodes: Some(1_000_000),
+                ..GoParams::default()
+            },
+            GoParams {
+                infinite: true,
+                ..GoParams::default()
+            },
+        ] {
+            let command = params.to_command();
+            assert_eq!(
+                parse_command(&command),
+                Some(Command::Go(params)),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn time_budget() {
+        let params = GoParams {
+            wtime: millis(60050),
+            btime: millis(100),
+            winc: millis(2000),
+            binc: millis(2000),
+            movestogo: Some(20),
+            ..GoParams::default()
+        };
+        // a share of the time left plus most of the increment
+        assert_eq!(params.limits(true).time, millis(60000 / 20 + 1500));
+        // never more than half of what is left when the clock is nearly empty
+        let left = Duration::from_millis(100) - MOVE_OVERHEAD;
+        assert_eq!(params.limits(false).time, Some(left / 2));
+
+        let fixed = GoParams {
+            movetime: millis(700),
+            ..params
+        };
+        assert_eq!(fixed.limits(false).time, millis(700));
+        let infinite = GoParams {
+            infinite: true,
+            ..params
+        };
+        assert_eq!(infinite.limits(true).time, None);
+    }
+}
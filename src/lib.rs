@@ -0,0 +1,7 @@
+pub mod camera;
+pub mod engine;
+pub mod logic;
+pub mod network;
+pub mod sound;
+pub mod states;
+pub mod ui;
@@ -0,0 +1,184 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    engine::{
+        book::{OpeningBook, Variety},
+        eval::EvalParams,
+        player::{BuiltInAi, ComputerPlayer},
+    },
+    states::{
+        AppState, BookVariety, EnginePath, GameModeState, HintLimit, LocalControl, PlayerColor,
+        SearchStyle, SearchThreads,
+    },
+};
+
+use super::{
+    animation::PieceAnimation,
+    board::{apply_move_requests, BoardRecource, GameEnded, MoveHistory, MoveRequested},
+    hint::Hints,
+    record::GameRecord,
+};
+
+// how long the computer thinks about each move
+const THINK_TIME: Duration = Duration::from_secs(1);
+// built by the book builder from recorded games, without it the AI thinks from the first move on
+pub const BOOK_PATH: &str = "assets/opening.book";
+// written by the tuner, without it the AI uses the built-in values of the evaluation
+pub const EVAL_PATH: &str = "assets/eval.params";
+// finished games are kept there like the ones of the server, e.g. to review them later
+pub const GAMES_DIR: &str = "games";
+
+// The computer plays the color the player didn't pick in singleplayer games
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            start_computer_player.run_if(in_state(GameModeState::Singleplayer)),
+        )
+        .add_systems(
+            Update,
+            play_computer_moves
+                .before(apply_move_requests)
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameModeState::Singleplayer)),
+        )
+        // the last move of the game is only in the history after the update
+        .add_systems(
+            PostUpdate,
+            record_game
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameModeState::Singleplayer)),
+        )
+        .add_systems(OnExit(AppState::InGame), stop_computer_player)
+        // a finished game can be analyzed without leaving it
+        .add_systems(OnExit(GameModeState::Singleplayer), stop_computer_player);
+    }
+}
+
+#[derive(Resource)]
+pub struct Computer(ComputerPlayer);
+
+fn start_computer_player(
+    mut commands: Commands,
+    engine_path: Res<EnginePath>,
+    style: Res<SearchStyle>,
+    threads: Res<SearchThreads>,
+    variety: Res<BookVariety>,
+    player_color: Res<PlayerColor>,
+    mut local_control: ResMut<LocalControl>,
+) {
+    let path = engine_path.0.trim();
+    let path = (!path.is_empty()).then(|| PathBuf::from(path));
+    let book = match variety.0 {
+        Variety::Off => None,
+        _ => load_book(),
+    };
+    let built_in = BuiltInAi {
+        style: style.0,
+        threads: threads.0,
+        book,
+        variety: variety.0,
+        eval: load_eval(),
+    };
+    let computer = ComputerPlayer::start(path, THINK_TIME, built_in);
+    commands.insert_resource(Computer(computer));
+    *local_control = LocalControl::only(player_color.0);
+}
+
+// None without a book, a broken one is only worth a warning
+pub fn load_book() -> Option<OpeningBook> {
+    match OpeningBook::load(Path::new(BOOK_PATH)) {
+        Ok(book) => Some(book),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => {
+            warn!("can't read the opening book {}: {}", BOOK_PATH, err);
+            None
+        }
+    }
+}
+
+// the built-in values without a tuned file, a broken one is only worth a warning
+pub fn load_eval() -> EvalParams {
+    match EvalParams::load(Path::new(EVAL_PATH)) {
+        Ok(eval) => eval,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => EvalParams::default(),
+        Err(err) => {
+            // the error already names the file
+            warn!("can't read the tuned evaluation: {}", err);
+            EvalParams::default()
+        }
+    }
+}
+
+// asks for a move whenever it's the computer's turn and plays it once it's found, the moves
+// go through the same checks as the ones of the player
+fn play_computer_moves(
+    computer: Option<ResMut<Computer>>,
+    board: Res<BoardRecource>,
+    player_color: Res<PlayerColor>,
+    animations: Query<(), With<PieceAnimation>>,
+    mut move_requested: EventWriter<MoveRequested>,
+) {
+    let Some(mut computer) = computer else {
+        return;
+    };
+    if let Some(computer_move) = computer.0.poll() {
+        if let Some(err) = computer_move.engine_error {
+            warn!("the engine failed, the built-in AI plays instead: {}", err);
+        }
+        if let Some((src, dst)) = computer_move.mv {
+            move_requested.send(MoveRequested { src, dst });
+        }
+        return;
+    }
+    let computers_turn = board.turn != player_color.0 && board.result.is_none();
+    if computers_turn && !computer.0.is_thinking() && animations.is_empty() {
+        computer.0.request_move(&board);
+    }
+}
+
+// the game record also tells how many hints the player needed
+fn record_game(
+    mut game_ended: EventReader<GameEnded>,
+    history: Res<MoveHistory>,
+    player_color: Res<PlayerColor>,
+    hints: Res<Hints>,
+    hint_limit: Res<HintLimit>,
+) {
+    for ev in game_ended.read() {
+        let (white, black) = if player_color.0 {
+            ("Player", "Computer")
+        } else {
+            ("Computer", "Player")
+        };
+        let mut record = GameRecord::new(white, black);
+        for &(src, dst) in &history.moves {
+            record.push_move(src, dst);
+        }
+        record.set_result(ev.result, ev.reason);
+        record.set_tag("Hints", &hints.used.to_string());
+        if let Some(limit) = hint_limit.0 {
+            record.set_tag("HintLimit", &limit.to_string());
+        }
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let name = format!("singleplayer-{}", finished_at);
+        match record.save(Path::new(GAMES_DIR), &name) {
+            Ok(path) => info!("game saved to {}", path.display()),
+            Err(err) => warn!("can't save the game: {}", err),
+        }
+    }
+}
+
+fn stop_computer_player(mut commands: Commands) {
+    commands.remove_resource::<Computer>();
+}
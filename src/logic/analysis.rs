@@ -0,0 +1,766 @@
+use bevy::{prelude::*, sprite::Anchor, text::Text2dBounds};
+
+use crate::{
+    engine::{
+        analyzer::Analyzer,
+        book::{BookMove, OpeningBook},
+        search::{Score, SearchInfo, MAX_LINES},
+    },
+    states::{
+        AppState, BoardInputBlocked, GameModeState, LocalControl, MoveAuthority, SearchThreads,
+        TextInputFocused,
+    },
+};
+
+use super::{
+    ai::{load_book, load_eval},
+    board::{
+        move_name, BoardChanged, BoardOrientation, BoardRecource, BoardReset, GameEndReason,
+        GameEnded, GameResult, MoveHistory,
+    },
+    piece::from_index_to_srceen_position,
+    OnGameScreen, GRID_SIZE, SQUARE_SIZE,
+};
+
+// how many lines are shown when the analysis starts
+const DEFAULT_LINES: usize = 3;
+// how many moves of every line are written out
+const SHOWN_LINE_MOVES: usize = 6;
+// how many of the moves before and after the current one are in the move list
+const SHOWN_PAST_MOVES: usize = 16;
+const SHOWN_NEXT_MOVES: usize = 6;
+const EVAL_BAR_WIDTH: f32 = 16.0;
+const PANEL_WIDTH: f32 = 180.0;
+const PANEL_FONT_SIZE: f32 = 15.0;
+const BEST_MOVE_COLOR: Color = Color::rgba(0.2, 0.8, 0.3, 0.9);
+const OTHER_MOVE_COLOR: Color = Color::rgba(0.3, 0.6, 1.0, 0.7);
+const BOOK_MOVE_COLOR: Color = Color::rgba(0.9, 0.7, 0.2, 0.8);
+
+// Both sides are moved by the player while the AI keeps searching the position on the board. It's
+// started from the game mode menu or from a finished local game, whose moves become the main line
+pub struct AnalysisPlugin;
+
+impl Plugin for AnalysisPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (offer_analysis, enter_analysis)
+                .run_if(in_state(AppState::InGame))
+                .run_if(
+                    in_state(GameModeState::Singleplayer)
+                        .or_else(in_state(GameModeState::Multiplayer)),
+                ),
+        )
+        .add_systems(
+            Update,
+            (
+                start_analysis,
+                follow_moves,
+                navigate_variations,
+                change_line_count,
+                update_analysis,
+                update_analysis_panel,
+                update_eval_bar,
+                draw_best_moves,
+            )
+                .chain()
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameModeState::Analysis)),
+        )
+        .add_systems(OnExit(AppState::InGame), stop_analysis);
+    }
+}
+
+// The moves looked at during an analysis. Every position can have several continuations, the
+// first one is the main line and the others are variations
+#[derive(Debug, Clone)]
+pub struct VariationTree {
+    nodes: Vec<VariationNode>,
+    current: usize,
+}
+
+#[derive(Debug, Clone)]
+struct VariationNode {
+    // None for the starting position
+    mv: Option<(usize, usize)>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+impl VariationTree {
+    // the moves of a game as the main line, looking at its last position
+    pub fn new(moves: &[(usize, usize)]) -> Self {
+        let mut tree = VariationTree {
+            nodes: vec![VariationNode {
+                mv: None,
+                parent: None,
+                children: Vec::new(),
+            }],
+            current: 0,
+        };
+        for &mv in moves {
+            tree.play(mv);
+        }
+        tree
+    }
+
+    // follows the move if it was already looked at, otherwise it starts a new variation
+    pub fn play(&mut self, mv: (usize, usize)) {
+        let existing = self.nodes[self.current]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].mv == Some(mv));
+        self.current = match existing {
+            Some(child) => child,
+            None => {
+                let child = self.nodes.len();
+                self.nodes.push(VariationNode {
+                    mv: Some(mv),
+                    parent: Some(self.current),
+                    children: Vec::new(),
+                });
+                self.nodes[self.current].children.push(child);
+                child
+            }
+        };
+    }
+
+    // the moves from the starting position to the current one
+    pub fn path(&self) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        let mut node = &self.nodes[self.current];
+        while let (Some(mv), Some(parent)) = (node.mv, node.parent) {
+            moves.push(mv);
+            node = &self.nodes[parent];
+        }
+        moves.reverse();
+        moves
+    }
+
+    // how many moves were played to get to the current position
+    pub fn ply(&self) -> usize {
+        let mut ply = 0;
+        let mut node = &self.nodes[self.current];
+        while let Some(parent) = node.parent {
+            ply += 1;
+            node = &self.nodes[parent];
+        }
+        ply
+    }
+
+    // the first continuation of every position, the moves of the game when it was analyzed
+    pub fn main_line(&self) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        let mut node = &self.nodes[0];
+        while let Some(&child) = node.children.first() {
+            node = &self.nodes[child];
+            moves.extend(node.mv);
+        }
+        moves
+    }
+
+    // goes to the position after `ply` moves of the main line, or its end if it's shorter
+    pub fn go_to_main_line(&mut self, ply: usize) {
+        self.current = 0;
+        for _ in 0..ply {
+            if !self.forward() {
+                break;
+            }
+        }
+    }
+
+    // the main line from the current position on
+    pub fn continuation(&self) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        let mut node = &self.nodes[self.current];
+        while let Some(&child) = node.children.first() {
+            node = &self.nodes[child];
+            moves.extend(node.mv);
+        }
+        moves
+    }
+
+    // the other moves that were looked at instead of the current one
+    pub fn alternatives(&self) -> Vec<(usize, usize)> {
+        let Some(parent) = self.nodes[self.current].parent else {
+            return Vec::new();
+        };
+        self.nodes[parent]
+            .children
+            .iter()
+            .filter(|&&child| child != self.current)
+            .filter_map(|&child| self.nodes[child].mv)
+            .collect()
+    }
+
+    // one move back, false at the starting position
+    pub fn back(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // one move forward on the main line from here, false at its end
+    pub fn forward(&mut self) -> bool {
+        match self.nodes[self.current].children.first() {
+            Some(&child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // goes to the previous or the next move played from the same position, false without one
+    pub fn switch_variation(&mut self, step: isize) -> bool {
+        let Some(parent) = self.nodes[self.current].parent else {
+            return false;
+        };
+        let siblings = &self.nodes[parent].children;
+        if siblings.len() < 2 {
+            return false;
+        }
+        let index = siblings
+            .iter()
+            .position(|&child| child == self.current)
+            .unwrap_or(0);
+        let next = (index as isize + step).rem_euclid(siblings.len() as isize) as usize;
+        self.current = siblings[next];
+        true
+    }
+}
+
+#[derive(Resource)]
+pub struct AnalysisSession {
+    pub tree: VariationTree,
+    analyzer: Analyzer,
+    lines: usize,
+    // the hash of the position the analyzer looks at, None to start it again
+    analyzed: Option<u64>,
+    // the best lines found for it so far, by their place
+    found: Vec<SearchInfo>,
+    // how the game ended in this position, nothing is searched then
+    ended: Option<(GameResult, GameEndReason)>,
+    book: Option<OpeningBook>,
+    book_moves: Vec<BookMove>,
+}
+
+// Tag component used to tag the text that offers to analyze a finished game
+#[derive(Component)]
+struct AnalysisHint;
+
+#[derive(Component)]
+struct AnalysisPanel;
+
+#[derive(Component)]
+struct EvalBarFill;
+
+// the score from white's point of view, the search gives it for the side to move
+fn white_score(score: Score, turn: bool) -> Score {
+    match (score, turn) {
+        (_, true) => score,
+        (Score::Centipawns(value), false) => Score::Centipawns(-value),
+        (Score::Mate(moves), false) => Score::Mate(-moves),
+    }
+}
+
+fn score_text(score: Score) -> String {
+    match score {
+        Score::Centipawns(value) => format!("{:+.2}", value as f32 / 100.0),
+        Score::Mate(moves) => format!("#{}", moves),
+    }
+}
+
+// how much of the eval bar is white's, half for an even position
+fn white_share(score: Score) -> f32 {
+    match score {
+        Score::Centipawns(value) => 1.0 / (1.0 + (-value as f32 / 400.0).exp()),
+        Score::Mate(moves) if moves > 0 => 1.0,
+        Score::Mate(_) => 0.0,
+    }
+}
+
+fn offer_analysis(mut commands: Commands, mut game_ended: EventReader<GameEnded>) {
+    if game_ended.is_empty() {
+        return;
+    }
+    game_ended.clear();
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "Press A to analyze the game",
+                TextStyle {
+                    font_size: SQUARE_SIZE * 0.5,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ),
+            transform: Transform::from_xyz(0.0, -(GRID_SIZE / 2.0 + SQUARE_SIZE * 0.8), 0.0),
+            ..default()
+        },
+        AnalysisHint,
+        OnGameScreen,
+    ));
+}
+
+fn enter_analysis(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    board: Res<BoardRecource>,
+    mut game_mode: ResMut<NextState<GameModeState>>,
+) {
+    if keys.just_pressed(KeyCode::KeyA) && !text_input.0 && board.result.is_some() {
+        game_mode.set(GameModeState::Analysis);
+    }
+}
+
+// sets the board to the current position of the tree, a finished game can be played on from
+// any of its positions
+pub fn show_current_position(
+    tree: &VariationTree,
+    board: &mut BoardRecource,
+    history: &mut MoveHistory,
+    board_reset: &mut EventWriter<BoardReset>,
+) {
+    let path = tree.path();
+    *board = BoardRecource::new_game();
+    for &(src, dst) in &path {
+        if board.make_move(src, dst).is_none() {
+            warn!("illegal move in the analysis {}", move_name(src, dst));
+            break;
+        }
+    }
+    history.moves = path;
+    board_reset.send(BoardReset);
+}
+
+// runs once the game is on the screen, the moves played so far become the main line
+#[allow(clippy::too_many_arguments)]
+fn start_analysis(
+    mut commands: Commands,
+    session: Option<Res<AnalysisSession>>,
+    threads: Res<SearchThreads>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+    mut local_control: ResMut<LocalControl>,
+    mut authority: ResMut<MoveAuthority>,
+    mut input_blocked: ResMut<BoardInputBlocked>,
+    hints: Query<Entity, With<AnalysisHint>>,
+) {
+    if session.is_some() {
+        return;
+    }
+    let tree = VariationTree::new(&history.moves);
+    show_current_position(&tree, &mut board, &mut history, &mut board_reset);
+    *local_control = LocalControl::BOTH;
+    *authority = MoveAuthority::Local;
+    input_blocked.0 = false;
+    for hint in &hints {
+        commands.entity(hint).despawn_recursive();
+    }
+
+    let bar_x = -(GRID_SIZE / 2.0 + SQUARE_SIZE * 0.8);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.15, 0.15, 0.15),
+                custom_size: Some(Vec2::new(EVAL_BAR_WIDTH, GRID_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_xyz(bar_x, 0.0, 0.0),
+            ..default()
+        },
+        OnGameScreen,
+    ));
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.9, 0.9, 0.9),
+                custom_size: Some(Vec2::new(EVAL_BAR_WIDTH, GRID_SIZE / 2.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(bar_x, 0.0, 0.1),
+            ..default()
+        },
+        EvalBarFill,
+        OnGameScreen,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: PANEL_FONT_SIZE,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ),
+            text_anchor: Anchor::TopLeft,
+            text_2d_bounds: Text2dBounds {
+                size: Vec2::new(PANEL_WIDTH, f32::INFINITY),
+            },
+            transform: Transform::from_xyz(
+                GRID_SIZE / 2.0 + SQUARE_SIZE * 0.5,
+                GRID_SIZE / 2.0,
+                0.0,
+            ),
+            ..default()
+        },
+        AnalysisPanel,
+        OnGameScreen,
+    ));
+
+    let book = load_book();
+    commands.insert_resource(AnalysisSession {
+        tree,
+        analyzer: Analyzer::new(threads.0, load_eval()),
+        lines: DEFAULT_LINES,
+        analyzed: None,
+        found: Vec::new(),
+        ended: None,
+        book,
+        book_moves: Vec::new(),
+    });
+}
+
+// the moves played on the board continue the tree from the current position
+fn follow_moves(
+    mut board_events: EventReader<BoardChanged>,
+    session: Option<ResMut<AnalysisSession>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    for ev in board_events.read() {
+        session.tree.play((ev.src, ev.dst));
+    }
+}
+
+// left and right go through the moves, up and down through the other moves played instead of the
+// current one
+fn navigate_variations(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    session: Option<ResMut<AnalysisSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if text_input.0 {
+        return;
+    }
+    let tree = &mut session.tree;
+    let moved = if keys.just_pressed(KeyCode::ArrowLeft) {
+        tree.back()
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        tree.forward()
+    } else if keys.just_pressed(KeyCode::ArrowUp) {
+        tree.switch_variation(-1)
+    } else if keys.just_pressed(KeyCode::ArrowDown) {
+        tree.switch_variation(1)
+    } else {
+        false
+    };
+    if moved {
+        show_current_position(tree, &mut board, &mut history, &mut board_reset);
+    }
+}
+
+// plus and minus change how many lines are searched
+fn change_line_count(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    session: Option<ResMut<AnalysisSession>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if text_input.0 {
+        return;
+    }
+    let lines = if keys.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+        (session.lines + 1).min(MAX_LINES)
+    } else if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        session.lines.saturating_sub(1).max(1)
+    } else {
+        return;
+    };
+    if lines != session.lines {
+        session.lines = lines;
+        session.analyzed = None;
+    }
+}
+
+// starts the search again whenever the position changes and collects what it finds
+fn update_analysis(board: Res<BoardRecource>, session: Option<ResMut<AnalysisSession>>) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if session.analyzed != Some(board.hash) {
+        let session = &mut *session;
+        session.analyzed = Some(board.hash);
+        session.found.clear();
+        session.ended = board.game_status();
+        session.book_moves = session
+            .book
+            .as_ref()
+            .map(|book| book.moves(&board))
+            .unwrap_or_default();
+        match session.ended {
+            Some(_) => session.analyzer.stop(),
+            None => session.analyzer.analyze(&board, session.lines),
+        }
+    }
+    // read through the resource so it only counts as changed when something was found
+    let infos = session.analyzer.poll();
+    for info in infos {
+        let line = info.line;
+        if line < session.found.len() {
+            session.found[line] = info;
+        } else if line == session.found.len() {
+            session.found.push(info);
+        }
+    }
+}
+
+fn move_list_text(tree: &VariationTree) -> String {
+    let path = tree.path();
+    let current = path.len();
+    let moves: Vec<(usize, usize)> = path.into_iter().chain(tree.continuation()).collect();
+    let start = current.saturating_sub(SHOWN_PAST_MOVES);
+    let mut text = String::new();
+    if start > 0 {
+        text += "... ";
+    }
+    for (ply, &(src, dst)) in moves
+        .iter()
+        .enumerate()
+        .take(current + SHOWN_NEXT_MOVES)
+        .skip(start)
+    {
+        if ply % 2 == 0 {
+            text += &format!("{}. ", ply / 2 + 1);
+        } else if ply == start {
+            text += &format!("{}... ", ply / 2 + 1);
+        }
+        if ply + 1 == current {
+            text += &format!("[{}] ", move_name(src, dst));
+        } else {
+            text += &format!("{} ", move_name(src, dst));
+        }
+    }
+    if moves.len() > current + SHOWN_NEXT_MOVES {
+        text += "...";
+    }
+    text
+}
+
+fn update_analysis_panel(
+    board: Res<BoardRecource>,
+    session: Option<Res<AnalysisSession>>,
+    mut panels: Query<&mut Text, With<AnalysisPanel>>,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    if !session.is_changed() && !board.is_changed() {
+        return;
+    }
+    let mut text = String::new();
+    match (session.ended, session.found.first()) {
+        (Some((result, reason)), _) => {
+            text += &format!("{} by {}\n", result.name(), reason.name());
+        }
+        (None, Some(best)) => text += &format!("Depth {}\n", best.depth),
+        (None, None) => text += "Thinking...\n",
+    }
+    for info in &session.found {
+        let moves: Vec<String> = info
+            .pv
+            .iter()
+            .take(SHOWN_LINE_MOVES)
+            .map(|&(src, dst)| move_name(src, dst))
+            .collect();
+        text += &format!(
+            "{} {}\n",
+            score_text(white_score(info.score, board.turn)),
+            moves.join(" ")
+        );
+    }
+    if !session.book_moves.is_empty() {
+        let moves: Vec<String> = session
+            .book_moves
+            .iter()
+            .map(|book_move| {
+                let (src, dst) = book_move.mv;
+                format!("{} ({})", move_name(src, dst), book_move.weight)
+            })
+            .collect();
+        text += &format!("\nBook: {}\n", moves.join(", "));
+    }
+    text += &format!("\n{}\n", move_list_text(&session.tree));
+    let alternatives = session.tree.alternatives();
+    if !alternatives.is_empty() {
+        let moves: Vec<String> = alternatives
+            .iter()
+            .map(|&(src, dst)| move_name(src, dst))
+            .collect();
+        text += &format!("Instead: {}\n", moves.join(", "));
+    }
+    text += &format!(
+        "\nLeft/Right: moves\nUp/Down: variations\n+/-: lines ({})\nR: review the game",
+        session.lines
+    );
+    for mut panel in &mut panels {
+        panel.sections[0].value = text.clone();
+    }
+}
+
+// white's part of the bar grows from white's side of the board
+fn update_eval_bar(
+    board: Res<BoardRecource>,
+    session: Option<Res<AnalysisSession>>,
+    orientation: Res<BoardOrientation>,
+    mut fills: Query<(&mut Sprite, &mut Transform), With<EvalBarFill>>,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    let share = match (session.ended, session.found.first()) {
+        (Some((GameResult::Win(winner), _)), _) => winner as u8 as f32,
+        (Some((GameResult::Draw, _)), _) => 0.5,
+        (None, Some(best)) => white_share(white_score(best.score, board.turn)),
+        (None, None) => 0.5,
+    };
+    for (mut sprite, mut transform) in &mut fills {
+        let height = GRID_SIZE * share;
+        sprite.custom_size = Some(Vec2::new(EVAL_BAR_WIDTH, height));
+        let bottom = -GRID_SIZE / 2.0 + height / 2.0;
+        transform.translation.y = if orientation.flipped { -bottom } else { bottom };
+    }
+}
+
+fn draw_arrow(gizmos: &mut Gizmos, (src, dst): (usize, usize), flipped: bool, color: Color) {
+    gizmos.arrow_2d(
+        from_index_to_srceen_position(src, flipped).truncate(),
+        from_index_to_srceen_position(dst, flipped).truncate(),
+        color,
+    );
+}
+
+// the first move of every line, the best one drawn last so it's on top
+fn draw_best_moves(
+    session: Option<Res<AnalysisSession>>,
+    orientation: Res<BoardOrientation>,
+    mut gizmos: Gizmos,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    for book_move in &session.book_moves {
+        draw_arrow(
+            &mut gizmos,
+            book_move.mv,
+            orientation.flipped,
+            BOOK_MOVE_COLOR,
+        );
+    }
+    for info in session.found.iter().rev() {
+        let Some(&mv) = info.pv.first() else {
+            continue;
+        };
+        let color = match info.line {
+            0 => BEST_MOVE_COLOR,
+            _ => OTHER_MOVE_COLOR,
+        };
+        draw_arrow(&mut gizmos, mv, orientation.flipped, color);
+    }
+}
+
+// dropping the session stops the search
+fn stop_analysis(mut commands: Commands) {
+    commands.remove_resource::<AnalysisSession>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the moves only need to be told apart, the tree doesn't check them
+    const GAME: [(usize, usize); 4] = [(1, 2), (3, 4), (5, 6), (7, 8)];
+
+    #[test]
+    fn back_and_forward_follow_the_main_line() {
+        let mut tree = VariationTree::new(&GAME);
+        assert_eq!(tree.ply(), 4);
+        assert_eq!(tree.path(), GAME);
+        assert!(!tree.forward());
+
+        assert!(tree.back());
+        assert!(tree.back());
+        assert_eq!(tree.path(), GAME[..2]);
+        assert_eq!(tree.continuation(), GAME[2..]);
+        assert!(tree.forward());
+        assert_eq!(tree.path(), GAME[..3]);
+
+        tree.go_to_main_line(0);
+        assert!(!tree.back());
+        assert_eq!(tree.continuation(), GAME);
+        tree.go_to_main_line(10);
+        assert_eq!(tree.ply(), 4);
+    }
+
+    #[test]
+    fn new_moves_start_variations() {
+        let mut tree = VariationTree::new(&GAME);
+        tree.go_to_main_line(1);
+        tree.play((9, 10));
+        tree.play((11, 12));
+        assert_eq!(tree.path(), [GAME[0], (9, 10), (11, 12)]);
+        assert_eq!(tree.alternatives(), Vec::new());
+        assert!(tree.back());
+        assert_eq!(tree.alternatives(), [GAME[1]]);
+        assert_eq!(tree.continuation(), [(11, 12)]);
+        // the main line stays the moves of the game
+        assert_eq!(tree.main_line(), GAME);
+
+        // a move that was looked at before is followed instead of added again
+        tree.go_to_main_line(1);
+        tree.play(GAME[1]);
+        assert_eq!(tree.path(), GAME[..2]);
+        assert_eq!(tree.continuation(), GAME[2..]);
+    }
+
+    #[test]
+    fn switching_between_variations() {
+        let mut tree = VariationTree::new(&GAME);
+        tree.go_to_main_line(1);
+        tree.play((9, 10));
+        tree.go_to_main_line(1);
+        tree.play((13, 14));
+
+        assert!(tree.switch_variation(1));
+        assert_eq!(tree.path(), [GAME[0], GAME[1]]);
+        assert!(tree.switch_variation(1));
+        assert_eq!(tree.path(), [GAME[0], (9, 10)]);
+        assert!(tree.switch_variation(-1));
+        assert_eq!(tree.path(), [GAME[0], GAME[1]]);
+        assert!(tree.switch_variation(-1));
+        assert_eq!(tree.path(), [GAME[0], (13, 14)]);
+
+        // the first move of the game has no other move next to it
+        tree.go_to_main_line(1);
+        assert!(!tree.switch_variation(1));
+        tree.go_to_main_line(0);
+        assert!(!tree.switch_variation(1));
+    }
+}
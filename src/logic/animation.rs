@@ -0,0 +1,150 @@
+use std::f32::consts::PI;
+
+use bevy::{app::App, prelude::*};
+
+use crate::states::AppState;
+
+use super::{board::BoardOrientation, piece::from_index_to_srceen_position, SQUARE_SIZE};
+
+// moving pieces are drawn above the pieces that stand still
+const MOVING_PIECE_Z: f32 = 0.2;
+
+pub struct PieceAnimationPlugin;
+
+impl Plugin for PieceAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, animate_pieces.run_if(in_state(AppState::InGame)));
+    }
+}
+
+pub enum AnimationKind {
+    // moves the piece between two squares, leapers jump instead of sliding and the image is
+    // swapped half way for promotions
+    Slide {
+        from: usize,
+        to: usize,
+        jump: bool,
+        new_image: Option<Handle<Image>>,
+    },
+    // the piece changes in place
+    Change {
+        new_image: Option<Handle<Image>>,
+    },
+    // a captured piece disappears and is despawned at the end
+    FadeOut,
+}
+
+// added to a piece while it's being animated, the input is blocked as long as any piece has it
+#[derive(Component)]
+pub struct PieceAnimation {
+    kind: AnimationKind,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl PieceAnimation {
+    pub fn new(kind: AnimationKind, duration: f32) -> Self {
+        PieceAnimation {
+            kind,
+            elapsed: 0.0,
+            duration,
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+fn animate_pieces(
+    mut commands: Commands,
+    time: Res<Time>,
+    orientation: Res<BoardOrientation>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pieces: Query<(
+        Entity,
+        &mut Transform,
+        &Handle<ColorMaterial>,
+        &mut PieceAnimation,
+    )>,
+) {
+    for (entity, mut transform, material, mut animation) in &mut pieces {
+        animation.elapsed += time.delta_seconds();
+        let t = animation.progress();
+        let done = t >= 1.0;
+        // a smooth step, so the pieces speed up and slow down instead of moving at a constant speed
+        let eased = t * t * (3.0 - 2.0 * t);
+        // grows and shrinks back once over the course of the animation
+        let bump = (PI * t).sin();
+
+        match &animation.kind {
+            AnimationKind::Slide {
+                from,
+                to,
+                jump,
+                new_image,
+            } => {
+                let start = from_index_to_srceen_position(*from, orientation.flipped);
+                let end = from_index_to_srceen_position(*to, orientation.flipped);
+                let mut scale = 1.0;
+                if *jump {
+                    scale += 0.3 * bump;
+                }
+                if new_image.is_some() {
+                    scale += 0.2 * bump;
+                }
+                if t >= 0.5 {
+                    swap_image(&mut materials, material, new_image);
+                }
+                if done {
+                    transform.translation = end;
+                    transform.scale = Vec3::splat(SQUARE_SIZE);
+                } else {
+                    transform.translation =
+                        start.lerp(end, eased).truncate().extend(MOVING_PIECE_Z);
+                    transform.scale = Vec3::splat(SQUARE_SIZE * scale);
+                }
+            }
+            AnimationKind::Change { new_image } => {
+                if t >= 0.5 {
+                    swap_image(&mut materials, material, new_image);
+                }
+                let scale = if done { 1.0 } else { 1.0 - 0.5 * bump };
+                transform.scale = Vec3::splat(SQUARE_SIZE * scale);
+            }
+            AnimationKind::FadeOut => {
+                if let Some(material) = materials.get_mut(material) {
+                    material.color.set_a(1.0 - t);
+                }
+                if done {
+                    commands.entity(entity).despawn();
+                    continue;
+                }
+            }
+        }
+        if done {
+            commands.entity(entity).remove::<PieceAnimation>();
+        }
+    }
+}
+
+fn swap_image(
+    materials: &mut Assets<ColorMaterial>,
+    material: &Handle<ColorMaterial>,
+    new_image: &Option<Handle<Image>>,
+) {
+    let Some(image) = new_image else {
+        return;
+    };
+    // only touch the material once, so it isn't marked as changed on every frame
+    if materials.get(material).and_then(|m| m.texture.as_ref()) == Some(image) {
+        return;
+    }
+    if let Some(material) = materials.get_mut(material) {
+        material.texture = Some(image.clone());
+    }
+}
@@ -1,12 +1,24 @@
+use std::{sync::atomic::AtomicBool, time::Duration};
+
 use bevy::{app::App, math::vec2, prelude::*};
 use bevy_pkv::PkvStore;
+use serde::{Deserialize, Serialize};
 
 use crate::create_piece;
-use crate::{states::AppState, tuple_as};
+use crate::{
+    engine::{
+        eval::EvalParams,
+        search::{search, SearchLimits},
+        tt::TranspositionTable,
+    },
+    states::{AppState, BoardTheme, GameModeState, MoveAuthority, PlayerColor, TextInputFocused},
+    tuple_as,
+};
 
 use super::{
     piece::{MovementsRules, Piece, PieceTypes, Rules},
-    GRID_BLOCK_SIZE, SQUARE_SIZE, TILE_NUMBER,
+    zobrist::{hash_board, piece_key, side_key},
+    OnGameScreen, GRID_BLOCK_SIZE, SQUARE_SIZE, TILE_NUMBER,
 };
 
 pub struct BoardPlugin;
@@ -14,10 +26,30 @@ pub struct BoardPlugin;
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<BoardRecource>()
+            .init_resource::<BoardOrientation>()
+            .add_event::<BoardChanged>()
+            .add_event::<MoveRequested>()
+            .add_event::<BoardReset>()
+            .add_event::<GameEnded>()
+            .add_event::<LowTime>()
+            .init_resource::<MoveAuthority>()
+            .init_resource::<StartingPosition>()
+            .init_resource::<MoveHistory>()
             .add_systems(
                 OnEnter(AppState::InGame),
-                (setup_board, spawn_grid.after(setup_board)),
+                (orient_board, setup_board, spawn_grid.after(setup_board)),
             )
+            .add_systems(
+                Update,
+                (
+                    flip_board,
+                    update_labels.after(flip_board),
+                    check_game_end,
+                    record_moves,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(Update, apply_board_theme)
             .insert_resource(PkvStore::new("ChessPro", "ChessPro"));
     }
 }
@@ -26,17 +58,282 @@ impl Plugin for BoardPlugin {
 pub struct BoardRecource {
     pub grid: Vec<Option<Piece>>,
     pub turn: bool,
+    // set once the game is over, no more moves can be made after that
+    pub result: Option<GameResult>,
+    // the Zobrist hash of the pieces and the side to move, kept up to date by every move
+    pub hash: u64,
 }
 
+#[cfg(test)]
 impl BoardRecource {
+    // the pieces of the board as something tests can compare, pieces themselves can't be
+    pub(crate) fn pieces(&self) -> Vec<Option<(PieceTypes, bool, bool)>> {
+        self.grid
+            .iter()
+            .map(|piece| piece.map(|p| (p.get_type(), p.get_color(), p.has_moved())))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameResult {
+    // the color of the winner
+    Win(bool),
+    Draw,
+}
+
+impl GameResult {
+    // written the same way as in chess notation
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameResult::Win(true) => "1-0",
+            GameResult::Win(false) => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "1-0" => Some(GameResult::Win(true)),
+            "0-1" => Some(GameResult::Win(false)),
+            "1/2-1/2" => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameEndReason {
+    Checkmate,
+    Stalemate,
+    Timeout,
+    // the loser left a network game
+    Abandoned,
+}
+
+impl GameEndReason {
+    pub const ALL: [GameEndReason; 4] = [
+        GameEndReason::Checkmate,
+        GameEndReason::Stalemate,
+        GameEndReason::Timeout,
+        GameEndReason::Abandoned,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameEndReason::Checkmate => "checkmate",
+            GameEndReason::Stalemate => "stalemate",
+            GameEndReason::Timeout => "timeout",
+            GameEndReason::Abandoned => "abandoned",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        GameEndReason::ALL
+            .into_iter()
+            .find(|reason| reason.name() == name)
+    }
+}
+
+// sent once when the game is over
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GameEnded {
+    pub result: GameResult,
+    pub reason: GameEndReason,
+}
+
+// sent once per game for each player who is running out of time
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LowTime {
+    pub color: bool,
+}
+
+impl BoardRecource {
+    // the board at the start of a game, white to move
+    pub fn new_game() -> Self {
+        let mut board = BoardRecource {
+            grid: vec![None; TILE_NUMBER * TILE_NUMBER],
+            turn: true,
+            result: None,
+            hash: 0,
+        };
+        for i in 0..TILE_NUMBER {
+            board.grid[to_board_index(i, TILE_NUMBER - 2)] = create_piece!(white PieceTypes::Pawn);
+            board.grid[to_board_index(i, 1)] = create_piece!(black PieceTypes::Pawn);
+        }
+        board.grid[to_board_index(0, 0)] = create_piece!(black PieceTypes::Rook);
+        board.grid[to_board_index(TILE_NUMBER - 1, 0)] = create_piece!(black PieceTypes::Rook);
+        board.grid[to_board_index(TILE_NUMBER - 1, TILE_NUMBER - 1)] =
+            create_piece!(white PieceTypes::Rook);
+        board.grid[to_board_index(0, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Rook);
+
+        board.grid[to_board_index(1, 0)] = create_piece!(black PieceTypes::Bishop);
+        board.grid[to_board_index(TILE_NUMBER - 2, 0)] = create_piece!(black PieceTypes::Bishop);
+        board.grid[to_board_index(TILE_NUMBER - 2, TILE_NUMBER - 1)] =
+            create_piece!(white PieceTypes::Bishop);
+        board.grid[to_board_index(1, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Bishop);
+
+        board.grid[to_board_index(2, 0)] = create_piece!(black PieceTypes::Knight);
+        board.grid[to_board_index(TILE_NUMBER - 3, 0)] = create_piece!(black PieceTypes::Knight);
+        board.grid[to_board_index(TILE_NUMBER - 3, TILE_NUMBER - 1)] =
+            create_piece!(white PieceTypes::Knight);
+        board.grid[to_board_index(2, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Knight);
+
+        board.grid[to_board_index(3, 0)] = create_piece!(black PieceTypes::King);
+        board.grid[to_board_index(3, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::King);
+
+        board.grid[to_board_index(4, 0)] = create_piece!(black PieceTypes::Queen);
+        board.grid[to_board_index(4, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Queen);
+
+        board.grid[to_board_index(5, 0)] = create_piece!(black PieceTypes::Jester);
+        board.grid[to_board_index(5, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Jester);
+
+        board.grid[to_board_index(6, 0)] = create_piece!(black PieceTypes::GrandCommander);
+        board.grid[to_board_index(6, TILE_NUMBER - 1)] =
+            create_piece!(white PieceTypes::GrandCommander);
+
+        board.grid[to_board_index(7, 0)] = create_piece!(black PieceTypes::Amazon);
+        board.grid[to_board_index(7, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Amazon);
+
+        board.grid[to_board_index(8, 0)] = create_piece!(black PieceTypes::Abbess);
+        board.grid[to_board_index(8, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Abbess);
+
+        board.grid[to_board_index(9, 0)] = create_piece!(black PieceTypes::ShortRook);
+        board.grid[to_board_index(9, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::ShortRook);
+        board.hash = hash_board(&board);
+        board
+    }
+
     pub fn change_turn(&mut self) {
         self.turn = !self.turn;
+        self.hash ^= side_key();
+    }
+}
+
+// moves that are played before the game is shown, e.g. when watching a game that already
+// started, they are used up by the next game
+#[derive(Resource, Default, Debug, Clone)]
+pub struct StartingPosition {
+    pub moves: Vec<(usize, usize)>,
+}
+
+// the moves that led to the board from the start of the game, e.g. to analyze it afterwards
+#[derive(Resource, Default, Debug, Clone)]
+pub struct MoveHistory {
+    pub moves: Vec<(usize, usize)>,
+}
+
+// which way the board is drawn, when flipped black is at the bottom of the screen
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct BoardOrientation {
+    pub flipped: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelSide {
+    Bottom,
+    Top,
+    Left,
+    Right,
+}
+
+// a file (a-p) or rank (1-16) label drawn next to the board, `line` is the column for files
+// and the row for ranks
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BoardLabel {
+    line: usize,
+    side: LabelSide,
+}
+
+impl BoardLabel {
+    fn text(&self) -> String {
+        match self.side {
+            LabelSide::Bottom | LabelSide::Top => file_name(self.line).to_string(),
+            LabelSide::Left | LabelSide::Right => (TILE_NUMBER - self.line).to_string(),
+        }
+    }
+
+    fn position(&self, flipped: bool) -> Vec3 {
+        let line = if flipped {
+            TILE_NUMBER - 1 - self.line
+        } else {
+            self.line
+        };
+        let half_board = GRID_BLOCK_SIZE / 2.0;
+        let along = line as f32 - half_board;
+        // the board is drawn half a square off the center, see `spawn_grid`
+        let low_edge = -(half_board + 0.5 + LABEL_OFFSET) * SQUARE_SIZE;
+        let high_edge = (half_board - 0.5 + LABEL_OFFSET) * SQUARE_SIZE;
+        match self.side {
+            LabelSide::Bottom => Vec3::new(along * SQUARE_SIZE, low_edge, 0.0),
+            LabelSide::Top => Vec3::new(along * SQUARE_SIZE, high_edge, 0.0),
+            LabelSide::Left => Vec3::new(
+                low_edge,
+                (half_board - 1.0 - line as f32) * SQUARE_SIZE,
+                0.0,
+            ),
+            LabelSide::Right => Vec3::new(
+                high_edge,
+                (half_board - 1.0 - line as f32) * SQUARE_SIZE,
+                0.0,
+            ),
+        }
+    }
+}
+
+// distance of the labels from the edge of the board, in squares
+const LABEL_OFFSET: f32 = 0.3;
+
+// in singleplayer and network games the player's pieces are always at the bottom of the screen
+fn orient_board(
+    game_mode: Res<State<GameModeState>>,
+    player_color: Res<PlayerColor>,
+    mut orientation: ResMut<BoardOrientation>,
+) {
+    let own_side = matches!(
+        game_mode.get(),
+        GameModeState::Singleplayer | GameModeState::Network
+    );
+    orientation.flipped = own_side && !player_color.0;
+}
+
+fn flip_board(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    mut orientation: ResMut<BoardOrientation>,
+) {
+    if keys.just_pressed(KeyCode::KeyF) && !text_input.0 {
+        orientation.flipped = !orientation.flipped;
+    }
+}
+
+fn update_labels(
+    orientation: Res<BoardOrientation>,
+    mut labels: Query<(&mut Transform, &BoardLabel)>,
+) {
+    if !orientation.is_changed() {
+        return;
+    }
+    for (mut transform, label) in &mut labels {
+        transform.translation = label.position(orientation.flipped);
     }
 }
 
-fn setup_board(mut board: ResMut<BoardRecource>, mut pkv: ResMut<PkvStore>) {
-    board.turn = true;
-    board.grid = vec![None; TILE_NUMBER * TILE_NUMBER];
+pub fn setup_board(
+    mut board: ResMut<BoardRecource>,
+    mut starting_position: ResMut<StartingPosition>,
+    mut history: ResMut<MoveHistory>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    *board = BoardRecource::new_game();
+    history.moves.clear();
+    for (src, dst) in std::mem::take(&mut starting_position.moves) {
+        if board.make_move(src, dst).is_none() {
+            warn!("illegal starting move {}", move_name(src, dst));
+            break;
+        }
+        history.moves.push((src, dst));
+    }
     // if let Ok(saved_board) = pkv.get::<Vec<Option<Piece>>>("default_board") {
     //     board.grid = saved_board;
     // } else {
@@ -44,77 +341,145 @@ fn setup_board(mut board: ResMut<BoardRecource>, mut pkv: ResMut<PkvStore>) {
     //     pkv.set("board", &saved_board)
     //         .expect("failed to store user");
     // }
+}
 
-    for i in 0..TILE_NUMBER {
-        board.grid[to_board_index(i, TILE_NUMBER - 2)] = create_piece!(white PieceTypes::Pawn);
-        board.grid[to_board_index(i, 1)] = create_piece!(black PieceTypes::Pawn);
+// checks after every move if the side to move was checkmated or stalemated
+pub fn check_game_end(
+    mut board_events: EventReader<BoardChanged>,
+    mut board: ResMut<BoardRecource>,
+    mut game_ended: EventWriter<GameEnded>,
+) {
+    if board_events.is_empty() {
+        return;
     }
-    board.grid[to_board_index(0, 0)] = create_piece!(black PieceTypes::Rook);
-    board.grid[to_board_index(TILE_NUMBER - 1, 0)] = create_piece!(black PieceTypes::Rook);
-    board.grid[to_board_index(TILE_NUMBER - 1, TILE_NUMBER - 1)] =
-        create_piece!(white PieceTypes::Rook);
-    board.grid[to_board_index(0, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Rook);
-
-    board.grid[to_board_index(1, 0)] = create_piece!(black PieceTypes::Bishop);
-    board.grid[to_board_index(TILE_NUMBER - 2, 0)] = create_piece!(black PieceTypes::Bishop);
-    board.grid[to_board_index(TILE_NUMBER - 2, TILE_NUMBER - 1)] =
-        create_piece!(white PieceTypes::Bishop);
-    board.grid[to_board_index(1, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Bishop);
+    board_events.clear();
+    if board.result.is_some() {
+        return;
+    }
+    if let Some((result, reason)) = board.game_status() {
+        board.result = Some(result);
+        game_ended.send(GameEnded { result, reason });
+    }
+}
 
-    board.grid[to_board_index(2, 0)] = create_piece!(black PieceTypes::Knight);
-    board.grid[to_board_index(TILE_NUMBER - 3, 0)] = create_piece!(black PieceTypes::Knight);
-    board.grid[to_board_index(TILE_NUMBER - 3, TILE_NUMBER - 1)] =
-        create_piece!(white PieceTypes::Knight);
-    board.grid[to_board_index(2, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Knight);
+fn record_moves(mut board_events: EventReader<BoardChanged>, mut history: ResMut<MoveHistory>) {
+    for ev in board_events.read() {
+        history.moves.push((ev.src, ev.dst));
+    }
+}
 
-    board.grid[to_board_index(3, 0)] = create_piece!(black PieceTypes::King);
-    board.grid[to_board_index(3, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::King);
+fn apply_board_theme(theme: Res<BoardTheme>, mut squares: Query<(&mut Sprite, &BoardSquare)>) {
+    if !theme.is_changed() {
+        return;
+    }
+    let (white, black) = theme.colors();
+    for (mut sprite, square) in &mut squares {
+        sprite.color = if square.light { white } else { black };
+    }
+}
 
-    board.grid[to_board_index(4, 0)] = create_piece!(black PieceTypes::Queen);
-    board.grid[to_board_index(4, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Queen);
+pub fn to_board_index(x: usize, y: usize) -> usize {
+    TILE_NUMBER * y + x
+}
 
-    board.grid[to_board_index(5, 0)] = create_piece!(black PieceTypes::Jester);
-    board.grid[to_board_index(5, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Jester);
+pub fn to_cord_index(index: usize) -> (usize, usize) {
+    (index % TILE_NUMBER, index / TILE_NUMBER)
+}
 
-    board.grid[to_board_index(6, 0)] = create_piece!(black PieceTypes::GrandCommander);
-    board.grid[to_board_index(6, TILE_NUMBER - 1)] =
-        create_piece!(white PieceTypes::GrandCommander);
+pub fn file_name(x: usize) -> char {
+    (b'a' + x as u8) as char
+}
 
-    board.grid[to_board_index(7, 0)] = create_piece!(black PieceTypes::Amazon);
-    board.grid[to_board_index(7, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Amazon);
+// the opposite of `square_name`
+pub fn parse_square(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    let file = chars.next()?;
+    if !file.is_ascii_lowercase() {
+        return None;
+    }
+    let x = (file as u8 - b'a') as usize;
+    let rank: usize = chars.as_str().parse().ok()?;
+    if x >= TILE_NUMBER || rank == 0 || rank > TILE_NUMBER {
+        return None;
+    }
+    Some(to_board_index(x, TILE_NUMBER - rank))
+}
 
-    board.grid[to_board_index(8, 0)] = create_piece!(black PieceTypes::Abbess);
-    board.grid[to_board_index(8, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::Abbess);
+// a move is written as its source and destination squares, e.g. `e15e13`
+pub fn move_name(src: usize, dst: usize) -> String {
+    format!("{}{}", square_name(src), square_name(dst))
+}
 
-    board.grid[to_board_index(9, 0)] = create_piece!(black PieceTypes::ShortRook);
-    board.grid[to_board_index(9, TILE_NUMBER - 1)] = create_piece!(white PieceTypes::ShortRook);
+pub fn parse_move(name: &str) -> Option<(usize, usize)> {
+    // the destination starts at the second letter
+    let split = name
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_ascii_lowercase())?
+        .0;
+    let (src, dst) = name.split_at(split);
+    Some((parse_square(src)?, parse_square(dst)?))
 }
 
-pub fn to_board_index(x: usize, y: usize) -> usize {
-    TILE_NUMBER * y + x
+// the name of a square, files go from a to p left to right and ranks from 1 to 16 bottom to top
+// from white's point of view
+pub fn square_name(index: usize) -> String {
+    let (x, y) = to_cord_index(index);
+    format!("{}{}", file_name(x), TILE_NUMBER - y)
 }
 
-pub fn to_cord_index(index: usize) -> (usize, usize) {
-    (index % TILE_NUMBER, index / TILE_NUMBER)
+// a square of the board, light squares and dark squares are colored by the `BoardTheme`
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BoardSquare {
+    light: bool,
 }
 
-fn spawn_grid(mut commands: Commands) {
-    let white = Color::hex("eeeed2").unwrap();
-    let black = Color::hex("769656").unwrap();
+fn spawn_grid(mut commands: Commands, orientation: Res<BoardOrientation>, theme: Res<BoardTheme>) {
+    let (white, black) = theme.colors();
     for y in (GRID_BLOCK_SIZE as i32 / -2)..(GRID_BLOCK_SIZE as i32 / 2) {
         for x in (GRID_BLOCK_SIZE as i32 / -2)..(GRID_BLOCK_SIZE as i32 / 2) {
-            let color = if (x + y) % 2 == 0 { white } else { black };
+            let light = (x + y) % 2 == 0;
+            let color = if light { white } else { black };
             let transform =
                 Transform::from_xyz(x as f32 * SQUARE_SIZE, y as f32 * SQUARE_SIZE, 0.0);
-            commands.spawn(SpriteBundle {
-                transform,
-                sprite: Sprite {
-                    color,
-                    custom_size: Some(vec2(SQUARE_SIZE, SQUARE_SIZE)),
+            commands.spawn((
+                SpriteBundle {
+                    transform,
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(vec2(SQUARE_SIZE, SQUARE_SIZE)),
+                        ..Default::default()
+                    },
                     ..Default::default()
                 },
-                ..Default::default()
-            });
+                BoardSquare { light },
+                OnGameScreen,
+            ));
+        }
+    }
+
+    let text_style = TextStyle {
+        font_size: SQUARE_SIZE / 3.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+        ..default()
+    };
+    for line in 0..TILE_NUMBER {
+        for side in [
+            LabelSide::Bottom,
+            LabelSide::Top,
+            LabelSide::Left,
+            LabelSide::Right,
+        ] {
+            let label = BoardLabel { line, side };
+            commands.spawn((
+                Text2dBundle {
+                    text: Text::from_section(label.text(), text_style.clone()),
+                    transform: Transform::from_translation(label.position(orientation.flipped)),
+                    ..default()
+                },
+                label,
+                OnGameScreen,
+            ));
         }
     }
 }
@@ -128,33 +493,140 @@ pub enum PieceResult {
     Changed(PieceTypes, bool),
 }
 
+// what `make` changed on the board, for `unmake` to put it back
+#[derive(Debug, Clone, Copy)]
+pub struct MoveUndo {
+    src: usize,
+    dst: usize,
+    moved: Piece,
+    captured: Option<Piece>,
+    hash: u64,
+}
+
+// pawns become queens on the last rank of the opponent
+fn promotes(piece: &Piece, dst: usize) -> bool {
+    let (_, dst_row) = to_cord_index(dst);
+    piece.get_type() == PieceTypes::Pawn && (dst_row == 0 || dst_row == TILE_NUMBER - 1)
+}
+
+// sent after a move was made on the board, with the pieces that were affected by it
+#[derive(Event, Debug, Clone)]
+pub struct BoardChanged {
+    pub src: usize,
+    pub dst: usize,
+    pub results: Vec<(Entity, PieceResult)>,
+}
+
+// sent when the whole board was replaced during a game, e.g. after resyncing a network game, so
+// everything drawn from it is rebuilt
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BoardReset;
+
+// a move the player wants to make, it's only played if it's legal
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MoveRequested {
+    pub src: usize,
+    pub dst: usize,
+}
+
+// plays the requested moves on the board, unless another computer decides which moves are legal
+pub fn apply_move_requests(
+    mut requests: EventReader<MoveRequested>,
+    mut board: ResMut<BoardRecource>,
+    mut board_changed: EventWriter<BoardChanged>,
+    authority: Res<MoveAuthority>,
+) {
+    if *authority != MoveAuthority::Local {
+        return;
+    }
+    for request in requests.read() {
+        board.play(request.src, request.dst, &mut board_changed);
+    }
+}
+
 impl BoardRecource {
-    pub fn move_piece(&mut self, src: usize, dst: usize) -> Vec<(Entity, PieceResult)> {
+    // makes the move and tells everyone about it, returns false if the move isn't legal
+    pub fn play(
+        &mut self,
+        src: usize,
+        dst: usize,
+        board_changed: &mut EventWriter<BoardChanged>,
+    ) -> bool {
+        let Some(results) = self.move_piece(src, dst) else {
+            return false;
+        };
+        board_changed.send(BoardChanged { src, dst, results });
+        true
+    }
+
+    // plays the move if it's legal, returns the entities of the pieces that were affected by it
+    pub fn move_piece(&mut self, src: usize, dst: usize) -> Option<Vec<(Entity, PieceResult)>> {
         let mut new_pieces_position = Vec::new();
+        let (src_piece, dst_piece) = (self.grid[src], self.grid[dst]);
+        let move_result = self.make_move(src, dst)?;
+
+        // boards without sprites, e.g. on the server, have no entities to report
+        if let Some(src_id) = src_piece.and_then(|piece| piece.get_id()) {
+            new_pieces_position.push((src_id, move_result[0]));
+        }
+        if let Some(dst_id) = dst_piece.and_then(|piece| piece.get_id()) {
+            new_pieces_position.push((dst_id, move_result[1]));
+        }
+        Some(new_pieces_position)
+    }
+
+    // plays the move if it's legal, returns what happened to the moving and the captured piece
+    pub fn make_move(&mut self, src: usize, dst: usize) -> Option<[PieceResult; 2]> {
         let (move_check, move_result) = self.handle_move(src, dst);
 
         if !move_check {
-            return new_pieces_position;
+            return None;
         }
         if self.will_be_in_check(src, dst) {
-            return new_pieces_position;
+            return None;
         }
-        self.grid[src].as_mut().unwrap().set_has_moved();
-        self.change_turn();
+        self.make(src, dst);
 
-        let src_id = self.grid[src].unwrap().get_id().unwrap();
-        new_pieces_position.push((src_id, move_result[0]));
-        if let Some(piece) = self.grid[dst] {
-            let dst_id = piece.get_id().unwrap();
-            new_pieces_position.push((dst_id, move_result[1]));
-            self.grid[dst] = None;
+        Some(move_result)
+    }
+
+    // Plays a move without checking it, only for moves known to be legal, e.g. from
+    // `legal_moves`. The search makes and unmakes its moves on one board instead of copying it
+    pub fn make(&mut self, src: usize, dst: usize) -> MoveUndo {
+        let moved = self.grid[src].expect("a legal move starts on a piece");
+        let captured = self.grid[dst];
+        let undo = MoveUndo {
+            src,
+            dst,
+            moved,
+            captured,
+            hash: self.hash,
+        };
+
+        let mut piece = moved;
+        piece.set_has_moved();
+        if promotes(&piece, dst) {
+            piece.promote(PieceTypes::Queen);
+        }
+        self.hash ^= piece_key(src, &moved) ^ piece_key(dst, &piece);
+        if let Some(captured) = captured {
+            self.hash ^= piece_key(dst, &captured);
         }
-        self.grid.swap(src, dst);
+        self.grid[src] = None;
+        self.grid[dst] = Some(piece);
+        self.change_turn();
+        undo
+    }
 
-        new_pieces_position
+    // takes back the last move that was made
+    pub fn unmake(&mut self, undo: MoveUndo) {
+        self.grid[undo.src] = Some(undo.moved);
+        self.grid[undo.dst] = undo.captured;
+        self.turn = !self.turn;
+        self.hash = undo.hash;
     }
 
-    pub fn get_possible_moves(&mut self, src: usize) -> Vec<usize> {
+    pub fn get_possible_moves(&self, src: usize) -> Vec<usize> {
         let mut possible_moves = Vec::new();
         for i in 0..(TILE_NUMBER * TILE_NUMBER) {
             if self.is_move_legal(src, i) && !self.will_be_in_check(src, i) {
@@ -173,10 +645,10 @@ impl BoardRecource {
         if let Some(piece) = &self.grid[src] {
             match piece.get_type() {
                 PieceTypes::Pawn => {
-                    if dst_row == 0 || dst_row == (TILE_NUMBER as i32 - 1) {
+                    // the pawn itself is promoted by `make`
+                    if promotes(piece, dst) {
                         src_result =
                             PieceResult::Promoted(dst, PieceTypes::Queen, piece.get_color());
-                        self.grid[src].unwrap().promote(PieceTypes::Queen);
                     }
                 }
                 PieceTypes::Rook => {}
@@ -225,7 +697,7 @@ impl BoardRecource {
                 return false;
             }
 
-            if !rules.step_over_rule && !can_step_over(self.grid.clone(), src, dst, true) {
+            if !rules.step_over_rule && !can_step_over(&self.grid, src, dst, true) {
                 return false;
             }
 
@@ -267,6 +739,76 @@ impl BoardRecource {
         false
     }
 
+    pub fn is_in_check(&self, color: bool) -> bool {
+        let king_pos = self.grid.iter().position(|p| {
+            if let Some(piece) = p {
+                (piece.get_color() == color) && (piece.get_type() == PieceTypes::King)
+            } else {
+                false
+            }
+        });
+        let Some(king) = king_pos else {
+            return false;
+        };
+        let mut temp_board = self.clone();
+        temp_board.turn = !color;
+        (0..(TILE_NUMBER * TILE_NUMBER)).any(|i| temp_board.is_move_legal(i, king))
+    }
+
+    // every legal move of the side to move
+    pub fn legal_moves(&self) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        for src in 0..(TILE_NUMBER * TILE_NUMBER) {
+            if matches!(self.grid[src], Some(piece) if piece.get_color() == self.turn) {
+                moves.extend(
+                    self.get_possible_moves(src)
+                        .into_iter()
+                        .map(|dst| (src, dst)),
+                );
+            }
+        }
+        moves
+    }
+
+    // The moves that follow the rules of the pieces but may leave the own king in check, much
+    // faster to find than `legal_moves`. The playouts of the MCTS play them and end once a king
+    // is taken instead of mated
+    pub fn pseudo_legal_moves(&self) -> Vec<(usize, usize)> {
+        let mut moves = Vec::new();
+        for src in 0..(TILE_NUMBER * TILE_NUMBER) {
+            if matches!(self.grid[src], Some(piece) if piece.get_color() == self.turn) {
+                moves.extend(
+                    (0..(TILE_NUMBER * TILE_NUMBER))
+                        .filter(|&dst| self.is_move_legal(src, dst))
+                        .map(|dst| (src, dst)),
+                );
+            }
+        }
+        moves
+    }
+
+    pub fn has_legal_moves(&self) -> bool {
+        for i in 0..(TILE_NUMBER * TILE_NUMBER) {
+            let own_piece = matches!(self.grid[i], Some(piece) if piece.get_color() == self.turn);
+            if own_piece && !self.get_possible_moves(i).is_empty() {
+                return true;
+            }
+        }
+        false
+    }
+
+    // the result of the game if the side to move has no legal moves left
+    pub fn game_status(&self) -> Option<(GameResult, GameEndReason)> {
+        if self.has_legal_moves() {
+            return None;
+        }
+        if self.is_in_check(self.turn) {
+            Some((GameResult::Win(!self.turn), GameEndReason::Checkmate))
+        } else {
+            Some((GameResult::Draw, GameEndReason::Stalemate))
+        }
+    }
+
     fn will_be_in_check(&self, src: usize, dst: usize) -> bool {
         let mut temp_board = self.clone();
         if !temp_board.is_move_legal(src, dst) {
@@ -299,21 +841,32 @@ impl BoardRecource {
     }
 }
 
-pub fn get_best_next_move(root_board: &mut BoardRecource) -> Option<usize> {
-    let mut cloned_board = root_board.clone();
-    let mut best_move = 0_usize;
-    for (index, piece_iter) in cloned_board.clone().grid.iter().enumerate() {
-        if let Some(piece) = piece_iter {
-            let possible_moves = cloned_board.get_possible_moves(index);
-            if let Some(pos) = possible_moves.first().copied() {
-                return Some(pos);
-            }
-        }
-    }
-    None
+// the built-in AI, the move it finds after thinking for `think_time` on `threads` threads with the
+// evaluation `eval`. `tt` is best kept for the whole game, the search of every move starts from
+// what the ones before found
+pub fn get_best_next_move(
+    board: &BoardRecource,
+    think_time: Duration,
+    tt: &TranspositionTable,
+    eval: &EvalParams,
+    threads: usize,
+) -> Option<(usize, usize)> {
+    let limits = SearchLimits {
+        time: Some(think_time),
+        ..default()
+    };
+    search(
+        board,
+        limits,
+        tt,
+        eval,
+        threads,
+        &AtomicBool::new(false),
+        |_| {},
+    )
 }
 
-pub fn can_step_over(board: Vec<Option<Piece>>, from: usize, to: usize, start: bool) -> bool {
+pub fn can_step_over(board: &[Option<Piece>], from: usize, to: usize, start: bool) -> bool {
     if from == to {
         return true;
     }
@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use bevy::{app::App, prelude::*};
+
+use crate::states::{AppState, BoardInputBlocked, GameModeState, MoveAuthority, TimeControl};
+
+use super::{
+    board::{
+        BoardChanged, BoardOrientation, BoardRecource, GameEndReason, GameEnded, GameResult,
+        LowTime,
+    },
+    OnGameScreen, SQUARE_SIZE, TILE_NUMBER,
+};
+
+// below this a player is warned that they are running out of time
+const LOW_TIME: Duration = Duration::from_secs(30);
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), start_clock)
+            .add_systems(
+                Update,
+                spawn_clock_texts
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(resource_added::<GameClock>),
+            )
+            .add_systems(
+                Update,
+                (add_increment, tick_clock, update_clock_texts)
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(resource_exists::<GameClock>)
+                    .run_if(not(untimed)),
+            )
+            // a finished game can be turned into an analysis
+            .add_systems(
+                Update,
+                stop_clock
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(resource_exists::<GameClock>)
+                    .run_if(untimed),
+            )
+            .add_systems(OnExit(AppState::InGame), remove_clock);
+    }
+}
+
+// there is no clock in an analysis, in puzzles or in the tutorial
+fn untimed(game_mode: Res<State<GameModeState>>) -> bool {
+    matches!(
+        game_mode.get(),
+        GameModeState::Analysis | GameModeState::Puzzle | GameModeState::Tutorial
+    )
+}
+
+// only exists during games that are played with a clock
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct GameClock {
+    pub white: Duration,
+    pub black: Duration,
+    pub base: Duration,
+    pub increment: Duration,
+    // whether each side was already warned about being low on time, white first
+    low_time_warned: [bool; 2],
+}
+
+impl GameClock {
+    pub fn new(base: Duration, increment: Duration) -> Self {
+        GameClock {
+            white: base,
+            black: base,
+            base,
+            increment,
+            low_time_warned: [false; 2],
+        }
+    }
+
+    // the clock of a game whose moves another computer decides, it only shows the times that
+    // computer sends
+    pub fn showing(white: Duration, black: Duration) -> Self {
+        GameClock {
+            white,
+            black,
+            ..GameClock::new(Duration::ZERO, Duration::ZERO)
+        }
+    }
+
+    pub fn remaining(&self, color: bool) -> Duration {
+        if color {
+            self.white
+        } else {
+            self.black
+        }
+    }
+
+    fn remaining_mut(&mut self, color: bool) -> &mut Duration {
+        if color {
+            &mut self.white
+        } else {
+            &mut self.black
+        }
+    }
+
+    // runs the clock of this player, returns the time they have left
+    pub fn tick(&mut self, color: bool, delta: Duration) -> Duration {
+        let remaining = self.remaining_mut(color);
+        *remaining = remaining.saturating_sub(delta);
+        *remaining
+    }
+
+    pub fn add_increment(&mut self, color: bool) {
+        let increment = self.increment;
+        *self.remaining_mut(color) += increment;
+    }
+}
+
+#[derive(Component)]
+struct ClockText {
+    color: bool,
+}
+
+// when another computer decides the moves the clock comes from there, see `Message::Clock`
+fn start_clock(
+    mut commands: Commands,
+    time_control: Res<TimeControl>,
+    game_mode: Res<State<GameModeState>>,
+    authority: Res<MoveAuthority>,
+) {
+    let timed = *authority == MoveAuthority::Local && !untimed(game_mode);
+    match time_control.0 {
+        Some((minutes, increment)) if timed => {
+            commands.insert_resource(GameClock::new(
+                Duration::from_secs(minutes * 60),
+                Duration::from_secs(increment),
+            ));
+        }
+        _ => commands.remove_resource::<GameClock>(),
+    }
+}
+
+fn stop_clock(mut commands: Commands, texts: Query<Entity, With<ClockText>>) {
+    commands.remove_resource::<GameClock>();
+    for entity in &texts {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn remove_clock(mut commands: Commands) {
+    commands.remove_resource::<GameClock>();
+}
+
+fn add_increment(
+    mut board_events: EventReader<BoardChanged>,
+    board: Res<BoardRecource>,
+    mut clock: ResMut<GameClock>,
+) {
+    for _ in board_events.read() {
+        // the turn already passed to the other side, so the increment goes to the one who isn't
+        // on the move
+        clock.add_increment(!board.turn);
+    }
+}
+
+fn tick_clock(
+    time: Res<Time>,
+    mut board: ResMut<BoardRecource>,
+    mut clock: ResMut<GameClock>,
+    mut low_time: EventWriter<LowTime>,
+    mut game_ended: EventWriter<GameEnded>,
+    input_blocked: Res<BoardInputBlocked>,
+    authority: Res<MoveAuthority>,
+) {
+    // the clock doesn't run while the player can't see the board
+    if board.result.is_some() || input_blocked.0 {
+        return;
+    }
+    let color = board.turn;
+    let remaining = clock.tick(color, time.delta());
+
+    let warned = &mut clock.low_time_warned[usize::from(!color)];
+    if remaining < LOW_TIME && !*warned {
+        *warned = true;
+        low_time.send(LowTime { color });
+    }
+    // when another computer decides the moves it also decides when the time is up
+    if remaining.is_zero() && *authority == MoveAuthority::Local {
+        let result = GameResult::Win(!color);
+        board.result = Some(result);
+        game_ended.send(GameEnded {
+            result,
+            reason: GameEndReason::Timeout,
+        });
+    }
+}
+
+fn spawn_clock_texts(mut commands: Commands) {
+    for color in [true, false] {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font_size: SQUARE_SIZE * 0.8,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+                ..default()
+            },
+            ClockText { color },
+            OnGameScreen,
+        ));
+    }
+}
+
+// the clock of the player at the bottom of the board is drawn next to the bottom of the board
+fn update_clock_texts(
+    clock: Res<GameClock>,
+    orientation: Res<BoardOrientation>,
+    mut texts: Query<(&mut Text, &mut Transform, &ClockText)>,
+) {
+    let bottom_color = !orientation.flipped;
+    let x = (TILE_NUMBER as f32 / 2.0 + 2.0) * SQUARE_SIZE;
+    let y = (TILE_NUMBER as f32 / 2.0 - 2.0) * SQUARE_SIZE;
+    for (mut text, mut transform, clock_text) in &mut texts {
+        let seconds = clock.remaining(clock_text.color).as_secs();
+        text.sections[0].value = format!("{}:{:02}", seconds / 60, seconds % 60);
+        transform.translation = if clock_text.color == bottom_color {
+            Vec3::new(x, -y, 0.0)
+        } else {
+            Vec3::new(x, y, 0.0)
+        };
+    }
+}
@@ -0,0 +1,203 @@
+use super::{
+    board::{to_board_index, BoardRecource},
+    piece::{Piece, PieceTypes, Rules},
+    zobrist::hash_board,
+    TILE_NUMBER,
+};
+
+// Positions written the way chess engines and their GUIs expect them, e.g. the start position:
+//   rbnkqjgaes3nbr/pppppppppppppppp/16/.../PPPPPPPPPPPPPPPP/RBNKQJGAES3NBR w - - 0 1
+// ranks go from 16 down to 1, white pieces are upper case and black ones lower case. There is no
+// castling or en passant, so those fields are always `-`. The double steps of the pieces that
+// move like pawns depend on whether they moved already, which FEN can't tell, so a piece counts
+// as unmoved as long as it stands on the two home ranks of its side
+pub const START_FEN: &str = "rbnkqjgaes3nbr/pppppppppppppppp/16/16/16/16/16/16/16/16/16/16/16/16/PPPPPPPPPPPPPPPP/RBNKQJGAES3NBR w - - 0 1";
+
+pub fn piece_letter(piece_type: PieceTypes) -> char {
+    match piece_type {
+        PieceTypes::Pawn => 'p',
+        PieceTypes::Rook => 'r',
+        PieceTypes::Knight => 'n',
+        PieceTypes::Bishop => 'b',
+        PieceTypes::Queen => 'q',
+        PieceTypes::King => 'k',
+        PieceTypes::Jester => 'j',
+        PieceTypes::Amazon => 'a',
+        PieceTypes::GrandCommander => 'g',
+        PieceTypes::Abbess => 'e',
+        PieceTypes::ShortRook => 's',
+    }
+}
+
+pub fn parse_piece_letter(letter: char) -> Option<PieceTypes> {
+    let piece_type = match letter.to_ascii_lowercase() {
+        'p' => PieceTypes::Pawn,
+        'r' => PieceTypes::Rook,
+        'n' => PieceTypes::Knight,
+        'b' => PieceTypes::Bishop,
+        'q' => PieceTypes::Queen,
+        'k' => PieceTypes::King,
+        'j' => PieceTypes::Jester,
+        'a' => PieceTypes::Amazon,
+        'g' => PieceTypes::GrandCommander,
+        'e' => PieceTypes::Abbess,
+        's' => PieceTypes::ShortRook,
+        _ => return None,
+    };
+    Some(piece_type)
+}
+
+// the two ranks each side starts on, y = 0 is black's back rank
+pub fn on_home_ranks(y: usize, color: bool) -> bool {
+    if color {
+        y >= TILE_NUMBER - 2
+    } else {
+        y < 2
+    }
+}
+
+impl BoardRecource {
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::with_capacity(TILE_NUMBER);
+        for y in 0..TILE_NUMBER {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for x in 0..TILE_NUMBER {
+                match self.grid[to_board_index(x, y)] {
+                    None => empty += 1,
+                    Some(piece) => {
+                        if empty > 0 {
+                            rank += &empty.to_string();
+                            empty = 0;
+                        }
+                        let letter = piece_letter(piece.get_type());
+                        rank.push(if piece.get_color() {
+                            letter.to_ascii_uppercase()
+                        } else {
+                            letter
+                        });
+                    }
+                }
+            }
+            if empty > 0 {
+                rank += &empty.to_string();
+            }
+            ranks.push(rank);
+        }
+        let turn = if self.turn { "w" } else { "b" };
+        format!("{} {} - - 0 1", ranks.join("/"), turn)
+    }
+
+    // only the pieces and the side to move are read, the other fields are optional
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or("empty FEN")?;
+        let turn = match fields.next().unwrap_or("w") {
+            "w" => true,
+            "b" => false,
+            other => return Err(format!("unknown side to move: {}", other)),
+        };
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != TILE_NUMBER {
+            return Err(format!(
+                "expected {} ranks, found {}",
+                TILE_NUMBER,
+                ranks.len()
+            ));
+        }
+        let mut board = BoardRecource {
+            grid: vec![None; TILE_NUMBER * TILE_NUMBER],
+            turn,
+            result: None,
+            hash: 0,
+        };
+        for (y, rank) in ranks.into_iter().enumerate() {
+            let mut x = 0;
+            let mut empty = 0;
+            for c in rank.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    empty = empty * 10 + digit as usize;
+                    continue;
+                }
+                x += empty;
+                empty = 0;
+                let piece_type =
+                    parse_piece_letter(c).ok_or_else(|| format!("unknown piece: {}", c))?;
+                if x >= TILE_NUMBER {
+                    return Err(format!("rank {} is too long", TILE_NUMBER - y));
+                }
+                let color = c.is_ascii_uppercase();
+                let mut piece = Piece::new(piece_type, color, Rules::get_rules(piece_type));
+                if !on_home_ranks(y, color) {
+                    piece.set_has_moved();
+                }
+                board.grid[to_board_index(x, y)] = Some(piece);
+                x += 1;
+            }
+            if x + empty != TILE_NUMBER {
+                return Err(format!(
+                    "rank {} has {} squares",
+                    TILE_NUMBER - y,
+                    x + empty
+                ));
+            }
+        }
+        board.hash = hash_board(&board);
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the start position with its third rank from the top replaced by `rank`
+    fn with_rank(rank: &str) -> String {
+        let mut ranks: Vec<&str> = START_FEN.split(' ').next().unwrap().split('/').collect();
+        ranks[2] = rank;
+        format!("{} w - - 0 1", ranks.join("/"))
+    }
+
+    #[test]
+    fn start_fen_is_the_new_game() {
+        let board = BoardRecource::from_fen(START_FEN).unwrap();
+        let new_game = BoardRecource::new_game();
+        assert_eq!(board.pieces(), new_game.pieces());
+        assert_eq!(board.turn, new_game.turn);
+        assert_eq!(board.hash, new_game.hash);
+        assert_eq!(new_game.to_fen(), START_FEN);
+    }
+
+    #[test]
+    fn mid_game_positions_round_trip() {
+        for fen in [
+            "rbnkqjgaes3nbr/pppppppp1ppppppp/16/8p7/16/16/16/16/16/16/16/16/7P8/16/PPPPPPP1PPPPPPPP/RBNKQJGAES3NBR b - - 0 1",
+            "16/3k12/16/16/16/16/16/7A8/16/16/16/16/16/16/3K12/16 w - - 0 1",
+        ] {
+            assert_eq!(BoardRecource::from_fen(fen).unwrap().to_fen(), fen);
+        }
+
+        let mut board = BoardRecource::new_game();
+        for _ in 0..12 {
+            let moves = board.legal_moves();
+            let (src, dst) = moves[moves.len() / 2];
+            board.make_move(src, dst).unwrap();
+            let fen = board.to_fen();
+            assert_eq!(BoardRecource::from_fen(&fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn malformed_ranks_are_errors() {
+        assert!(BoardRecource::from_fen(&with_rank("16")).is_ok());
+        // too long
+        assert!(BoardRecource::from_fen(&with_rank("17")).is_err());
+        assert!(BoardRecource::from_fen(&with_rank("16p")).is_err());
+        // too short
+        assert!(BoardRecource::from_fen(&with_rank("15")).is_err());
+        assert!(BoardRecource::from_fen(&with_rank("pppp")).is_err());
+        // unknown letter
+        assert!(BoardRecource::from_fen(&with_rank("15x")).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    engine::{
+        book::Variety,
+        player::{AiStyle, BuiltInAi, ComputerPlayer},
+    },
+    states::{AppState, GameModeState, HintLimit, PlayerColor, SearchThreads, TextInputFocused},
+};
+
+use super::{
+    ai::load_eval,
+    board::{BoardOrientation, BoardRecource},
+    piece::from_index_to_srceen_position,
+    OnGameScreen, SQUARE_SIZE,
+};
+
+// how long the AI thinks about a hint
+const HINT_TIME: Duration = Duration::from_millis(1500);
+const HINT_COLOR: Color = Color::rgb(0.2, 0.8, 1.0);
+
+// In singleplayer the player can ask the AI for the best move with the hint button or H. The
+// first hint for a position shows the piece to move, asking again shows where it goes, which
+// doesn't cost another hint
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hints>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    reset_hints,
+                    spawn_hint_button.run_if(in_state(GameModeState::Singleplayer)),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (ask_for_hint, receive_hint, update_hint_button, draw_hint)
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Singleplayer)),
+            )
+            .add_systems(OnExit(AppState::InGame), stop_hint_helper)
+            .add_systems(
+                OnExit(GameModeState::Singleplayer),
+                (stop_hint_helper, despawn_hint_button),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Hints {
+    // how many the player asked for this game
+    pub used: u32,
+    // the hash of the position of the last hint
+    position: Option<u64>,
+    // the move the AI found for it, None while it's thinking
+    mv: Option<(usize, usize)>,
+    // false while only the piece to move is shown
+    show_target: bool,
+}
+
+impl Hints {
+    pub fn left(&self, limit: HintLimit) -> Option<u32> {
+        limit.0.map(|limit| limit.saturating_sub(self.used))
+    }
+}
+
+// the AI that finds the hints, started with the first one
+#[derive(Resource)]
+struct HintHelper(ComputerPlayer);
+
+#[derive(Component)]
+struct HintButton;
+
+fn reset_hints(mut hints: ResMut<Hints>) {
+    *hints = Hints::default();
+}
+
+fn hint_button_text(hints: &Hints, limit: HintLimit) -> String {
+    match hints.left(limit) {
+        Some(left) => format!("Hint ({} left)", left),
+        None => "Hint".to_string(),
+    }
+}
+
+fn spawn_hint_button(mut commands: Commands, hints: Res<Hints>, limit: Res<HintLimit>) {
+    if limit.0 == Some(0) {
+        return;
+    }
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                ..default()
+            },
+            HintButton,
+            OnGameScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                hint_button_text(&hints, *limit),
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ask_for_hint(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<HintButton>)>,
+    board: Res<BoardRecource>,
+    player_color: Res<PlayerColor>,
+    limit: Res<HintLimit>,
+    threads: Res<SearchThreads>,
+    mut hints: ResMut<Hints>,
+    helper: Option<ResMut<HintHelper>>,
+) {
+    let key_pressed = keys.just_pressed(KeyCode::KeyH) && !text_input.0;
+    let button_pressed = buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    if !key_pressed && !button_pressed {
+        return;
+    }
+    if board.turn != player_color.0 || board.result.is_some() {
+        return;
+    }
+    if hints.position == Some(board.hash) {
+        if hints.mv.is_some() {
+            hints.show_target = true;
+        }
+        return;
+    }
+    // the hint for an earlier position is still being looked for
+    if helper.as_ref().is_some_and(|helper| helper.0.is_thinking()) {
+        return;
+    }
+    if hints.left(*limit) == Some(0) {
+        return;
+    }
+
+    *hints = Hints {
+        used: hints.used + 1,
+        position: Some(board.hash),
+        mv: None,
+        show_target: false,
+    };
+    match helper {
+        Some(mut helper) => helper.0.request_move(&board),
+        None => {
+            let built_in = BuiltInAi {
+                style: AiStyle::AlphaBeta,
+                threads: threads.0,
+                book: None,
+                variety: Variety::Off,
+                eval: load_eval(),
+            };
+            let mut helper = ComputerPlayer::start(None, HINT_TIME, built_in);
+            helper.request_move(&board);
+            commands.insert_resource(HintHelper(helper));
+        }
+    }
+}
+
+fn receive_hint(
+    helper: Option<ResMut<HintHelper>>,
+    board: Res<BoardRecource>,
+    mut hints: ResMut<Hints>,
+) {
+    let Some(mut helper) = helper else {
+        return;
+    };
+    let Some(hint) = helper.0.poll() else {
+        return;
+    };
+    // the player may have moved in the meantime
+    if hints.position == Some(board.hash) {
+        hints.mv = hint.mv;
+    }
+}
+
+fn update_hint_button(
+    hints: Res<Hints>,
+    limit: Res<HintLimit>,
+    buttons: Query<&Children, With<HintButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !hints.is_changed() {
+        return;
+    }
+    for children in &buttons {
+        if let Ok(mut text) = texts.get_mut(children[0]) {
+            text.sections[0].value = hint_button_text(&hints, *limit);
+        }
+    }
+}
+
+// a frame around the piece and, once asked again, around the square it goes to
+fn draw_hint(
+    hints: Res<Hints>,
+    board: Res<BoardRecource>,
+    orientation: Res<BoardOrientation>,
+    mut gizmos: Gizmos,
+) {
+    let Some((src, dst)) = hints.mv else {
+        return;
+    };
+    if hints.position != Some(board.hash) {
+        return;
+    }
+    let mut squares = vec![src];
+    if hints.show_target {
+        squares.push(dst);
+    }
+    for square in squares {
+        gizmos.rect_2d(
+            from_index_to_srceen_position(square, orientation.flipped).truncate(),
+            0.0,
+            Vec2::splat(SQUARE_SIZE * 0.95),
+            HINT_COLOR,
+        );
+    }
+}
+
+fn stop_hint_helper(mut commands: Commands) {
+    commands.remove_resource::<HintHelper>();
+}
+
+fn despawn_hint_button(mut commands: Commands, buttons: Query<Entity, With<HintButton>>) {
+    for entity in &buttons {
+        commands.entity(entity).despawn_recursive();
+    }
+}
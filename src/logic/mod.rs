@@ -2,15 +2,34 @@ use bevy::{
     app::{App, Plugin, Startup},
     core_pipeline::core_2d::Camera2dBundle,
     ecs::system::Commands,
+    prelude::*,
 };
 
-use self::{board::BoardPlugin, piece::PiecePlugin};
+use crate::states::{AppState, BoardInputBlocked};
 
+use self::{
+    ai::AiPlugin, analysis::AnalysisPlugin, animation::PieceAnimationPlugin, board::BoardPlugin,
+    clock::ClockPlugin, hint::HintPlugin, piece::PiecePlugin, puzzle_player::PuzzlePlugin,
+    review::ReviewPlugin, tutorial::TutorialPlugin,
+};
+
+pub mod ai;
+pub mod analysis;
+pub mod animation;
 pub mod board;
+pub mod clock;
+pub mod fen;
+pub mod hint;
 pub mod piece;
+pub mod puzzle;
+pub mod puzzle_player;
+pub mod record;
+pub mod review;
+pub mod tutorial;
+pub mod zobrist;
 
 const GRID_BLOCK_SIZE: f32 = 16.0;
-const TILE_NUMBER: usize = GRID_BLOCK_SIZE as usize;
+pub const TILE_NUMBER: usize = GRID_BLOCK_SIZE as usize;
 const GRID_SIZE: f32 = 840.0;
 const SQUARE_SIZE: f32 = GRID_SIZE / GRID_BLOCK_SIZE;
 
@@ -18,7 +37,34 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(BoardPlugin).add_plugins(PiecePlugin);
+        app.add_plugins(BoardPlugin)
+            .add_plugins(PiecePlugin)
+            .add_plugins(ClockPlugin)
+            .add_plugins(PieceAnimationPlugin)
+            .add_plugins(AiPlugin)
+            .add_plugins(HintPlugin)
+            .add_plugins(AnalysisPlugin)
+            .add_plugins(ReviewPlugin)
+            .add_plugins(PuzzlePlugin)
+            .add_plugins(TutorialPlugin)
+            .init_resource::<BoardInputBlocked>()
+            .add_systems(OnEnter(AppState::InGame), unblock_board_input)
+            .add_systems(OnExit(AppState::InGame), despawn_game_entities);
+    }
+}
+
+// Tag component used to tag every entity of the game itself: the board, its labels, the pieces
+// and everything drawn on them
+#[derive(Component)]
+pub struct OnGameScreen;
+
+fn unblock_board_input(mut blocked: ResMut<BoardInputBlocked>) {
+    blocked.0 = false;
+}
+
+fn despawn_game_entities(to_despawn: Query<Entity, With<OnGameScreen>>, mut commands: Commands) {
+    for entity in &to_despawn {
+        commands.entity(entity).despawn_recursive();
     }
 }
 
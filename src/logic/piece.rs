@@ -1,10 +1,16 @@
-use crate::states::AppState;
+use crate::states::{
+    AnimationSpeed, AppState, BoardInputBlocked, LocalControl, MoveIndicatorStyle,
+};
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    board::{get_best_next_move, BoardRecource, PieceResult},
-    GRID_BLOCK_SIZE, GRID_SIZE, SQUARE_SIZE, TILE_NUMBER,
+    animation::{AnimationKind, PieceAnimation},
+    board::{
+        apply_move_requests, setup_board, BoardChanged, BoardOrientation, BoardRecource,
+        BoardReset, MoveRequested, PieceResult,
+    },
+    OnGameScreen, GRID_BLOCK_SIZE, GRID_SIZE, SQUARE_SIZE, TILE_NUMBER,
 };
 use bevy::{
     app::App,
@@ -22,14 +28,25 @@ impl Plugin for PiecePlugin {
             OnEnter(AppState::InGame),
             (
                 init_pieces_recources,
-                spawn_pieces.after(init_pieces_recources),
+                spawn_pieces.after(init_pieces_recources).after(setup_board),
+                reset_selection,
             ),
         )
         .add_systems(
             Update,
-            (select_piece, render_possible_routes).run_if(in_state(AppState::InGame)),
+            (
+                select_piece,
+                apply_move_requests,
+                respawn_pieces,
+                apply_board_changes,
+                apply_orientation,
+                update_possible_routes,
+                draw_capture_routes,
+            )
+                .chain()
+                .run_if(in_state(AppState::InGame)),
         )
-        // .add_systems(Update, play_ai)
+        .add_event::<SelectionChanged>()
         .init_resource::<PieceData>()
         .init_resource::<SelectedPiece>();
     }
@@ -39,6 +56,10 @@ impl Plugin for PiecePlugin {
 pub struct PieceData {
     mesh_handle: Mesh2dHandle,
     images: Vec<Handle<Image>>, // Use a vector to hold all image handles
+    // shared by every possible route indicator, so selecting a piece doesn't create new assets
+    route_mesh: Mesh2dHandle,
+    route_material: Handle<ColorMaterial>,
+    route_square_material: Handle<ColorMaterial>,
 }
 
 impl PieceData {
@@ -67,8 +88,24 @@ macro_rules! build_piece_data {
             $( $variant, )*
         }
 
+        impl $enum_name {
+            pub const ALL: &'static [$enum_name] = &[$( $enum_name::$variant, )*];
+        }
+
+        fn load_piece_images(asset_server: &AssetServer) -> Vec<Handle<Image>> {
+            let mut images = Vec::new();
+            $(
+                let data = load_piece_image!(asset_server, "white", stringify!($variant).to_lowercase());
+                images.push(data);
+                let data = load_piece_image!(asset_server, "black", stringify!($variant).to_lowercase());
+                images.push(data);
+            )*
+            images
+        }
+
         fn init_pieces_recources(
             mut meshes: ResMut<Assets<Mesh>>,
+            mut materials: ResMut<Assets<ColorMaterial>>,
             asset_server: Res<AssetServer>,
             mut piece_recourecs: ResMut<PieceData>,
         ) {
@@ -76,17 +113,22 @@ macro_rules! build_piece_data {
                 let mesh = Mesh::from(Rectangle::default());
                 let mesh_handle: Mesh2dHandle = meshes.add(mesh).into();
 
-                let mut images = Vec::new();
-                $(
-                    let data = load_piece_image!(asset_server, "white", stringify!($variant).to_lowercase());
-                    images.push(data);
-                    let data = load_piece_image!(asset_server, "black", stringify!($variant).to_lowercase());
-                    images.push(data);
-                )*
+                let images = load_piece_images(&asset_server);
+
+                let route_mesh: Mesh2dHandle = meshes
+                    .add(Circle {
+                        radius: GRID_BLOCK_SIZE / 2.0,
+                    })
+                    .into();
+                let route_material = materials.add(Color::BLACK);
+                let route_square_material = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.3));
 
                 PieceData {
                     mesh_handle,
                     images,
+                    route_mesh,
+                    route_material,
+                    route_square_material,
                 }
             };
         }
@@ -263,41 +305,43 @@ impl Piece {
 #[derive(Resource, Default)]
 struct SelectedPiece {
     selected: Option<usize>,
+    // legal moves of the selected piece, computed once when the selection or the board changes
+    possible_moves: Vec<usize>,
     optional_paths: Vec<Entity>,
 }
+
+// sent whenever a piece is selected or unselected
+#[derive(Event)]
+struct SelectionChanged;
 // ---------------------------------------------------------------
 
 // needed functions
 // ---------------------------------------------------------------
-#[allow(unused_assignments)]
-fn pos_to_cords(src: Vec2) -> Option<usize> {
-    let src_x = (src.x + SQUARE_SIZE / 2.0).floor() as i32;
-    let src_y = (src.y + SQUARE_SIZE / 2.0).floor() as i32;
-    let sqr_size = SQUARE_SIZE as i32;
-    let mut x = 0;
-    let mut y = 0;
-
-    if (src_x as f32) > (GRID_SIZE / 2.0) || (src_x as f32) < (-GRID_SIZE / 2.0) {
-        return None;
-    }
-    if (src_y as f32) > (GRID_SIZE / 2.0) || (src_y as f32) < (-GRID_SIZE / 2.0) {
+// maps a point in world space to the square under it, the camera can be zoomed and panned so the
+// point must already be converted from the window with the camera
+fn pos_to_cords(src: Vec2, flipped: bool) -> Option<usize> {
+    // the board is drawn half a square off the center, see `spawn_grid`
+    let half_board = TILE_NUMBER as f32 / 2.0;
+    let x = ((src.x + SQUARE_SIZE / 2.0) / SQUARE_SIZE + half_board).floor();
+    let y = (half_board - (src.y + SQUARE_SIZE / 2.0) / SQUARE_SIZE).floor();
+    if x < 0.0 || y < 0.0 || x >= TILE_NUMBER as f32 || y >= TILE_NUMBER as f32 {
         return None;
     }
-
-    if src_x <= 0 {
-        x = (TILE_NUMBER as i32 / 2) - 1 - src_x / -sqr_size;
-    } else {
-        x = (TILE_NUMBER as i32 / 2) + src_x / sqr_size;
-    }
-    if src_y <= 0 {
-        y = (TILE_NUMBER as i32 / 2) + src_y / -sqr_size;
+    let index = y as usize * TILE_NUMBER + x as usize;
+    // a flipped board is the same board rotated by 180 degrees
+    if flipped {
+        Some(TILE_NUMBER * TILE_NUMBER - 1 - index)
     } else {
-        y = (TILE_NUMBER as i32 / 2) - 1 - src_y / sqr_size;
+        Some(index)
     }
-    Some(y as usize * TILE_NUMBER + x as usize)
 }
 
-fn from_index_to_srceen_position(index: usize) -> Vec3 {
+pub fn from_index_to_srceen_position(index: usize, flipped: bool) -> Vec3 {
+    let index = if flipped {
+        TILE_NUMBER * TILE_NUMBER - 1 - index
+    } else {
+        index
+    };
     let x = index % TILE_NUMBER;
     let y = index / TILE_NUMBER;
     let mut position = Vec3::new(0., 0., 0.1);
@@ -317,119 +361,152 @@ fn from_index_to_srceen_position(index: usize) -> Vec3 {
 
 // system logic
 // ---------------------------------------------------------------
-fn render_possible_routes(
+fn update_possible_routes(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut selection_events: EventReader<SelectionChanged>,
+    mut board_events: EventReader<BoardChanged>,
     mut board: ResMut<BoardRecource>,
     mut selected_piece: ResMut<SelectedPiece>,
+    piece_recourecs: Res<PieceData>,
+    orientation: Res<BoardOrientation>,
+    indicator_style: Res<MoveIndicatorStyle>,
+) {
+    // the routes only need to be rebuilt when the selection, the board itself or the way they are
+    // shown has changed
+    if selection_events.is_empty() && board_events.is_empty() && !indicator_style.is_changed() {
+        return;
+    }
+    selection_events.clear();
+    board_events.clear();
+
+    let selected_piece = &mut *selected_piece;
+    for route in selected_piece.optional_paths.drain(..) {
+        commands.entity(route).despawn();
+    }
+    selected_piece.possible_moves = match selected_piece.selected {
+        Some(cords) if board.grid[cords].is_some() => board.get_possible_moves(cords),
+        _ => Vec::new(),
+    };
+    for &route in &selected_piece.possible_moves {
+        let position = from_index_to_srceen_position(route, orientation.flipped);
+        let bundle = match *indicator_style {
+            // occupied targets are marked by `draw_capture_routes` instead
+            MoveIndicatorStyle::Dots if board.grid[route].is_some() => continue,
+            MoveIndicatorStyle::Dots => MaterialMesh2dBundle {
+                mesh: piece_recourecs.route_mesh.clone(),
+                material: piece_recourecs.route_material.clone(),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            // drawn between the board and the pieces
+            MoveIndicatorStyle::Squares => MaterialMesh2dBundle {
+                mesh: piece_recourecs.mesh_handle.clone(),
+                material: piece_recourecs.route_square_material.clone(),
+                transform: Transform::from_xyz(position.x, position.y, 0.05)
+                    .with_scale(Vec3::splat(SQUARE_SIZE)),
+                ..default()
+            },
+            MoveIndicatorStyle::Hidden => continue,
+        };
+        let id = commands.spawn((bundle, OnGameScreen)).id();
+        selected_piece.optional_paths.push(id);
+    }
+}
+
+fn draw_capture_routes(
+    board: Res<BoardRecource>,
+    selected_piece: Res<SelectedPiece>,
+    orientation: Res<BoardOrientation>,
+    indicator_style: Res<MoveIndicatorStyle>,
     mut gizmos: Gizmos,
 ) {
-    match selected_piece.selected {
-        Some(cords) => {
-            for route in &selected_piece.optional_paths {
-                commands.entity(*route).despawn();
-            }
-            selected_piece.optional_paths = Vec::new();
-            for route in board.get_possible_moves(cords) {
-                if board.grid[route].is_some() {
-                    gizmos.circle_2d(
-                        from_index_to_srceen_position(route).xy(),
-                        GRID_BLOCK_SIZE * 2.0,
-                        Color::BLACK,
-                    );
-                    continue;
-                }
-                let id = commands
-                    .spawn(MaterialMesh2dBundle {
-                        mesh: meshes
-                            .add(Circle {
-                                radius: GRID_BLOCK_SIZE / 2.0,
-                            })
-                            .into(),
-                        material: materials.add(Color::BLACK),
-                        transform: Transform::from_translation(from_index_to_srceen_position(
-                            route,
-                        )),
-                        ..default()
-                    })
-                    .id();
-                selected_piece.optional_paths.push(id);
-            }
-        }
-        None => {
-            for route in &selected_piece.optional_paths {
-                commands.entity(*route).despawn();
-            }
-            selected_piece.optional_paths = Vec::new();
+    if *indicator_style != MoveIndicatorStyle::Dots {
+        return;
+    }
+    for &route in &selected_piece.possible_moves {
+        if board.grid[route].is_some() {
+            gizmos.circle_2d(
+                from_index_to_srceen_position(route, orientation.flipped).xy(),
+                GRID_BLOCK_SIZE * 2.0,
+                Color::BLACK,
+            );
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn select_piece(
-    mut commands: Commands,
     mut mouse: EventReader<MouseButtonInput>,
-    mut board: ResMut<BoardRecource>,
+    board: Res<BoardRecource>,
     mut selected_piece: ResMut<SelectedPiece>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut selection_changed: EventWriter<SelectionChanged>,
+    mut move_requested: EventWriter<MoveRequested>,
+    local_control: Res<LocalControl>,
     q_windows: Query<&Window, With<PrimaryWindow>>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
-    mut pieces: Query<(&mut Transform, &mut Handle<ColorMaterial>), With<ComponentPiece>>,
-    piece_recourecs: Res<PieceData>,
+    mut pieces: Query<&mut Transform, With<ComponentPiece>>,
+    orientation: Res<BoardOrientation>,
+    animations: Query<(), With<PieceAnimation>>,
+    input_blocked: Res<BoardInputBlocked>,
+    ui: Query<&Interaction>,
 ) {
+    // no more moves once the game is over, while pieces are still moving, while something
+    // else covers the board or for spectators
+    let spectating = *local_control == LocalControl::NONE;
+    if board.result.is_some() || !animations.is_empty() || input_blocked.0 || spectating {
+        mouse.clear();
+        return;
+    }
+    // clicks on the interface over the board, e.g. the chat, aren't moves
+    if ui
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        mouse.clear();
+        return;
+    }
     for ev in mouse.read() {
+        // the other buttons are used by the camera
+        if ev.button != MouseButton::Left {
+            continue;
+        }
         match ev.state {
             ButtonState::Pressed => {}
             ButtonState::Released => {
+                let previous_selection = selected_piece.selected;
                 // get the mouse position on the screen and ajust them to the camera position
                 let (camera, camera_transform) = q_camera.single();
-                let position = q_windows
+                let Some(position) = q_windows
                     .single()
                     .cursor_position()
-                    .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor));
+                    .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+                else {
+                    continue;
+                };
                 // checks if there is a selected piece, if not, return the last selected piece to normal size
                 if let Some(cords) = selected_piece.selected {
                     if let Some(piece) = board.grid[cords] {
                         let id = piece.get_id().unwrap();
                         if let Ok(mut component) = pieces.get_mut(id) {
-                            component.0.scale -= Vec3::splat(SQUARE_SIZE / 2.0);
+                            component.scale -= Vec3::splat(SQUARE_SIZE / 2.0);
                         }
                     }
                 }
                 // get the last position and the new position and send them to check for the legal move,
-                // if the move is legal then rerender anything that is needed and reset the selected piece
-                if let Some(cords) = pos_to_cords(position.unwrap()) {
+                // if the move is legal then let `apply_board_changes` rerender anything that is needed
+                // and reset the selected piece
+                if let Some(cords) = pos_to_cords(position, orientation.flipped) {
                     match selected_piece.selected {
                         None => {
-                            selected_piece.selected = Some(cords);
+                            // only the pieces of the colors played on this computer can be picked
+                            let controlled = board.grid[cords]
+                                .is_some_and(|piece| local_control.controls(piece.get_color()));
+                            if controlled {
+                                selected_piece.selected = Some(cords);
+                            }
                         }
                         Some(src) => {
-                            let result = board.move_piece(src, cords);
-                            for res in result {
-                                if let Ok((mut component, mut img_handle)) = pieces.get_mut(res.0) {
-                                    match res.1 {
-                                        PieceResult::None() => {}
-                                        PieceResult::Moved(dst) => {
-                                            component.translation =
-                                                from_index_to_srceen_position(dst);
-                                        }
-                                        PieceResult::Captured() => {
-                                            commands.entity(res.0).despawn();
-                                        }
-                                        PieceResult::Promoted(dst, ptype, color) => {
-                                            println!("promoted");
-                                            component.translation =
-                                                from_index_to_srceen_position(dst);
-                                            *img_handle =
-                                                materials.add(piece_recourecs.get(ptype, color));
-                                        }
-                                        PieceResult::Changed(ptype, color) => {
-                                            *img_handle =
-                                                materials.add(piece_recourecs.get(ptype, color));
-                                        }
-                                    }
-                                }
-                            }
+                            move_requested.send(MoveRequested { src, dst: cords });
                             selected_piece.selected = None;
                         }
                     }
@@ -440,7 +517,7 @@ fn select_piece(
                         Some(piece) => {
                             let id = piece.get_id().unwrap();
                             if let Ok(mut component) = pieces.get_mut(id) {
-                                component.0.scale += Vec3::splat(SQUARE_SIZE / 2.0);
+                                component.scale += Vec3::splat(SQUARE_SIZE / 2.0);
                             }
                         }
                         None => {
@@ -448,35 +525,170 @@ fn select_piece(
                         }
                     }
                 }
+                if selected_piece.selected != previous_selection {
+                    selection_changed.send(SelectionChanged);
+                }
             }
         }
     }
 }
 
+// moves, removes and changes the sprites of the pieces that were affected by a move on the board
+fn apply_board_changes(
+    mut commands: Commands,
+    mut board_events: EventReader<BoardChanged>,
+    mut pieces: Query<&mut ComponentPiece>,
+    piece_recourecs: Res<PieceData>,
+    animation_speed: Res<AnimationSpeed>,
+) {
+    let duration = animation_speed.move_duration();
+    for ev in board_events.read() {
+        for &(id, result) in &ev.results {
+            if let Ok(mut piece) = pieces.get_mut(id) {
+                let jump = Rules::get_rules(piece.piece_type)
+                    .movment_rules
+                    .contains(MovementsRules::SHIFT_STEP_MOVMENT);
+                let kind = match result {
+                    PieceResult::None() => continue,
+                    PieceResult::Moved(dst) => AnimationKind::Slide {
+                        from: ev.src,
+                        to: dst,
+                        jump,
+                        new_image: None,
+                    },
+                    PieceResult::Captured() => AnimationKind::FadeOut,
+                    PieceResult::Promoted(dst, ptype, color) => {
+                        *piece = ComponentPiece {
+                            color,
+                            piece_type: ptype,
+                        };
+                        AnimationKind::Slide {
+                            from: ev.src,
+                            to: dst,
+                            jump,
+                            new_image: Some(piece_recourecs.get(ptype, color)),
+                        }
+                    }
+                    PieceResult::Changed(ptype, color) => {
+                        *piece = ComponentPiece {
+                            color,
+                            piece_type: ptype,
+                        };
+                        AnimationKind::Change {
+                            new_image: Some(piece_recourecs.get(ptype, color)),
+                        }
+                    }
+                };
+                commands
+                    .entity(id)
+                    .insert(PieceAnimation::new(kind, duration));
+            }
+        }
+    }
+}
+
+// the entities of the last game are gone, so the selection can't point to them anymore
+fn reset_selection(mut selected_piece: ResMut<SelectedPiece>) {
+    *selected_piece = SelectedPiece::default();
+}
+
+// moves every piece to its square when the board is flipped
+fn apply_orientation(
+    orientation: Res<BoardOrientation>,
+    board: Res<BoardRecource>,
+    mut pieces: Query<&mut Transform, With<ComponentPiece>>,
+    mut selection_changed: EventWriter<SelectionChanged>,
+) {
+    if !orientation.is_changed() {
+        return;
+    }
+    for (index, piece) in board.grid.iter().enumerate() {
+        if let Some(id) = piece.and_then(|p| p.get_id()) {
+            if let Ok(mut transform) = pieces.get_mut(id) {
+                transform.translation = from_index_to_srceen_position(index, orientation.flipped);
+            }
+        }
+    }
+    // the possible routes are drawn on the old squares, so they need to be rebuilt
+    selection_changed.send(SelectionChanged);
+}
+
 fn spawn_pieces(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut board: ResMut<BoardRecource>,
     piece_recourecs: Res<PieceData>,
+    orientation: Res<BoardOrientation>,
+) {
+    spawn_piece_entities(
+        &mut commands,
+        &mut materials,
+        &mut board,
+        &piece_recourecs,
+        orientation.flipped,
+    );
+}
+
+// the board was replaced in the middle of the game, so the pieces on the screen are replaced too
+#[allow(clippy::too_many_arguments)]
+fn respawn_pieces(
+    mut board_reset: EventReader<BoardReset>,
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut board: ResMut<BoardRecource>,
+    piece_recourecs: Res<PieceData>,
+    orientation: Res<BoardOrientation>,
+    pieces: Query<Entity, With<ComponentPiece>>,
+    mut selected_piece: ResMut<SelectedPiece>,
+    mut selection_changed: EventWriter<SelectionChanged>,
+) {
+    if board_reset.is_empty() {
+        return;
+    }
+    board_reset.clear();
+    for entity in &pieces {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_piece_entities(
+        &mut commands,
+        &mut materials,
+        &mut board,
+        &piece_recourecs,
+        orientation.flipped,
+    );
+    *selected_piece = SelectedPiece::default();
+    selection_changed.send(SelectionChanged);
+}
+
+fn spawn_piece_entities(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    board: &mut BoardRecource,
+    piece_recourecs: &PieceData,
+    flipped: bool,
 ) {
     for (index, piece) in board.grid.iter_mut().enumerate() {
         match piece {
             Some(p) => {
-                let position = Transform::from_translation(from_index_to_srceen_position(index));
+                let position =
+                    Transform::from_translation(from_index_to_srceen_position(index, flipped));
                 let id = commands
-                    .spawn(PieceBundle {
-                        // mesh: mesh_handle,
-                        piece: ComponentPiece {
-                            color: p.color,
-                            piece_type: p.piece_type,
+                    .spawn((
+                        PieceBundle {
+                            // mesh: mesh_handle,
+                            piece: ComponentPiece {
+                                color: p.color,
+                                piece_type: p.piece_type,
+                            },
+                            sprite: MaterialMesh2dBundle {
+                                transform: position.with_scale(Vec3::splat(SQUARE_SIZE)),
+                                mesh: piece_recourecs.mesh_handle.clone(),
+                                material: materials.add(piece_recourecs.get(p.piece_type, p.color)),
+                                ..default()
+                            },
                         },
-                        sprite: MaterialMesh2dBundle {
-                            transform: position.with_scale(Vec3::splat(SQUARE_SIZE)),
-                            mesh: piece_recourecs.mesh_handle.clone(),
-                            material: materials.add(piece_recourecs.get(p.piece_type, p.color)),
-                            ..default()
-                        },
-                    })
+                        OnGameScreen,
+                    ))
                     .id();
                 p.id = Some(id);
             }
@@ -0,0 +1,237 @@
+use std::{fs, io, path::Path};
+
+use super::{
+    board::{move_name, parse_move, BoardRecource, GameEndReason},
+    piece::PieceTypes,
+};
+
+// Positions with a best move to find, one puzzle per line with the fields separated by commas
+// the way the puzzle databases of chess sites are written:
+//   mate2-01,11A2s1/14gk/.../16 w - - 0 1,l16o16 p15p14 o16p16,1400,mateIn2 amazon
+// the id, the position as FEN, the solution, the rating and the themes. The side to move in the
+// position solves the puzzle, the solution starts with their move and alternates with the
+// answers of the opponent, so it always ends with a move of the solver. Lines starting with # are
+// comments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub id: String,
+    pub fen: String,
+    pub solution: Vec<(usize, usize)>,
+    // how hard it is, on the same scale as the ratings of players
+    pub rating: u32,
+    // what the puzzle is about, e.g. "mateIn2" or the piece that solves it, "amazon"
+    pub themes: Vec<String>,
+}
+
+impl Puzzle {
+    // checks that the position can be read and that every move of the solution is legal in it
+    pub fn new(
+        id: &str,
+        fen: &str,
+        solution: Vec<(usize, usize)>,
+        rating: u32,
+        themes: Vec<String>,
+    ) -> Result<Self, String> {
+        // the fields are separated by commas and the themes by spaces
+        if id.is_empty() || id.contains([',', ' ']) {
+            return Err(format!("not a puzzle id: {:?}", id));
+        }
+        if solution.len() % 2 == 0 {
+            return Err(format!(
+                "{}: the solution must end with a move of the solver",
+                id
+            ));
+        }
+        let mut board = BoardRecource::from_fen(fen).map_err(|err| format!("{}: {}", id, err))?;
+        for &(src, dst) in &solution {
+            if board.make_move(src, dst).is_none() {
+                return Err(format!("{}: {} isn't legal", id, move_name(src, dst)));
+            }
+        }
+        Ok(Puzzle {
+            id: id.to_string(),
+            fen: fen.to_string(),
+            solution,
+            rating,
+            themes,
+        })
+    }
+
+    // the position after the first `plies` moves of the solution
+    pub fn position_after(&self, plies: usize) -> BoardRecource {
+        let mut board = BoardRecource::from_fen(&self.fen).expect("the puzzle was checked");
+        for &(src, dst) in self.solution.iter().take(plies) {
+            board.make_move(src, dst);
+        }
+        board
+    }
+
+    // the color that solves the puzzle
+    pub fn solver(&self) -> bool {
+        self.position_after(0).turn
+    }
+
+    // Whether `mv` is right for the move `ply` of the solution. Any mate is as good as the one of
+    // the solution, puzzles often have more than one for the last move
+    pub fn accepts(&self, ply: usize, mv: (usize, usize)) -> bool {
+        if self.solution.get(ply) == Some(&mv) {
+            return true;
+        }
+        let mut board = self.position_after(ply);
+        board.make_move(mv.0, mv.1).is_some()
+            && matches!(board.game_status(), Some((_, GameEndReason::Checkmate)))
+    }
+
+    pub fn to_line(&self) -> String {
+        let solution: Vec<String> = self
+            .solution
+            .iter()
+            .map(|&(src, dst)| move_name(src, dst))
+            .collect();
+        format!(
+            "{},{},{},{},{}",
+            self.id,
+            self.fen,
+            solution.join(" "),
+            self.rating,
+            self.themes.join(" ")
+        )
+    }
+
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [id, fen, solution, rating, themes] = fields[..] else {
+            return Err(format!("expected 5 fields: {}", line));
+        };
+        let solution = solution
+            .split_whitespace()
+            .map(|mv| parse_move(mv).ok_or_else(|| format!("{}: not a move: {}", id, mv)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rating = rating
+            .parse()
+            .map_err(|_| format!("{}: not a rating: {}", id, rating))?;
+        let themes = themes.split_whitespace().map(str::to_string).collect();
+        Puzzle::new(id, fen, solution, rating, themes)
+    }
+}
+
+// the theme of the puzzles solved with the piece
+pub fn piece_theme(piece_type: PieceTypes) -> &'static str {
+    match piece_type {
+        PieceTypes::Pawn => "pawn",
+        PieceTypes::Rook => "rook",
+        PieceTypes::Knight => "knight",
+        PieceTypes::Bishop => "bishop",
+        PieceTypes::Queen => "queen",
+        PieceTypes::King => "king",
+        PieceTypes::Jester => "jester",
+        PieceTypes::Amazon => "amazon",
+        PieceTypes::GrandCommander => "grandCommander",
+        PieceTypes::Abbess => "abbess",
+        PieceTypes::ShortRook => "shortRook",
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PuzzleSet {
+    // in the order of the file
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl PuzzleSet {
+    pub fn contains(&self, id: &str) -> bool {
+        self.puzzles.iter().any(|puzzle| puzzle.id == id)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# puzzles: id,FEN,solution,rating,themes\n");
+        for puzzle in &self.puzzles {
+            text += &puzzle.to_line();
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut puzzles = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let puzzle =
+                Puzzle::parse(line).map_err(|err| format!("line {}: {}", number + 1, err))?;
+            puzzles.push(puzzle);
+        }
+        Ok(PuzzleSet { puzzles })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        PuzzleSet::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::{board::GameResult, puzzle_player::PUZZLES_PATH};
+
+    use super::*;
+
+    #[test]
+    fn bundled_mates_are_mates() {
+        let set = PuzzleSet::load(Path::new(PUZZLES_PATH)).unwrap();
+        let mates: Vec<&Puzzle> = set
+            .puzzles
+            .iter()
+            .filter(|puzzle| {
+                puzzle
+                    .themes
+                    .iter()
+                    .any(|theme| theme.starts_with("mateIn"))
+            })
+            .collect();
+        assert!(!mates.is_empty());
+        for puzzle in mates {
+            let end = puzzle.position_after(puzzle.solution.len());
+            assert_eq!(
+                end.game_status(),
+                Some((GameResult::Win(puzzle.solver()), GameEndReason::Checkmate)),
+                "{}",
+                puzzle.id
+            );
+        }
+    }
+
+    #[test]
+    fn puzzles_round_trip() {
+        let line = "mate1-01,k1j13/16/2N13/16/2N3S9/16/16/16/16/16/16/16/16/16/16/14K1 w - - 0 1,c12b14,1000,mateIn1 knight";
+        let puzzle = Puzzle::parse(line).unwrap();
+        assert_eq!(puzzle.to_line(), line);
+        assert_eq!(Puzzle::parse(&puzzle.to_line()).unwrap(), puzzle);
+
+        let set = PuzzleSet {
+            puzzles: vec![puzzle],
+        };
+        assert_eq!(PuzzleSet::parse(&set.to_text()).unwrap(), set);
+    }
+
+    #[test]
+    fn broken_puzzles_are_errors() {
+        let fen = "k1j13/16/2N13/16/2N3S9/16/16/16/16/16/16/16/16/16/16/14K1 w - - 0 1";
+        // too few fields
+        assert!(Puzzle::parse(&format!("mate1-01,{},c12b14,1000", fen)).is_err());
+        // a move that isn't legal
+        assert!(Puzzle::parse(&format!("mate1-01,{},c12c11,1000,mateIn1", fen)).is_err());
+        // the opponent has the last move
+        assert!(Puzzle::parse(&format!("mate1-01,{},c12b14 a16a15,1000,mateIn1", fen)).is_err());
+        assert!(Puzzle::parse(&format!("mate1-01,{},c12b14,hard,mateIn1", fen)).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use bevy::{prelude::*, sprite::Anchor, text::Text2dBounds};
+use bevy_pkv::PkvStore;
+use serde::{Deserialize, Serialize};
+
+use crate::states::{
+    AppState, BoardInputBlocked, GameModeState, LocalControl, MoveAuthority, TextInputFocused,
+};
+
+use super::{
+    board::{
+        BoardChanged, BoardOrientation, BoardRecource, BoardReset, MoveHistory, MoveRequested,
+    },
+    puzzle::{Puzzle, PuzzleSet},
+    OnGameScreen, GRID_SIZE, SQUARE_SIZE,
+};
+
+// the puzzles of the puzzle mode, the puzzle miner adds the ones it finds in recorded games
+pub const PUZZLES_PATH: &str = "assets/puzzles.txt";
+const PROGRESS_KEY: &str = "puzzle_progress";
+// how long the opponent waits before answering, and a wrong move stays on the board
+const REPLY_DELAY: Duration = Duration::from_millis(600);
+const PANEL_WIDTH: f32 = 180.0;
+const PANEL_FONT_SIZE: f32 = 16.0;
+
+// The player solves the puzzles one after the other, easiest first. Their moves are checked
+// against the solution and the opponent answers with the moves of it. How every puzzle went is
+// kept in the PkvStore, so the next session starts with one that wasn't solved yet
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PuzzleProgress>()
+            .add_systems(Startup, load_progress)
+            .add_systems(Update, save_progress)
+            .add_systems(
+                Update,
+                (
+                    start_puzzles,
+                    check_moves,
+                    play_replies,
+                    change_puzzle,
+                    update_puzzle_panel,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Puzzle)),
+            )
+            .add_systems(OnExit(AppState::InGame), stop_puzzles);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PuzzleResult {
+    // how many tries ended with the puzzle solved without a wrong move and how many didn't
+    pub solved: u32,
+    pub failed: u32,
+}
+
+// how every puzzle the player tried went, by the id of the puzzle
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PuzzleProgress {
+    results: HashMap<String, PuzzleResult>,
+}
+
+impl PuzzleProgress {
+    pub fn result(&self, id: &str) -> PuzzleResult {
+        self.results.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn is_solved(&self, id: &str) -> bool {
+        self.result(id).solved > 0
+    }
+
+    pub fn record(&mut self, id: &str, solved: bool) {
+        let result = self.results.entry(id.to_string()).or_default();
+        if solved {
+            result.solved += 1;
+        } else {
+            result.failed += 1;
+        }
+    }
+}
+
+enum PuzzleState {
+    // waiting for the move of the player
+    Solving,
+    // the opponent answers once the timer is done
+    Replying(Timer),
+    // the wrong move is taken back once the timer is done
+    Wrong(Timer),
+    Solved,
+}
+
+#[derive(Resource)]
+pub struct PuzzleSession {
+    // easiest first
+    puzzles: Vec<Puzzle>,
+    current: usize,
+    // how many moves of the solution were played
+    ply: usize,
+    state: PuzzleState,
+    // a wrong move fails the try even if the puzzle is solved after it
+    mistake: bool,
+    // whether the try was already recorded, a wrong move is recorded right away
+    recorded: bool,
+    message: &'static str,
+}
+
+impl PuzzleSession {
+    fn puzzle(&self) -> Option<&Puzzle> {
+        self.puzzles.get(self.current)
+    }
+
+    fn record(&mut self, progress: &mut PuzzleProgress, solved: bool) {
+        if self.recorded {
+            return;
+        }
+        self.recorded = true;
+        if let Some(puzzle) = self.puzzles.get(self.current) {
+            progress.record(&puzzle.id, solved);
+        }
+    }
+}
+
+#[derive(Component)]
+struct PuzzlePanel;
+
+fn load_progress(mut progress: ResMut<PuzzleProgress>, pkv: Res<PkvStore>) {
+    if let Ok(stored) = pkv.get::<PuzzleProgress>(PROGRESS_KEY) {
+        *progress = stored;
+    }
+}
+
+fn save_progress(progress: Res<PuzzleProgress>, mut pkv: ResMut<PkvStore>) {
+    if progress.is_changed() && !progress.is_added() {
+        pkv.set(PROGRESS_KEY, &*progress)
+            .expect("failed to store the puzzle progress");
+    }
+}
+
+// the next puzzle after `current` that wasn't solved yet, simply the next one once all were
+fn next_puzzle(puzzles: &[Puzzle], progress: &PuzzleProgress, current: usize) -> usize {
+    let count = puzzles.len().max(1);
+    (1..=puzzles.len())
+        .map(|step| (current + step) % count)
+        .find(|&i| !progress.is_solved(&puzzles[i].id))
+        .unwrap_or((current + 1) % count)
+}
+
+// puts the position after the first `ply` moves of the solution on the board
+fn show_position(
+    puzzle: &Puzzle,
+    ply: usize,
+    board: &mut BoardRecource,
+    history: &mut MoveHistory,
+    board_reset: &mut EventWriter<BoardReset>,
+) {
+    *board = puzzle.position_after(ply);
+    history.moves = puzzle.solution[..ply].to_vec();
+    board_reset.send(BoardReset);
+}
+
+// starts the current puzzle from its first move, the solver's pieces at the bottom
+fn start_puzzle(
+    session: &mut PuzzleSession,
+    board: &mut BoardRecource,
+    history: &mut MoveHistory,
+    board_reset: &mut EventWriter<BoardReset>,
+    orientation: &mut BoardOrientation,
+    local_control: &mut LocalControl,
+) {
+    session.ply = 0;
+    session.state = PuzzleState::Solving;
+    session.mistake = false;
+    session.recorded = false;
+    session.message = "Find the best move";
+    let Some(puzzle) = session.puzzle() else {
+        *local_control = LocalControl::NONE;
+        return;
+    };
+    show_position(puzzle, 0, board, history, board_reset);
+    let solver = puzzle.solver();
+    orientation.flipped = !solver;
+    *local_control = LocalControl::only(solver);
+}
+
+// runs once the board is on the screen
+#[allow(clippy::too_many_arguments)]
+fn start_puzzles(
+    mut commands: Commands,
+    session: Option<Res<PuzzleSession>>,
+    progress: Res<PuzzleProgress>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut local_control: ResMut<LocalControl>,
+    mut authority: ResMut<MoveAuthority>,
+    mut input_blocked: ResMut<BoardInputBlocked>,
+) {
+    if session.is_some() {
+        return;
+    }
+    let mut puzzles = match PuzzleSet::load(Path::new(PUZZLES_PATH)) {
+        Ok(set) => set.puzzles,
+        Err(err) => {
+            warn!("can't read the puzzles {}: {}", PUZZLES_PATH, err);
+            Vec::new()
+        }
+    };
+    puzzles.sort_by_key(|puzzle| puzzle.rating);
+    *authority = MoveAuthority::Local;
+    input_blocked.0 = false;
+
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: PANEL_FONT_SIZE,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ),
+            text_anchor: Anchor::TopLeft,
+            text_2d_bounds: Text2dBounds {
+                size: Vec2::new(PANEL_WIDTH, f32::INFINITY),
+            },
+            transform: Transform::from_xyz(
+                GRID_SIZE / 2.0 + SQUARE_SIZE * 0.5,
+                GRID_SIZE / 2.0,
+                0.0,
+            ),
+            ..default()
+        },
+        PuzzlePanel,
+        OnGameScreen,
+    ));
+
+    let current = next_puzzle(&puzzles, &progress, puzzles.len().saturating_sub(1));
+    let mut session = PuzzleSession {
+        puzzles,
+        current,
+        ply: 0,
+        state: PuzzleState::Solving,
+        mistake: false,
+        recorded: false,
+        message: "",
+    };
+    start_puzzle(
+        &mut session,
+        &mut board,
+        &mut history,
+        &mut board_reset,
+        &mut orientation,
+        &mut local_control,
+    );
+    commands.insert_resource(session);
+}
+
+// every move of the player is checked against the solution, the other moves on the board are
+// the answers of the opponent
+fn check_moves(
+    mut board_events: EventReader<BoardChanged>,
+    session: Option<ResMut<PuzzleSession>>,
+    mut progress: ResMut<PuzzleProgress>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    for ev in board_events.read() {
+        let Some(puzzle) = session.puzzle() else {
+            continue;
+        };
+        let (ply, mv) = (session.ply, (ev.src, ev.dst));
+        if ply % 2 == 1 {
+            session.ply += 1;
+            session.state = PuzzleState::Solving;
+            continue;
+        }
+        if !puzzle.accepts(ply, mv) {
+            session.state = PuzzleState::Wrong(Timer::new(REPLY_DELAY, TimerMode::Once));
+            session.mistake = true;
+            session.message = "That's not it, try again";
+            session.record(&mut progress, false);
+            continue;
+        }
+        // a mate other than the one of the solution ends the puzzle as well
+        let solved = ply + 1 == puzzle.solution.len() || puzzle.solution[ply] != mv;
+        session.ply += 1;
+        if !solved {
+            session.state = PuzzleState::Replying(Timer::new(REPLY_DELAY, TimerMode::Once));
+            session.message = "Right, keep going";
+            continue;
+        }
+        session.state = PuzzleState::Solved;
+        session.message = if session.mistake {
+            "Solved, but not on the first try"
+        } else {
+            "Solved!"
+        };
+        session.record(&mut progress, true);
+    }
+}
+
+// the answer of the opponent, or taking back a wrong move, after a moment
+fn play_replies(
+    time: Res<Time>,
+    session: Option<ResMut<PuzzleSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+    mut move_requested: EventWriter<MoveRequested>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    let session = &mut *session;
+    let Some(puzzle) = session.puzzles.get(session.current) else {
+        return;
+    };
+    match &mut session.state {
+        PuzzleState::Replying(timer) => {
+            if timer.tick(time.delta()).just_finished() {
+                let (src, dst) = puzzle.solution[session.ply];
+                move_requested.send(MoveRequested { src, dst });
+            }
+        }
+        PuzzleState::Wrong(timer) => {
+            if timer.tick(time.delta()).just_finished() {
+                show_position(
+                    puzzle,
+                    session.ply,
+                    &mut board,
+                    &mut history,
+                    &mut board_reset,
+                );
+                session.state = PuzzleState::Solving;
+            }
+        }
+        PuzzleState::Solving | PuzzleState::Solved => {}
+    }
+}
+
+// N goes on to the next puzzle and R starts this one again
+#[allow(clippy::too_many_arguments)]
+fn change_puzzle(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    session: Option<ResMut<PuzzleSession>>,
+    progress: Res<PuzzleProgress>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut local_control: ResMut<LocalControl>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    let next = keys.just_pressed(KeyCode::KeyN);
+    let retry = keys.just_pressed(KeyCode::KeyR);
+    if (!next && !retry) || text_input.0 || session.puzzles.is_empty() {
+        return;
+    }
+    if next {
+        session.current = next_puzzle(&session.puzzles, &progress, session.current);
+    }
+    start_puzzle(
+        &mut session,
+        &mut board,
+        &mut history,
+        &mut board_reset,
+        &mut orientation,
+        &mut local_control,
+    );
+}
+
+fn panel_text(session: &PuzzleSession, progress: &PuzzleProgress) -> String {
+    let Some(puzzle) = session.puzzle() else {
+        return format!("No puzzles in {}", PUZZLES_PATH);
+    };
+    let solved = session
+        .puzzles
+        .iter()
+        .filter(|puzzle| progress.is_solved(&puzzle.id))
+        .count();
+    let result = progress.result(&puzzle.id);
+    let side = if puzzle.solver() { "White" } else { "Black" };
+    format!(
+        "Puzzle {} of {}\nRating {}\nThemes: {}\n\n{} to move\n{}\n\nSolved {} of {} puzzles\n\
+         This one: solved {}, failed {}\n\nN: next puzzle\nR: try again",
+        session.current + 1,
+        session.puzzles.len(),
+        puzzle.rating,
+        puzzle.themes.join(", "),
+        side,
+        session.message,
+        solved,
+        session.puzzles.len(),
+        result.solved,
+        result.failed
+    )
+}
+
+fn update_puzzle_panel(
+    session: Option<Res<PuzzleSession>>,
+    progress: Res<PuzzleProgress>,
+    mut panels: Query<&mut Text, With<PuzzlePanel>>,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    if !session.is_changed() && !progress.is_changed() {
+        return;
+    }
+    for mut text in &mut panels {
+        text.sections[0].value = panel_text(&session, &progress);
+    }
+}
+
+fn stop_puzzles(mut commands: Commands) {
+    commands.remove_resource::<PuzzleSession>();
+}
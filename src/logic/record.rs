@@ -0,0 +1,259 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::board::{move_name, parse_move, BoardRecource, GameEndReason, GameResult};
+
+// A finished or ongoing game written as text, a few header tags followed by the moves:
+//   [White "Alice"]
+//   [Black "Bob"]
+//   [Result "1-0"]
+//   [Termination "checkmate"]
+//
+//   1. e3e5 e14e12 2. d2d4 ... 1-0
+//
+//   ; 2 Alice: good luck
+// it's close to chess PGN, but the moves are always written as source and destination squares
+// since the pieces and the board size don't fit the usual notation. The chat of the game follows
+// as comment lines with the number of moves played when it was said, so other tools skip it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameRecord {
+    // in the order they are written, e.g. ("White", "Alice")
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<(usize, usize)>,
+    pub chat: Vec<ChatLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatLine {
+    // how many moves were played when it was said
+    pub after_moves: usize,
+    pub name: String,
+    pub text: String,
+}
+
+impl GameRecord {
+    pub const EXTENSION: &'static str = "game";
+
+    pub fn new(white: &str, black: &str) -> Self {
+        let mut record = GameRecord::default();
+        record.set_tag("White", white);
+        record.set_tag("Black", black);
+        record
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        // the value is written between quotes, so it can't hold any
+        let value = value.replace(['"', '\n'], "'");
+        match self.tags.iter_mut().find(|(tag, _)| tag == key) {
+            Some((_, old)) => *old = value,
+            None => self.tags.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.tag("Result").and_then(GameResult::parse)
+    }
+
+    pub fn reason(&self) -> Option<GameEndReason> {
+        self.tag("Termination").and_then(GameEndReason::parse)
+    }
+
+    pub fn set_result(&mut self, result: GameResult, reason: GameEndReason) {
+        self.set_tag("Result", result.name());
+        self.set_tag("Termination", reason.name());
+    }
+
+    pub fn push_move(&mut self, src: usize, dst: usize) {
+        self.moves.push((src, dst));
+    }
+
+    pub fn push_chat(&mut self, name: &str, text: &str) {
+        // a line break would end the comment and a colon the name
+        self.chat.push(ChatLine {
+            after_moves: self.moves.len(),
+            name: name.replace(['\n', ':'], " "),
+            text: text.replace('\n', " "),
+        });
+    }
+
+    // the position after the first `moves` moves, None if one of them isn't legal
+    pub fn position_after(&self, moves: usize) -> Option<BoardRecource> {
+        let mut board = BoardRecource::new_game();
+        for &(src, dst) in self.moves.iter().take(moves) {
+            board.make_move(src, dst)?;
+        }
+        Some(board)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (key, value) in &self.tags {
+            text += &format!("[{} \"{}\"]\n", key, value);
+        }
+        text.push('\n');
+
+        let mut words = Vec::new();
+        for (i, &(src, dst)) in self.moves.iter().enumerate() {
+            if i % 2 == 0 {
+                words.push(format!("{}.", i / 2 + 1));
+            }
+            words.push(move_name(src, dst));
+        }
+        words.push(
+            self.result()
+                .map_or("*", |result| result.name())
+                .to_string(),
+        );
+        // keeps the lines short enough to read
+        for line in words.chunks(16) {
+            text += &line.join(" ");
+            text.push('\n');
+        }
+        if !self.chat.is_empty() {
+            text.push('\n');
+        }
+        for line in &self.chat {
+            text += &format!("; {} {}: {}\n", line.after_moves, line.name, line.text);
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut record = GameRecord::default();
+        for line in text.lines().map(str::trim) {
+            if let Some(tag) = line.strip_prefix('[') {
+                let tag = tag
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("unclosed tag: {}", line))?;
+                let (key, value) = tag
+                    .split_once(' ')
+                    .ok_or_else(|| format!("tag without a value: {}", line))?;
+                record.set_tag(key, value.trim_matches('"'));
+                continue;
+            }
+            // other comments are skipped
+            if let Some(comment) = line.strip_prefix(';') {
+                if let Some(chat) = parse_chat_line(comment.trim()) {
+                    record.chat.push(chat);
+                }
+                continue;
+            }
+            for word in line.split_whitespace() {
+                // move numbers and the result at the end are only there for people
+                if word.ends_with('.') || word == "*" || GameResult::parse(word).is_some() {
+                    continue;
+                }
+                let mv = parse_move(word).ok_or_else(|| format!("not a move: {}", word))?;
+                record.moves.push(mv);
+            }
+        }
+        Ok(record)
+    }
+
+    pub fn save(&self, dir: &Path, name: &str) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(name).with_extension(GameRecord::EXTENSION);
+        fs::write(&path, self.to_text())?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        GameRecord::parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    // every record in `dir`, e.g. the games directory of the server. Broken records are skipped,
+    // they shouldn't stop a tool that reads the whole archive
+    pub fn load_dir(dir: &Path) -> io::Result<Vec<Self>> {
+        let mut records = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(GameRecord::EXTENSION) {
+                continue;
+            }
+            if let Ok(record) = GameRecord::load(&path) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+fn parse_chat_line(comment: &str) -> Option<ChatLine> {
+    let (after_moves, rest) = comment.split_once(' ')?;
+    let (name, text) = rest.split_once(": ")?;
+    Some(ChatLine {
+        after_moves: after_moves.parse().ok()?,
+        name: name.to_string(),
+        text: text.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the first moves of a game, enough to fill more than one line of moves
+    fn record_with_moves(count: usize) -> GameRecord {
+        let mut record = GameRecord::new("Alice", "Bob");
+        let mut board = BoardRecource::new_game();
+        for _ in 0..count {
+            let moves = board.legal_moves();
+            let (src, dst) = moves[moves.len() / 2];
+            board.make_move(src, dst).unwrap();
+            record.push_move(src, dst);
+        }
+        record
+    }
+
+    #[test]
+    fn finished_games_round_trip() {
+        let mut record = record_with_moves(21);
+        record.set_tag("TimeControl", "600+5");
+        record.set_result(GameResult::Win(false), GameEndReason::Timeout);
+
+        let text = record.to_text();
+        assert!(text.contains("[Result \"0-1\"]"));
+        assert!(text.trim_end().ends_with("0-1"));
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.tag("TimeControl"), Some("600+5"));
+        assert_eq!(parsed.result(), Some(GameResult::Win(false)));
+        assert_eq!(parsed.reason(), Some(GameEndReason::Timeout));
+    }
+
+    #[test]
+    fn unfinished_games_end_with_a_star() {
+        let record = record_with_moves(5);
+        let text = record.to_text();
+        assert!(text.trim_end().ends_with('*'));
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.result(), None);
+    }
+
+    #[test]
+    fn chat_lines_round_trip() {
+        let mut record = record_with_moves(3);
+        record.push_chat("Alice", "good luck: have fun");
+        let (src, dst) = record_with_moves(4).moves[3];
+        record.push_move(src, dst);
+        record.push_chat("Bob: the second", "two\nlines");
+        assert_eq!(record.chat[1].name, "Bob  the second");
+        assert_eq!(record.chat[1].text, "two lines");
+
+        let parsed = GameRecord::parse(&record.to_text()).unwrap();
+        assert_eq!(parsed.chat, record.chat);
+        assert_eq!(parsed.chat[0].text, "good luck: have fun");
+        assert_eq!(parsed.chat[1].after_moves, 4);
+    }
+}
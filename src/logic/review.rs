@@ -0,0 +1,323 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    engine::{
+        review::{review_game, GameReview, MoveClass},
+        search::SearchLimits,
+        tt::TranspositionTable,
+    },
+    states::{AppState, GameModeState, SearchThreads, TextInputFocused},
+};
+
+use super::{
+    ai::load_eval,
+    analysis::{show_current_position, AnalysisSession},
+    board::{move_name, BoardRecource, BoardReset, MoveHistory},
+    OnGameScreen,
+};
+
+// how long the AI looks at every position of the reviewed game
+const REVIEW_TIME: Duration = Duration::from_millis(300);
+// how many rows of moves are listed around the current one
+const SHOWN_ROWS: usize = 20;
+const REVIEW_FONT_SIZE: f32 = 14.0;
+const MOVE_BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+
+// R in the analysis reviews the main line, the moves of the game that is analyzed. Every move is
+// listed with how good it was next to the board, a click on one goes to the position after it
+pub struct ReviewPlugin;
+
+impl Plugin for ReviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                start_review,
+                receive_review,
+                update_review_panel,
+                jump_to_move,
+            )
+                .chain()
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameModeState::Analysis)),
+        )
+        .add_systems(OnExit(AppState::InGame), stop_review);
+    }
+}
+
+enum ReviewUpdate {
+    // the number of positions searched so far
+    Progress(usize),
+    Done(GameReview),
+}
+
+#[derive(Resource)]
+pub struct ReviewSession {
+    // the main line when the review was started
+    moves: Vec<(usize, usize)>,
+    stop: Arc<AtomicBool>,
+    updates: Mutex<Receiver<ReviewUpdate>>,
+    done: usize,
+    pub review: Option<GameReview>,
+}
+
+// the review thread ends soon after it was told to stop, e.g. when another review replaces it
+impl Drop for ReviewSession {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Component)]
+struct ReviewPanel;
+
+// shows the position after the move of this ply when clicked
+#[derive(Component)]
+struct ReviewMove {
+    ply: usize,
+}
+
+fn class_color(class: MoveClass) -> Color {
+    match class {
+        MoveClass::Best => Color::rgb(0.4, 0.85, 0.4),
+        MoveClass::Good => Color::rgb(0.9, 0.9, 0.9),
+        MoveClass::Inaccuracy => Color::rgb(0.95, 0.85, 0.3),
+        MoveClass::Mistake => Color::rgb(0.95, 0.55, 0.2),
+        MoveClass::Blunder => Color::rgb(0.95, 0.25, 0.25),
+    }
+}
+
+fn text_style(color: Color) -> TextStyle {
+    TextStyle {
+        font_size: REVIEW_FONT_SIZE,
+        color,
+        ..default()
+    }
+}
+
+fn start_review(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    analysis: Option<Res<AnalysisSession>>,
+    threads: Res<SearchThreads>,
+    panels: Query<(), With<ReviewPanel>>,
+) {
+    let Some(analysis) = analysis else {
+        return;
+    };
+    if !keys.just_pressed(KeyCode::KeyR) || text_input.0 {
+        return;
+    }
+    let moves = analysis.tree.main_line();
+    if moves.is_empty() {
+        return;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, updates) = mpsc::channel();
+    let (reviewed, thread_stop, threads) = (moves.clone(), stop.clone(), threads.0);
+    let eval = load_eval();
+    thread::spawn(move || {
+        let tt = TranspositionTable::default();
+        let limits = SearchLimits {
+            time: Some(REVIEW_TIME),
+            ..Default::default()
+        };
+        let review = review_game(
+            &reviewed,
+            limits,
+            &tt,
+            &eval,
+            threads,
+            &thread_stop,
+            |done| {
+                let _ = sender.send(ReviewUpdate::Progress(done));
+            },
+        );
+        let _ = sender.send(ReviewUpdate::Done(review));
+    });
+    // a review that is still running is stopped by dropping it
+    commands.insert_resource(ReviewSession {
+        moves,
+        stop,
+        updates: Mutex::new(updates),
+        done: 0,
+        review: None,
+    });
+
+    if panels.is_empty() {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    width: Val::Px(165.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                ..default()
+            },
+            ReviewPanel,
+            OnGameScreen,
+        ));
+    }
+}
+
+fn receive_review(review: Option<ResMut<ReviewSession>>) {
+    let Some(mut review) = review else {
+        return;
+    };
+    let updates: Vec<ReviewUpdate> = review.updates.lock().unwrap().try_iter().collect();
+    for update in updates {
+        match update {
+            ReviewUpdate::Progress(done) => review.done = done,
+            ReviewUpdate::Done(game_review) => review.review = Some(game_review),
+        }
+    }
+}
+
+fn spawn_move_button(
+    parent: &mut ChildBuilder,
+    ply: usize,
+    mv: (usize, usize),
+    class: MoveClass,
+    current: bool,
+) {
+    let (src, dst) = mv;
+    let name = format!("{}{}", move_name(src, dst), class.symbol());
+    let name = if current { format!("[{}]", name) } else { name };
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(65.0),
+                    ..default()
+                },
+                background_color: MOVE_BUTTON_COLOR.into(),
+                ..default()
+            },
+            ReviewMove { ply },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                name,
+                text_style(class_color(class)),
+            ));
+        });
+}
+
+// rebuilt when the review goes on or another move of it is looked at
+fn update_review_panel(
+    mut commands: Commands,
+    review: Option<Res<ReviewSession>>,
+    analysis: Option<Res<AnalysisSession>>,
+    panels: Query<Entity, With<ReviewPanel>>,
+    mut shown: Local<Option<Option<usize>>>,
+) {
+    let (Some(review), Some(analysis)) = (review, analysis) else {
+        return;
+    };
+    // the ply of the move that led to the board, if it's one of the reviewed moves
+    let path = analysis.tree.path();
+    let current = (!path.is_empty() && review.moves.starts_with(&path)).then(|| path.len() - 1);
+    if !review.is_changed() && *shown == Some(current) {
+        return;
+    }
+    *shown = Some(current);
+    let Ok(panel) = panels.get_single() else {
+        return;
+    };
+    commands.entity(panel).despawn_descendants();
+
+    let white = Color::rgb(0.9, 0.9, 0.9);
+    let Some(game_review) = &review.review else {
+        let progress = format!("Reviewing {}/{}", review.done, review.moves.len() + 1);
+        commands.entity(panel).with_children(|parent| {
+            parent.spawn(TextBundle::from_section(progress, text_style(white)));
+        });
+        return;
+    };
+    commands.entity(panel).with_children(|parent| {
+        for (color, name) in [(true, "White"), (false, "Black")] {
+            let accuracy = game_review
+                .accuracy(color)
+                .map_or("-".to_string(), |accuracy| format!("{:.1}%", accuracy));
+            let summary = format!(
+                "{} {}\n{} ?! {} ? {} ??",
+                name,
+                accuracy,
+                game_review.count(color, MoveClass::Inaccuracy),
+                game_review.count(color, MoveClass::Mistake),
+                game_review.count(color, MoveClass::Blunder)
+            );
+            parent.spawn(TextBundle::from_section(summary, text_style(white)));
+        }
+
+        let rows = game_review.moves.len().div_ceil(2);
+        let current_row = current.unwrap_or(0) / 2;
+        let first = current_row
+            .saturating_sub(SHOWN_ROWS / 2)
+            .min(rows.saturating_sub(SHOWN_ROWS));
+        for row in first..rows.min(first + SHOWN_ROWS) {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(format!("{}.", row + 1), text_style(white))
+                            .with_style(Style {
+                                width: Val::Px(30.0),
+                                ..default()
+                            }),
+                    );
+                    for ply in [row * 2, row * 2 + 1] {
+                        if let Some(mv) = game_review.moves.get(ply) {
+                            spawn_move_button(parent, ply, mv.mv, mv.class, current == Some(ply));
+                        }
+                    }
+                });
+        }
+    });
+}
+
+fn jump_to_move(
+    interactions: Query<(&Interaction, &ReviewMove), Changed<Interaction>>,
+    analysis: Option<ResMut<AnalysisSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+) {
+    let Some(mut analysis) = analysis else {
+        return;
+    };
+    for (interaction, review_move) in &interactions {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        analysis.tree.go_to_main_line(review_move.ply + 1);
+        show_current_position(&analysis.tree, &mut board, &mut history, &mut board_reset);
+    }
+}
+
+fn stop_review(mut commands: Commands) {
+    commands.remove_resource::<ReviewSession>();
+}
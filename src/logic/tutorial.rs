@@ -0,0 +1,738 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, sprite::Anchor, text::Text2dBounds};
+
+use crate::states::{
+    AppState, BoardInputBlocked, GameModeState, LocalControl, MoveAuthority, TextInputFocused,
+};
+
+use super::{
+    board::{
+        apply_move_requests, check_game_end, square_name, to_board_index, BoardChanged,
+        BoardOrientation, BoardRecource, BoardReset, MoveHistory,
+    },
+    fen::on_home_ranks,
+    piece::{from_index_to_srceen_position, Piece, PieceTypes, Rules},
+    zobrist::hash_board,
+    OnGameScreen, GRID_SIZE, SQUARE_SIZE, TILE_NUMBER,
+};
+
+// how long a finished or failed task stays on the board before the next one is set up
+const TASK_DELAY: Duration = Duration::from_millis(1200);
+const PANEL_WIDTH: f32 = 180.0;
+const PANEL_FONT_SIZE: f32 = 16.0;
+const MOVE_COLOR: Color = Color::rgb(0.2, 0.8, 1.0);
+const TARGET_COLOR: Color = Color::rgb(0.95, 0.75, 0.2);
+// white needs a king for any move to be legal, it waits in a corner out of the way
+const KING_SQUARE: (usize, usize) = (0, TILE_NUMBER - 1);
+
+// Every piece gets a lesson: it's put on an almost empty board with its moves highlighted, the
+// text tells how it moves and a few small tasks try it out. Black never moves, the turn goes back
+// to white after every move
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            pass_black_turn
+                .after(apply_move_requests)
+                .before(check_game_end)
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameModeState::Tutorial)),
+        )
+        .add_systems(
+            Update,
+            (
+                start_tutorial,
+                check_task,
+                next_task,
+                change_lesson,
+                update_tutorial_panel,
+                draw_moves,
+            )
+                .chain()
+                .after(pass_black_turn)
+                .run_if(in_state(AppState::InGame))
+                .run_if(in_state(GameModeState::Tutorial)),
+        )
+        .add_systems(OnExit(AppState::InGame), stop_tutorial);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Goal {
+    Reach(usize, usize),
+    Capture(usize, usize),
+    // every black piece
+    CaptureAll,
+}
+
+struct Task {
+    // where the piece of the lesson starts
+    start: (usize, usize),
+    // black pawns to capture or to be in the way
+    black: &'static [(usize, usize)],
+    goal: Goal,
+    // how many moves it may take
+    moves: usize,
+    hint: &'static str,
+}
+
+struct Lesson {
+    piece: PieceTypes,
+    name: &'static str,
+    rules: &'static str,
+    tasks: &'static [Task],
+}
+
+// squares are (x, y) with y = 0 on black's side, the fairy pieces come first
+const LESSONS: &[Lesson] = &[
+    Lesson {
+        piece: PieceTypes::Jester,
+        name: "Jester",
+        rules: "The Jester moves straight forward or backward: two squares while it's on its \
+                starting ranks, one square after that, jumping over anything in the way. It \
+                captures one square diagonally, in any direction.",
+        tasks: &[
+            Task {
+                start: (5, 15),
+                black: &[(5, 14)],
+                goal: Goal::Reach(5, 13),
+                moves: 1,
+                hint: "Two squares from the starting ranks, right over the piece in front.",
+            },
+            Task {
+                start: (5, 10),
+                black: &[],
+                goal: Goal::Reach(5, 11),
+                moves: 1,
+                hint: "Away from its starting ranks it steps one square, backward as well.",
+            },
+            Task {
+                start: (5, 10),
+                black: &[(6, 9), (7, 10)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "Diagonal steps capture, backward ones too.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Abbess,
+        name: "Abbess",
+        rules: "The Abbess moves exactly like the Jester: straight forward or backward, two \
+                squares from its starting ranks and one after that, jumping over pieces. It \
+                captures one square diagonally, in any direction.",
+        tasks: &[
+            Task {
+                start: (8, 15),
+                black: &[(8, 14)],
+                goal: Goal::Reach(8, 13),
+                moves: 1,
+                hint: "It jumps like the Jester.",
+            },
+            Task {
+                start: (8, 8),
+                black: &[],
+                goal: Goal::Reach(8, 9),
+                moves: 1,
+                hint: "One square back.",
+            },
+            Task {
+                start: (8, 8),
+                black: &[(9, 7), (8, 6), (9, 5)],
+                goal: Goal::CaptureAll,
+                moves: 3,
+                hint: "Zigzag from one piece to the next.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Amazon,
+        name: "Amazon",
+        rules: "The Amazon moves like a queen over a short distance: up to four squares \
+                straight and up to three diagonally. It can't jump over pieces.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(7, 4),
+                moves: 1,
+                hint: "Four squares straight is as far as it goes.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(10, 5),
+                moves: 1,
+                hint: "Three squares diagonally is as far as it goes.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(7, 0),
+                moves: 2,
+                hint: "Too far for one move, take two.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(7, 6), (7, 4)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "It can't jump, take the nearer piece first.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::GrandCommander,
+        name: "Grand Commander",
+        rules: "The Grand Commander jumps: one square one way and two the other like a knight, \
+                but also one and three or two and four. It jumps over anything in between.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(8, 6),
+                moves: 1,
+                hint: "The jump of the knight.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(8, 5),
+                moves: 1,
+                hint: "One square across and three forward.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(7, 7), (8, 7), (8, 6), (9, 6)],
+                goal: Goal::Reach(9, 4),
+                moves: 1,
+                hint: "Two across and four forward, over the pieces around it.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(10, 7), (8, 3)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "Two jumps, each of a different shape.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::ShortRook,
+        name: "Short Rook",
+        rules: "The Short Rook moves one square straight: up, down, left or right.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(7, 7),
+                moves: 1,
+                hint: "One square forward.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(9, 8),
+                moves: 2,
+                hint: "One square at a time.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(7, 6)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "Step forward, then capture.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Pawn,
+        name: "Pawn",
+        rules: "Pawns move straight forward and never back. From their starting ranks they move \
+                up to three squares, after that up to two. They capture one square diagonally \
+                forward and become a queen on the last rank.",
+        tasks: &[
+            Task {
+                start: (7, 14),
+                black: &[],
+                goal: Goal::Reach(7, 11),
+                moves: 1,
+                hint: "Three squares from the starting ranks.",
+            },
+            Task {
+                start: (7, 11),
+                black: &[],
+                goal: Goal::Reach(7, 9),
+                moves: 1,
+                hint: "Two squares once it left them.",
+            },
+            Task {
+                start: (7, 11),
+                black: &[(7, 10), (6, 10)],
+                goal: Goal::Capture(6, 10),
+                moves: 1,
+                hint: "Straight ahead is blocked, capture diagonally.",
+            },
+            Task {
+                start: (7, 2),
+                black: &[],
+                goal: Goal::Reach(7, 0),
+                moves: 1,
+                hint: "Reach the last rank to become a queen.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Rook,
+        name: "Rook",
+        rules: "The Rook moves any number of squares straight, as long as nothing is in the way.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(7, 0),
+                moves: 1,
+                hint: "All the way up.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(7, 3), (2, 3)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "Capture one, then turn.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Knight,
+        name: "Knight",
+        rules: "The Knight jumps one square one way and two the other, over anything in between.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[(7, 7), (8, 7)],
+                goal: Goal::Reach(8, 6),
+                moves: 1,
+                hint: "It jumps over the pieces in front.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(9, 7), (10, 9)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "Two jumps.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Bishop,
+        name: "Bishop",
+        rules: "The Bishop moves any number of squares diagonally, as long as nothing is in the \
+                way.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(3, 4),
+                moves: 1,
+                hint: "Four squares diagonally.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(10, 5), (12, 7)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "From one diagonal to the other.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::Queen,
+        name: "Queen",
+        rules: "The Queen moves any number of squares straight or diagonally, as long as nothing \
+                is in the way.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(7, 0),
+                moves: 1,
+                hint: "Straight up.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(3, 4), (3, 12)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "A diagonal, then straight down.",
+            },
+        ],
+    },
+    Lesson {
+        piece: PieceTypes::King,
+        name: "King",
+        rules: "The King moves one square straight: up, down, left or right. Unlike in chess it \
+                can't step diagonally, and it never moves into check.",
+        tasks: &[
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(7, 7),
+                moves: 1,
+                hint: "One square forward.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[],
+                goal: Goal::Reach(8, 7),
+                moves: 2,
+                hint: "No diagonal steps, go around.",
+            },
+            Task {
+                start: (7, 8),
+                black: &[(7, 6)],
+                goal: Goal::CaptureAll,
+                moves: 2,
+                hint: "Kings capture too.",
+            },
+        ],
+    },
+];
+
+enum TaskState {
+    Trying,
+    // the next task is set up once the timer is done
+    Done(Timer),
+    // the task starts again once the timer is done
+    Failed(Timer),
+}
+
+#[derive(Resource)]
+pub struct TutorialSession {
+    lesson: usize,
+    task: usize,
+    // made since the task was set up
+    moves: usize,
+    state: TaskState,
+    message: &'static str,
+    // the legal moves of the piece of the lesson and the hash of the board they are for
+    piece_moves: Vec<usize>,
+    piece_moves_of: Option<u64>,
+}
+
+impl TutorialSession {
+    fn lesson(&self) -> &'static Lesson {
+        &LESSONS[self.lesson]
+    }
+
+    fn task(&self) -> &'static Task {
+        &self.lesson().tasks[self.task]
+    }
+}
+
+#[derive(Component)]
+struct TutorialPanel;
+
+fn place(board: &mut BoardRecource, piece_type: PieceTypes, color: bool, (x, y): (usize, usize)) {
+    let mut piece = Piece::new(piece_type, color, Rules::get_rules(piece_type));
+    // the same as in a FEN, pieces away from their home ranks count as moved
+    if !on_home_ranks(y, color) {
+        piece.set_has_moved();
+    }
+    board.grid[to_board_index(x, y)] = Some(piece);
+}
+
+fn task_board(lesson: &Lesson, task: &Task) -> BoardRecource {
+    let mut board = BoardRecource {
+        grid: vec![None; TILE_NUMBER * TILE_NUMBER],
+        turn: true,
+        result: None,
+        hash: 0,
+    };
+    // the lesson of the king moves the only one
+    if lesson.piece != PieceTypes::King {
+        place(&mut board, PieceTypes::King, true, KING_SQUARE);
+    }
+    place(&mut board, lesson.piece, true, task.start);
+    for &square in task.black {
+        place(&mut board, PieceTypes::Pawn, false, square);
+    }
+    board.hash = hash_board(&board);
+    board
+}
+
+fn set_up_task(
+    session: &mut TutorialSession,
+    board: &mut BoardRecource,
+    history: &mut MoveHistory,
+    board_reset: &mut EventWriter<BoardReset>,
+) {
+    *board = task_board(session.lesson(), session.task());
+    history.moves.clear();
+    board_reset.send(BoardReset);
+    session.moves = 0;
+    session.state = TaskState::Trying;
+    session.message = "";
+}
+
+fn goal_reached(board: &BoardRecource, goal: Goal) -> bool {
+    match goal {
+        // a pawn that reaches the last rank counts even though it's a queen by then
+        Goal::Reach(x, y) | Goal::Capture(x, y) => {
+            matches!(board.grid[to_board_index(x, y)], Some(piece) if piece.get_color())
+        }
+        Goal::CaptureAll => board
+            .grid
+            .iter()
+            .all(|piece| !matches!(piece, Some(piece) if !piece.get_color())),
+    }
+}
+
+// runs once the board is on the screen
+#[allow(clippy::too_many_arguments)]
+fn start_tutorial(
+    mut commands: Commands,
+    session: Option<Res<TutorialSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut local_control: ResMut<LocalControl>,
+    mut authority: ResMut<MoveAuthority>,
+    mut input_blocked: ResMut<BoardInputBlocked>,
+) {
+    if session.is_some() {
+        return;
+    }
+    orientation.flipped = false;
+    *local_control = LocalControl::only(true);
+    *authority = MoveAuthority::Local;
+    input_blocked.0 = false;
+
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: PANEL_FONT_SIZE,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ),
+            text_anchor: Anchor::TopLeft,
+            text_2d_bounds: Text2dBounds {
+                size: Vec2::new(PANEL_WIDTH, f32::INFINITY),
+            },
+            transform: Transform::from_xyz(
+                GRID_SIZE / 2.0 + SQUARE_SIZE * 0.5,
+                GRID_SIZE / 2.0,
+                0.0,
+            ),
+            ..default()
+        },
+        TutorialPanel,
+        OnGameScreen,
+    ));
+
+    let mut session = TutorialSession {
+        lesson: 0,
+        task: 0,
+        moves: 0,
+        state: TaskState::Trying,
+        message: "",
+        piece_moves: Vec::new(),
+        piece_moves_of: None,
+    };
+    set_up_task(&mut session, &mut board, &mut history, &mut board_reset);
+    commands.insert_resource(session);
+}
+
+// black never moves, so white is to move again right away. It has to happen before the end of
+// the game is checked, black without any pieces that can move would be stalemated
+fn pass_black_turn(mut board_events: EventReader<BoardChanged>, mut board: ResMut<BoardRecource>) {
+    if board_events.is_empty() {
+        return;
+    }
+    board_events.clear();
+    if !board.turn {
+        board.change_turn();
+    }
+}
+
+fn check_task(
+    mut board_events: EventReader<BoardChanged>,
+    board: Res<BoardRecource>,
+    session: Option<ResMut<TutorialSession>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    for _ in board_events.read() {
+        if !matches!(session.state, TaskState::Trying) {
+            continue;
+        }
+        session.moves += 1;
+        let task = session.task();
+        if goal_reached(&board, task.goal) {
+            session.state = TaskState::Done(Timer::new(TASK_DELAY, TimerMode::Once));
+            session.message = "Well done!";
+        } else if session.moves >= task.moves {
+            session.state = TaskState::Failed(Timer::new(TASK_DELAY, TimerMode::Once));
+            session.message = "Not quite, try again.";
+        }
+    }
+}
+
+// a finished task makes way for the next one, the next lesson after the last one
+fn next_task(
+    time: Res<Time>,
+    session: Option<ResMut<TutorialSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    let done = match &mut session.state {
+        TaskState::Trying => return,
+        TaskState::Done(timer) => {
+            if !timer.tick(time.delta()).just_finished() {
+                return;
+            }
+            true
+        }
+        TaskState::Failed(timer) => {
+            if !timer.tick(time.delta()).just_finished() {
+                return;
+            }
+            false
+        }
+    };
+    if done {
+        session.task += 1;
+        if session.task == session.lesson().tasks.len() {
+            session.task = 0;
+            session.lesson = (session.lesson + 1) % LESSONS.len();
+        }
+    }
+    set_up_task(&mut session, &mut board, &mut history, &mut board_reset);
+}
+
+// left and right go to the lesson of another piece, R starts the task again
+fn change_lesson(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    session: Option<ResMut<TutorialSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut history: ResMut<MoveHistory>,
+    mut board_reset: EventWriter<BoardReset>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if text_input.0 {
+        return;
+    }
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        session.lesson = (session.lesson + 1) % LESSONS.len();
+        session.task = 0;
+    } else if keys.just_pressed(KeyCode::ArrowLeft) {
+        session.lesson = (session.lesson + LESSONS.len() - 1) % LESSONS.len();
+        session.task = 0;
+    } else if !keys.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    set_up_task(&mut session, &mut board, &mut history, &mut board_reset);
+}
+
+fn goal_text(lesson: &Lesson, task: &Task) -> String {
+    let goal = match task.goal {
+        Goal::Reach(x, y) => format!(
+            "Move the {} to {}",
+            lesson.name,
+            square_name(to_board_index(x, y))
+        ),
+        Goal::Capture(x, y) => {
+            format!("Capture the piece on {}", square_name(to_board_index(x, y)))
+        }
+        Goal::CaptureAll => "Capture every black piece".to_string(),
+    };
+    let moves = match task.moves {
+        1 => "in one move".to_string(),
+        moves => format!("in {} moves", moves),
+    };
+    format!("{} {}.", goal, moves)
+}
+
+fn update_tutorial_panel(
+    session: Option<Res<TutorialSession>>,
+    mut panels: Query<&mut Text, With<TutorialPanel>>,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    if !session.is_changed() {
+        return;
+    }
+    let (lesson, task) = (session.lesson(), session.task());
+    let text = format!(
+        "{} ({} of {})\n\n{}\n\nTask {} of {}: {}\n{}\n\n{}\n\nLeft, right: other pieces\n\
+         R: start the task again",
+        lesson.name,
+        session.lesson + 1,
+        LESSONS.len(),
+        lesson.rules,
+        session.task + 1,
+        lesson.tasks.len(),
+        goal_text(lesson, task),
+        task.hint,
+        session.message
+    );
+    for mut text_section in &mut panels {
+        text_section.sections[0].value = text.clone();
+    }
+}
+
+// the legal moves of the piece of the lesson, found by the same rules as in a game, and the
+// square of the task
+fn draw_moves(
+    board: Res<BoardRecource>,
+    orientation: Res<BoardOrientation>,
+    session: Option<ResMut<TutorialSession>>,
+    mut gizmos: Gizmos,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if session.piece_moves_of != Some(board.hash) {
+        let piece_type = session.lesson().piece;
+        let square = board.grid.iter().position(
+            |piece| matches!(piece, Some(piece) if piece.get_color() && piece.get_type() == piece_type),
+        );
+        session.piece_moves = square.map_or(Vec::new(), |square| board.get_possible_moves(square));
+        session.piece_moves_of = Some(board.hash);
+    }
+
+    let square_size = Vec2::splat(SQUARE_SIZE * 0.9);
+    for &square in &session.piece_moves {
+        let position = from_index_to_srceen_position(square, orientation.flipped);
+        gizmos.rect_2d(position.truncate(), 0.0, square_size, MOVE_COLOR);
+    }
+    if let Goal::Reach(x, y) | Goal::Capture(x, y) = session.task().goal {
+        let position = from_index_to_srceen_position(to_board_index(x, y), orientation.flipped);
+        gizmos.rect_2d(
+            position.truncate(),
+            0.0,
+            Vec2::splat(SQUARE_SIZE * 0.98),
+            TARGET_COLOR,
+        );
+    }
+}
+
+fn stop_tutorial(mut commands: Commands) {
+    commands.remove_resource::<TutorialSession>();
+}
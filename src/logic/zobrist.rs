@@ -0,0 +1,138 @@
+use super::{
+    board::BoardRecource,
+    piece::{MovementsRules, Piece, PieceTypes},
+    TILE_NUMBER,
+};
+
+// Zobrist hashing: every piece on every square has a random key and the hash of a board is all of
+// them XORed together, so a move only changes the keys of the squares it touches. Equal boards
+// always get the same hash, different boards collide only by chance
+
+const SQUARES: usize = TILE_NUMBER * TILE_NUMBER;
+const PIECE_TYPES: usize = PieceTypes::ShortRook as usize + 1;
+
+struct ZobristKeys {
+    // by square, piece type and color
+    pieces: [[[u64; 2]; PIECE_TYPES]; SQUARES],
+    // for pieces that lose moves once they moved, like a pawn its double step
+    moved: [u64; SQUARES],
+    white_to_move: u64,
+}
+
+// the same fixed seed on every computer, so hashes can be compared between runs
+const SEED: u64 = 0x5052_4f43_4845_5353;
+
+// the next state of the generator and the random number that comes with it
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+impl ZobristKeys {
+    const fn new() -> Self {
+        let mut state = SEED;
+        let mut key;
+        let mut keys = ZobristKeys {
+            pieces: [[[0; 2]; PIECE_TYPES]; SQUARES],
+            moved: [0; SQUARES],
+            white_to_move: 0,
+        };
+        let mut square = 0;
+        while square < SQUARES {
+            let mut piece_type = 0;
+            while piece_type < PIECE_TYPES {
+                (state, key) = split_mix(state);
+                keys.pieces[square][piece_type][0] = key;
+                (state, key) = split_mix(state);
+                keys.pieces[square][piece_type][1] = key;
+                piece_type += 1;
+            }
+            (state, key) = split_mix(state);
+            keys.moved[square] = key;
+            square += 1;
+        }
+        (_, key) = split_mix(state);
+        keys.white_to_move = key;
+        keys
+    }
+}
+
+static KEYS: ZobristKeys = ZobristKeys::new();
+
+// whether the moves of the piece depend on it having moved before, the only special right of the
+// game
+fn has_first_move_right(piece: &Piece) -> bool {
+    piece
+        .get_rules()
+        .movment_rules
+        .contains(MovementsRules::PAWN_MOVMENT)
+}
+
+// the key of `piece` standing on `square`
+pub fn piece_key(square: usize, piece: &Piece) -> u64 {
+    let key = KEYS.pieces[square][piece.get_type() as usize][piece.get_color() as usize];
+    if piece.has_moved() && has_first_move_right(piece) {
+        key ^ KEYS.moved[square]
+    } else {
+        key
+    }
+}
+
+// XORed in while white is to move, so the empty default board hashes to 0
+pub fn side_key() -> u64 {
+    KEYS.white_to_move
+}
+
+// the hash of the whole board, moves keep it up to date without calling this
+pub fn hash_board(board: &BoardRecource) -> u64 {
+    let mut hash = if board.turn { side_key() } else { 0 };
+    for (square, piece) in board.grid.iter().enumerate() {
+        if let Some(piece) = piece {
+            hash ^= piece_key(square, piece);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // random games from the start position that also take back some of their moves, the hash
+    // has to stay the one of the whole board and taking a move back has to restore the board
+    #[test]
+    fn make_and_unmake_keep_the_hash() {
+        let mut state = SEED;
+        let mut random;
+        for _ in 0..4 {
+            let mut board = BoardRecource::new_game();
+            let mut undos = Vec::new();
+            let mut before = Vec::new();
+            for _ in 0..80 {
+                (state, random) = split_mix(state);
+                if random % 4 == 0 && !undos.is_empty() {
+                    board.unmake(undos.pop().unwrap());
+                    assert_eq!((board.pieces(), board.turn), before.pop().unwrap());
+                } else {
+                    let moves = board.legal_moves();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let (src, dst) = moves[(random >> 8) as usize % moves.len()];
+                    before.push((board.pieces(), board.turn));
+                    undos.push(board.make(src, dst));
+                }
+                assert_eq!(board.hash, hash_board(&board));
+            }
+            while let Some(undo) = undos.pop() {
+                board.unmake(undo);
+                assert_eq!((board.pieces(), board.turn), before.pop().unwrap());
+                assert_eq!(board.hash, hash_board(&board));
+            }
+            assert_eq!(board.hash, BoardRecource::new_game().hash);
+        }
+    }
+}
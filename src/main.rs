@@ -1,11 +1,12 @@
 use bevy::{app::App, prelude::*};
-use logic::GamePlugin;
-use states::{AppState, GameModeState};
-use ui::GameUI;
-
-mod logic;
-mod states;
-mod ui;
+use rust_game::{
+    camera::CameraPlugin,
+    logic::GamePlugin,
+    network::NetworkPlugin,
+    sound::SoundPlugin,
+    states::{AppState, GameModeState, LocalControl, PlayerColor, TextInputFocused},
+    ui::GameUI,
+};
 
 fn main() {
     App::new()
@@ -13,6 +14,9 @@ fn main() {
         .insert_resource(ClearColor(Color::hex("353b45").unwrap()))
         .add_plugins(GamePlugin)
         .add_plugins(GameUI)
+        .add_plugins(SoundPlugin)
+        .add_plugins(NetworkPlugin)
+        .add_plugins(CameraPlugin)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 resolution: [1280., 940.].into(),
@@ -23,10 +27,8 @@ fn main() {
         }))
         .init_state::<GameModeState>()
         .init_state::<AppState>()
-        .add_systems(Startup, setup_camera)
+        .init_resource::<PlayerColor>()
+        .init_resource::<LocalControl>()
+        .init_resource::<TextInputFocused>()
         .run();
 }
-
-fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-}
@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+
+use super::{protocol::Message, NetworkRole, NetworkSession};
+
+// The longest chat message that is sent, longer ones are cut
+pub const MAX_CHAT_LENGTH: usize = 200;
+
+// The canned messages that can be sent with a single click
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emote {
+    Hello,
+    GoodLuck,
+    Thanks,
+    WellPlayed,
+    Oops,
+}
+
+impl Emote {
+    pub const ALL: [Emote; 5] = [
+        Emote::Hello,
+        Emote::GoodLuck,
+        Emote::Thanks,
+        Emote::WellPlayed,
+        Emote::Oops,
+    ];
+
+    // the name used on the wire
+    pub fn name(&self) -> &'static str {
+        match self {
+            Emote::Hello => "hello",
+            Emote::GoodLuck => "gl",
+            Emote::Thanks => "thanks",
+            Emote::WellPlayed => "wp",
+            Emote::Oops => "oops",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Emote> {
+        Emote::ALL.into_iter().find(|emote| emote.name() == name)
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Emote::Hello => "Hello!",
+            Emote::GoodLuck => "Good luck!",
+            Emote::Thanks => "Thanks!",
+            Emote::WellPlayed => "Well played!",
+            Emote::Oops => "Oops",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatContent {
+    Text(String),
+    Emote(Emote),
+}
+
+impl ChatContent {
+    // emotes are written between stars, like actions in most chats
+    pub fn text(&self) -> String {
+        match self {
+            ChatContent::Text(text) => text.clone(),
+            ChatContent::Emote(emote) => format!("*{}*", emote.text()),
+        }
+    }
+}
+
+// Words hidden by the profanity filter, matched as whole words in any case
+const PROFANITIES: [&str; 12] = [
+    "arse", "ass", "asshole", "bastard", "bitch", "bollocks", "crap", "damn", "dick", "fuck",
+    "piss", "shit",
+];
+
+// replaces the letters of every word of `PROFANITIES` with stars, the rest is kept as it is
+pub fn filter_profanity(text: &str) -> String {
+    let mut filtered = String::with_capacity(text.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, filtered: &mut String| {
+        if PROFANITIES.contains(&word.to_lowercase().as_str()) {
+            filtered.extend(word.chars().map(|_| '*'));
+        } else {
+            filtered.push_str(word);
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut filtered);
+            filtered.push(c);
+        }
+    }
+    flush(&mut word, &mut filtered);
+    filtered
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatEntry {
+    pub name: String,
+    pub content: ChatContent,
+    // sent from this computer, muting only hides the others
+    pub own: bool,
+}
+
+// Everything said during the current network game
+#[derive(Resource, Default, Debug)]
+pub struct ChatLog {
+    pub entries: Vec<ChatEntry>,
+    // hides the messages of the others, they are still kept in case the chat is unmuted
+    pub muted: bool,
+}
+
+impl ChatLog {
+    pub fn visible(&self) -> impl Iterator<Item = &ChatEntry> {
+        self.entries.iter().filter(|entry| entry.own || !self.muted)
+    }
+}
+
+// Sent by the chat panel, the message is added to the log once it's sent
+#[derive(Event, Debug, Clone)]
+pub struct ChatSent(pub ChatContent);
+
+// spectators only read the chat of the players
+pub fn send_chat(
+    session: Option<Res<NetworkSession>>,
+    mut sent: EventReader<ChatSent>,
+    mut chat: ResMut<ChatLog>,
+) {
+    let Some(session) = session else {
+        sent.clear();
+        return;
+    };
+    if session.role == NetworkRole::Spectator {
+        sent.clear();
+        return;
+    }
+    let name = session.name.clone();
+    for ChatSent(content) in sent.read() {
+        let content = match content {
+            ChatContent::Text(text) => {
+                let text: String = text.trim().chars().take(MAX_CHAT_LENGTH).collect();
+                if text.is_empty() {
+                    continue;
+                }
+                ChatContent::Text(text)
+            }
+            emote => emote.clone(),
+        };
+        session.connection.send(Message::Chat {
+            name: name.clone(),
+            content: content.clone(),
+        });
+        chat.entries.push(ChatEntry {
+            name: name.clone(),
+            content,
+            own: true,
+        });
+    }
+}
+
+pub fn clear_chat(mut chat: ResMut<ChatLog>) {
+    chat.entries.clear();
+    chat.muted = false;
+}
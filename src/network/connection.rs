@@ -0,0 +1,156 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::protocol::Message;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// how often the listener checks whether hosting was cancelled
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+
+pub enum NetEvent {
+    Received(Message),
+    Disconnected,
+}
+
+// An open connection to the other player, the socket is read and written on its own threads so
+// the game never waits for the network. Dropping it closes the socket once everything that
+// was sent is written
+pub struct Connection {
+    outgoing: Sender<Message>,
+    incoming: Mutex<Receiver<NetEvent>>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let (outgoing, to_write) = mpsc::channel::<Message>();
+        let (received, incoming) = mpsc::channel();
+
+        let mut writer = stream.try_clone()?;
+        thread::spawn(move || {
+            for message in to_write {
+                let line = message.to_line() + "\n";
+                if writer.write_all(line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            // also wakes up the reader thread so it can end
+            let _ = writer.shutdown(Shutdown::Both);
+        });
+
+        let reader = BufReader::new(stream);
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let event = match Message::parse(&line) {
+                    Some(message) => NetEvent::Received(message),
+                    // the other side doesn't speak the protocol
+                    None => NetEvent::Disconnected,
+                };
+                if received.send(event).is_err() {
+                    return;
+                }
+            }
+            let _ = received.send(NetEvent::Disconnected);
+        });
+
+        Ok(Connection {
+            outgoing,
+            incoming: Mutex::new(incoming),
+        })
+    }
+
+    pub fn send(&self, message: Message) {
+        // if the writer is gone the reader reports the disconnection
+        let _ = self.outgoing.send(message);
+    }
+
+    // the next thing that arrived, if anything did
+    pub fn receive(&self) -> Option<NetEvent> {
+        self.incoming.lock().unwrap().try_recv().ok()
+    }
+}
+
+// Waits for the other player on a background thread, either by listening for them or by
+// connecting to them
+pub struct PendingConnection {
+    result: Mutex<Receiver<io::Result<TcpStream>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl PendingConnection {
+    pub fn host(port: u16) -> Self {
+        let (sender, result) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let stop = cancelled.clone();
+        thread::spawn(move || {
+            let _ = sender.send(accept(port, &stop));
+        });
+        PendingConnection {
+            result: Mutex::new(result),
+            cancelled,
+        }
+    }
+
+    // connects after waiting for `delay`, so failed attempts can be retried without flooding
+    pub fn join(address: String, delay: Duration) -> Self {
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            let _ = sender.send(connect(&address));
+        });
+        PendingConnection {
+            result: Mutex::new(result),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // the socket once the other player is there, or why it failed
+    pub fn poll(&self) -> Option<io::Result<TcpStream>> {
+        self.result.lock().unwrap().try_recv().ok()
+    }
+}
+
+impl Drop for PendingConnection {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+fn accept(port: u16, cancelled: &AtomicBool) -> io::Result<TcpStream> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    while !cancelled.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                return Ok(stream);
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+            Err(err) => return Err(err),
+        }
+    }
+    Err(io::ErrorKind::Interrupted.into())
+}
+
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, "invalid address");
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
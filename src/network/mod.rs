@@ -0,0 +1,592 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use crate::{
+    logic::{
+        board::{
+            BoardChanged, BoardRecource, BoardReset, GameEnded, MoveHistory, MoveRequested,
+            StartingPosition,
+        },
+        clock::GameClock,
+    },
+    states::{
+        AppState, BoardInputBlocked, GameModeState, LocalControl, MoveAuthority, PlayerColor,
+        PlayerNames,
+    },
+};
+
+use self::{
+    chat::{clear_chat, send_chat, ChatEntry, ChatLog, ChatSent},
+    connection::{Connection, NetEvent, PendingConnection},
+    protocol::{Message, PROTOCOL_VERSION},
+};
+
+pub mod chat;
+pub mod connection;
+pub mod protocol;
+pub mod server;
+
+// Games between two computers, one player hosts and the other joins by address. The host's board
+// is the one that counts, the player who joined only sends the moves they want to make and plays
+// the moves the host sends back
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkStatus>()
+            .init_resource::<ChatLog>()
+            .add_event::<ChatSent>()
+            .add_systems(
+                Update,
+                (wait_for_opponent, handshake)
+                    .chain()
+                    .run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(
+                Update,
+                (
+                    reconnect,
+                    send_moves,
+                    send_chat,
+                    receive_messages,
+                    broadcast_moves,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Network)),
+            )
+            .add_systems(OnExit(AppState::InGame), (close_session, clear_chat));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkRole {
+    Host,
+    Guest,
+    // only watches a game on a server
+    Spectator,
+}
+
+// What's happening with the connection, shown on the network screen
+#[derive(Resource, Default, Debug, Clone)]
+pub struct NetworkStatus(pub String);
+
+// How long a lost connection to a server is retried, the server keeps the seat for a while too
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+// Exists while waiting for the other player to show up
+#[derive(Resource)]
+pub struct NetworkLobby {
+    pending: PendingConnection,
+    role: NetworkRole,
+    // where the connection goes, empty when hosting
+    address: String,
+}
+
+// Exists while connected to the other player
+#[derive(Resource)]
+pub struct NetworkSession {
+    connection: Connection,
+    pub role: NetworkRole,
+    // the name this side goes by
+    pub name: String,
+    pub opponent: String,
+    // the players of the game, for spectators
+    pub watching: Option<(String, String)>,
+    // the color played on this computer, once the colors are known
+    pub color: Option<bool>,
+    pub spectators: u32,
+    // the opponent lost the connection and has this long to come back
+    pub opponent_away: Option<Duration>,
+    // false until both sides agreed on the protocol version and the colors
+    started: bool,
+    address: String,
+    // lets the player get back into a game on a server, see `Message::Resume`
+    resume: Option<u64>,
+    // set while trying to get back into the game after losing the connection
+    reconnecting: Option<Reconnect>,
+}
+
+struct Reconnect {
+    pending: PendingConnection,
+    lost_at: Instant,
+}
+
+impl NetworkSession {
+    pub fn is_reconnecting(&self) -> bool {
+        self.reconnecting.is_some()
+    }
+}
+
+pub fn host_game(commands: &mut Commands, status: &mut NetworkStatus, port: u16) {
+    commands.remove_resource::<NetworkSession>();
+    commands.insert_resource(NetworkLobby {
+        pending: PendingConnection::host(port),
+        role: NetworkRole::Host,
+        address: String::new(),
+    });
+    status.0 = format!("Waiting for a player on port {}", port);
+}
+
+pub fn join_game(commands: &mut Commands, status: &mut NetworkStatus, address: String) {
+    commands.remove_resource::<NetworkSession>();
+    status.0 = format!("Connecting to {}", address);
+    commands.insert_resource(NetworkLobby {
+        pending: PendingConnection::join(address.clone(), Duration::ZERO),
+        role: NetworkRole::Guest,
+        address,
+    });
+}
+
+// watches the latest game of a server
+pub fn watch_game(commands: &mut Commands, status: &mut NetworkStatus, address: String) {
+    commands.remove_resource::<NetworkSession>();
+    status.0 = format!("Connecting to {}", address);
+    commands.insert_resource(NetworkLobby {
+        pending: PendingConnection::join(address.clone(), Duration::ZERO),
+        role: NetworkRole::Spectator,
+        address,
+    });
+}
+
+// stops hosting or joining, and closes the connection if there is one
+pub fn cancel_network_game(commands: &mut Commands, status: &mut NetworkStatus) {
+    commands.remove_resource::<NetworkLobby>();
+    commands.remove_resource::<NetworkSession>();
+    status.0.clear();
+}
+
+fn wait_for_opponent(
+    mut commands: Commands,
+    lobby: Option<Res<NetworkLobby>>,
+    mut status: ResMut<NetworkStatus>,
+    names: Res<PlayerNames>,
+    player_color: Res<PlayerColor>,
+) {
+    let Some(lobby) = lobby else {
+        return;
+    };
+    let Some(result) = lobby.pending.poll() else {
+        return;
+    };
+    commands.remove_resource::<NetworkLobby>();
+
+    match result.and_then(Connection::new) {
+        Ok(connection) => {
+            let name = names.get(player_color.0).to_string();
+            match lobby.role {
+                NetworkRole::Host => {}
+                NetworkRole::Guest => connection.send(Message::Hello {
+                    version: PROTOCOL_VERSION,
+                    name: name.clone(),
+                }),
+                NetworkRole::Spectator => connection.send(Message::Watch {
+                    version: PROTOCOL_VERSION,
+                    game: None,
+                    name: name.clone(),
+                }),
+            }
+            status.0 = "Connected, starting the game".to_string();
+            commands.insert_resource(NetworkSession {
+                connection,
+                role: lobby.role,
+                name,
+                opponent: String::new(),
+                watching: None,
+                color: None,
+                spectators: 0,
+                opponent_away: None,
+                started: false,
+                address: lobby.address.clone(),
+                resume: None,
+                reconnecting: None,
+            });
+        }
+        Err(err) => status.0 = format!("Connection failed: {}", err),
+    }
+}
+
+// the player who joined says hello and the host answers with the colors, then the game starts.
+// Spectators get the players and the moves played so far instead
+#[allow(clippy::too_many_arguments)]
+fn handshake(
+    mut commands: Commands,
+    session: Option<ResMut<NetworkSession>>,
+    mut status: ResMut<NetworkStatus>,
+    names: Res<PlayerNames>,
+    mut player_color: ResMut<PlayerColor>,
+    mut local_control: ResMut<LocalControl>,
+    mut authority: ResMut<MoveAuthority>,
+    mut starting_position: ResMut<StartingPosition>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if session.started {
+        return;
+    }
+    // only the handshake is read here, the messages after it are left for the game
+    let Some(event) = session.connection.receive() else {
+        return;
+    };
+    // Ok(true) once the game can start
+    let step = match (session.role, event) {
+        (NetworkRole::Host, NetEvent::Received(Message::Hello { version, name })) => {
+            if version != PROTOCOL_VERSION {
+                session.connection.send(Message::Error(format!(
+                    "the host uses protocol version {}",
+                    PROTOCOL_VERSION
+                )));
+                Err(format!(
+                    "The other player uses protocol version {}",
+                    version
+                ))
+            } else {
+                session.connection.send(Message::Welcome {
+                    version: PROTOCOL_VERSION,
+                    color: !player_color.0,
+                    name: names.get(player_color.0).to_string(),
+                });
+                session
+                    .connection
+                    .send(Message::Position { moves: Vec::new() });
+                session.opponent = name;
+                session.color = Some(player_color.0);
+                *authority = MoveAuthority::Local;
+                *local_control = LocalControl::only(player_color.0);
+                Ok(true)
+            }
+        }
+        (NetworkRole::Host, NetEvent::Received(Message::Watch { .. })) => {
+            session.connection.send(Message::Error(
+                "games can only be watched on a server".to_string(),
+            ));
+            Err("Someone tried to watch, games can only be watched on a server".to_string())
+        }
+        (
+            NetworkRole::Guest,
+            NetEvent::Received(Message::Welcome {
+                version,
+                color,
+                name,
+            }),
+        ) => {
+            if version != PROTOCOL_VERSION {
+                Err(format!("The host uses protocol version {}", version))
+            } else {
+                player_color.0 = color;
+                session.opponent = name;
+                session.color = Some(color);
+                *authority = MoveAuthority::Remote;
+                *local_control = LocalControl::only(color);
+                Ok(false)
+            }
+        }
+        // only servers hand out sessions, a player who hosts can't be reconnected to
+        (NetworkRole::Guest, NetEvent::Received(Message::Session(id))) => {
+            session.resume = Some(id);
+            Ok(false)
+        }
+        (NetworkRole::Guest, NetEvent::Received(Message::Position { moves }))
+            if session.color.is_some() =>
+        {
+            starting_position.moves = moves;
+            Ok(true)
+        }
+        (
+            NetworkRole::Spectator,
+            NetEvent::Received(Message::Watching {
+                version,
+                white,
+                black,
+            }),
+        ) => {
+            if version != PROTOCOL_VERSION {
+                Err(format!("The server uses protocol version {}", version))
+            } else {
+                session.watching = Some((white, black));
+                Ok(false)
+            }
+        }
+        (NetworkRole::Spectator, NetEvent::Received(Message::Position { moves }))
+            if session.watching.is_some() =>
+        {
+            starting_position.moves = moves;
+            // spectators see the game from white's side
+            player_color.0 = true;
+            *authority = MoveAuthority::Remote;
+            *local_control = LocalControl::NONE;
+            Ok(true)
+        }
+        (_, NetEvent::Received(Message::Error(reason))) => Err(reason),
+        (_, NetEvent::Received(_)) => Err("Unexpected message".to_string()),
+        (_, NetEvent::Disconnected) => Err("The connection was closed".to_string()),
+    };
+    match step {
+        Ok(false) => {}
+        Ok(true) => {
+            // the game mode is already set by the network screen
+            status.0 = match &session.watching {
+                Some((white, black)) => format!("Watching {} against {}", white, black),
+                None => format!("Playing against {}", session.opponent),
+            };
+            session.started = true;
+            app_state.set(AppState::InGame);
+        }
+        Err(reason) => {
+            status.0 = reason;
+            commands.remove_resource::<NetworkSession>();
+        }
+    }
+}
+
+// the player who joined asks the host to play their moves
+fn send_moves(session: Option<Res<NetworkSession>>, mut requests: EventReader<MoveRequested>) {
+    let Some(session) = session else {
+        return;
+    };
+    if session.role != NetworkRole::Guest {
+        requests.clear();
+        return;
+    }
+    for request in requests.read() {
+        session.connection.send(Message::Move {
+            src: request.src,
+            dst: request.dst,
+        });
+    }
+}
+
+// every move played on the host's board is sent to the player who joined, including their own,
+// together with the clocks and the end of the game
+fn broadcast_moves(
+    session: Option<Res<NetworkSession>>,
+    mut board_events: EventReader<BoardChanged>,
+    mut game_ended: EventReader<GameEnded>,
+    clock: Option<Res<GameClock>>,
+) {
+    let Some(session) = session else {
+        return;
+    };
+    if session.role != NetworkRole::Host {
+        board_events.clear();
+        game_ended.clear();
+        return;
+    }
+    let clock_started = clock.as_ref().is_some_and(|clock| clock.is_added());
+    let clock = clock.as_deref();
+    let send_clock = |clock: &GameClock| {
+        session.connection.send(Message::Clock {
+            white: clock.white,
+            black: clock.black,
+        });
+    };
+    // the player who joined only gets a clock when the game is played with one
+    if let Some(clock) = clock.filter(|_| clock_started) {
+        send_clock(clock);
+    }
+    for event in board_events.read() {
+        session.connection.send(Message::Moved {
+            src: event.src,
+            dst: event.dst,
+        });
+        if let Some(clock) = clock {
+            send_clock(clock);
+        }
+    }
+    for event in game_ended.read() {
+        session.connection.send(Message::GameOver {
+            result: event.result,
+            reason: event.reason,
+        });
+    }
+}
+
+// gets back into a server game after losing the connection, the server sends the whole game
+// again once it knows the session
+fn reconnect(
+    session: Option<ResMut<NetworkSession>>,
+    mut status: ResMut<NetworkStatus>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_mode: ResMut<NextState<GameModeState>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    let (Some(reconnecting), Some(id)) = (&session.reconnecting, session.resume) else {
+        return;
+    };
+    let lost_at = reconnecting.lost_at;
+    let result = reconnecting.pending.poll();
+    match result.map(|result| result.and_then(Connection::new)) {
+        None => {}
+        Some(Ok(connection)) => {
+            connection.send(Message::Resume {
+                version: PROTOCOL_VERSION,
+                session: id,
+            });
+            session.connection = connection;
+            session.reconnecting = None;
+            status.0 = format!("Playing against {}", session.opponent);
+        }
+        Some(Err(_)) if lost_at.elapsed() < RECONNECT_TIMEOUT => {
+            let pending = PendingConnection::join(session.address.clone(), RETRY_DELAY);
+            session.reconnecting = Some(Reconnect { pending, lost_at });
+        }
+        Some(Err(err)) => {
+            status.0 = format!("Couldn't get back into the game: {}", err);
+            app_state.set(AppState::Menu);
+            game_mode.set(GameModeState::NotInGame);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive_messages(
+    mut commands: Commands,
+    session: Option<ResMut<NetworkSession>>,
+    mut board: ResMut<BoardRecource>,
+    mut board_changed: EventWriter<BoardChanged>,
+    mut game_ended: EventWriter<GameEnded>,
+    mut board_reset: EventWriter<BoardReset>,
+    mut history: ResMut<MoveHistory>,
+    mut clock: Option<ResMut<GameClock>>,
+    mut input_blocked: ResMut<BoardInputBlocked>,
+    mut chat: ResMut<ChatLog>,
+    player_color: Res<PlayerColor>,
+    mut status: ResMut<NetworkStatus>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_mode: ResMut<NextState<GameModeState>>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    if session.is_reconnecting() {
+        return;
+    }
+    // the player who joined and the spectators follow the board of the host or the server
+    let remote = session.role != NetworkRole::Host;
+    while let Some(event) = session.connection.receive() {
+        let failure = match (session.role, event) {
+            (NetworkRole::Host, NetEvent::Received(Message::Move { src, dst })) => {
+                // the other player can only move their own pieces, and only while the game is on
+                let own_piece = board.grid[src].is_some_and(|p| p.get_color() != player_color.0);
+                let playing = board.result.is_none();
+                if !(playing && own_piece && board.play(src, dst, &mut board_changed)) {
+                    session.connection.send(Message::Reject { src, dst });
+                }
+                None
+            }
+            (_, NetEvent::Received(Message::Moved { src, dst })) if remote => {
+                if board.play(src, dst, &mut board_changed) {
+                    None
+                } else {
+                    session
+                        .connection
+                        .send(Message::Error("the boards are out of sync".to_string()));
+                    Some("The boards are out of sync".to_string())
+                }
+            }
+            // the board wasn't changed, so the player just tries another move
+            (NetworkRole::Guest, NetEvent::Received(Message::Reject { .. })) => None,
+            (_, NetEvent::Received(Message::Clock { white, black })) if remote => {
+                match clock.as_deref_mut() {
+                    Some(clock) => {
+                        clock.white = white;
+                        clock.black = black;
+                    }
+                    // the first one shows that the game is played with a clock
+                    None => commands.insert_resource(GameClock::showing(white, black)),
+                }
+                None
+            }
+            (_, NetEvent::Received(Message::GameOver { result, reason })) if remote => {
+                // checkmates are already found on this side too
+                if board.result.is_none() {
+                    board.result = Some(result);
+                    game_ended.send(GameEnded { result, reason });
+                }
+                None
+            }
+            // sent again when getting back into a game, the colors didn't change
+            (_, NetEvent::Received(Message::Welcome { .. })) if remote => None,
+            // the whole game after getting back into it, the moves missed in between are in it
+            (_, NetEvent::Received(Message::Position { moves })) if remote => {
+                let mut synced = BoardRecource::new_game();
+                if moves
+                    .iter()
+                    .all(|&(src, dst)| synced.make_move(src, dst).is_some())
+                {
+                    *board = synced;
+                    history.moves = moves;
+                    board_reset.send(BoardReset);
+                    input_blocked.0 = false;
+                    None
+                } else {
+                    Some("The game sent by the server isn't valid".to_string())
+                }
+            }
+            (_, NetEvent::Received(Message::Away { grace })) => {
+                session.opponent_away = Some(grace);
+                None
+            }
+            (_, NetEvent::Received(Message::Back)) => {
+                session.opponent_away = None;
+                None
+            }
+            (_, NetEvent::Received(Message::Chat { name, content })) => {
+                // the player who joined can't speak for someone else
+                let name = match session.role {
+                    NetworkRole::Host => session.opponent.clone(),
+                    _ => name,
+                };
+                chat.entries.push(ChatEntry {
+                    name,
+                    content,
+                    own: false,
+                });
+                None
+            }
+            (_, NetEvent::Received(Message::Spectators(count))) => {
+                session.spectators = count;
+                None
+            }
+            // once the game is over the other side may close the connection, the final position
+            // stays on the screen until the player leaves
+            (_, NetEvent::Disconnected) if board.result.is_some() => {
+                commands.remove_resource::<NetworkSession>();
+                return;
+            }
+            // a server keeps the seat for a while, so the player can come back
+            (NetworkRole::Guest, NetEvent::Disconnected) if session.resume.is_some() => {
+                session.reconnecting = Some(Reconnect {
+                    pending: PendingConnection::join(session.address.clone(), Duration::ZERO),
+                    lost_at: Instant::now(),
+                });
+                input_blocked.0 = true;
+                status.0 = "Connection lost, reconnecting".to_string();
+                return;
+            }
+            (_, NetEvent::Received(Message::Error(reason))) => Some(reason),
+            (_, NetEvent::Received(_)) => Some("Unexpected message".to_string()),
+            (NetworkRole::Spectator, NetEvent::Disconnected) => {
+                Some("The game can't be watched anymore".to_string())
+            }
+            (_, NetEvent::Disconnected) => Some(format!("{} left the game", session.opponent)),
+        };
+        if let Some(reason) = failure {
+            status.0 = reason;
+            commands.remove_resource::<NetworkSession>();
+            app_state.set(AppState::Menu);
+            game_mode.set(GameModeState::NotInGame);
+            return;
+        }
+    }
+}
+
+fn close_session(mut commands: Commands) {
+    commands.remove_resource::<NetworkSession>();
+}
@@ -0,0 +1,370 @@
+use std::time::Duration;
+
+use crate::logic::board::{move_name, parse_move, GameEndReason, GameResult};
+
+use super::chat::{ChatContent, Emote};
+
+// bumped whenever a message changes, players with different versions can't play together
+pub const PROTOCOL_VERSION: u32 = 5;
+pub const DEFAULT_PORT: u16 = 7878;
+
+// Every message is a single line of text, the first word is the kind of the message, e.g.
+//   HELLO 1 Alice
+//   WELCOME 1 black Bob
+//   MOVE e15e13
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    // sent by the player who joins, right after connecting
+    Hello {
+        version: u32,
+        name: String,
+    },
+    // the host's answer to `Hello`, `color` is the color of the player who joined, followed by
+    // the `Position` of the game
+    Welcome {
+        version: u32,
+        color: bool,
+        name: String,
+    },
+    // sent instead of `Hello` to watch a game, the latest one if no game is given
+    Watch {
+        version: u32,
+        game: Option<u64>,
+        name: String,
+    },
+    // the answer to `Watch`, followed by the `Position` of the game
+    Watching {
+        version: u32,
+        white: String,
+        black: String,
+    },
+    // all the moves played so far
+    Position {
+        moves: Vec<(usize, usize)>,
+    },
+    // how many people are watching the game, sent to everyone whenever it changes
+    Spectators(u32),
+    // the id a player needs to get back into their game after losing the connection, sent by
+    // the server right after `Welcome`
+    Session(u64),
+    // sent instead of `Hello` to get back into a game, answered like `Hello`
+    Resume {
+        version: u32,
+        session: u64,
+    },
+    // the opponent lost the connection, they lose if they aren't back in time
+    Away {
+        grace: Duration,
+    },
+    // the opponent is back
+    Back,
+    // a move the player who joined wants to make
+    Move {
+        src: usize,
+        dst: usize,
+    },
+    // a move that was played on the host's board, by either player
+    Moved {
+        src: usize,
+        dst: usize,
+    },
+    // the host didn't accept the `Move`
+    Reject {
+        src: usize,
+        dst: usize,
+    },
+    // the time both players have left, sent by the host after every move
+    Clock {
+        white: Duration,
+        black: Duration,
+    },
+    // the game is over, e.g. because a player ran out of time
+    GameOver {
+        result: GameResult,
+        reason: GameEndReason,
+    },
+    // a chat message or emote, the host and the server replace the name with the one of the
+    // player who sent it
+    Chat {
+        name: String,
+        content: ChatContent,
+    },
+    // something went wrong, the connection is closed after it
+    Error(String),
+}
+
+fn color_name(color: bool) -> &'static str {
+    if color {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+impl Message {
+    pub fn to_line(&self) -> String {
+        match self {
+            Message::Hello { version, name } => format!("HELLO {} {}", version, name),
+            Message::Welcome {
+                version,
+                color,
+                name,
+            } => format!("WELCOME {} {} {}", version, color_name(*color), name),
+            Message::Watch {
+                version,
+                game,
+                name,
+            } => {
+                let game = game.map_or("*".to_string(), |game| game.to_string());
+                format!("WATCH {} {} {}", version, game, name)
+            }
+            // names can hold spaces but no control characters, so they are split with a tab
+            Message::Watching {
+                version,
+                white,
+                black,
+            } => format!("WATCHING {} {}\t{}", version, white, black),
+            Message::Position { moves } => {
+                let moves: Vec<String> = moves
+                    .iter()
+                    .map(|&(src, dst)| move_name(src, dst))
+                    .collect();
+                format!("POSITION {}", moves.join(" "))
+            }
+            Message::Spectators(count) => format!("SPECTATORS {}", count),
+            Message::Session(session) => format!("SESSION {}", session),
+            Message::Resume { version, session } => format!("RESUME {} {}", version, session),
+            Message::Away { grace } => format!("AWAY {}", grace.as_secs()),
+            Message::Back => "BACK".to_string(),
+            Message::Move { src, dst } => format!("MOVE {}", move_name(*src, *dst)),
+            Message::Moved { src, dst } => format!("MOVED {}", move_name(*src, *dst)),
+            Message::Reject { src, dst } => format!("REJECT {}", move_name(*src, *dst)),
+            Message::Clock { white, black } => {
+                format!("CLOCK {} {}", white.as_millis(), black.as_millis())
+            }
+            Message::GameOver { result, reason } => {
+                format!("RESULT {} {}", result.name(), reason.name())
+            }
+            Message::Chat { name, content } => match content {
+                ChatContent::Text(text) => format!("CHAT {}\t{}", name, text),
+                ChatContent::Emote(emote) => format!("EMOTE {}\t{}", name, emote.name()),
+            },
+            Message::Error(reason) => format!("ERROR {}", reason),
+        }
+    }
+
+    pub fn parse(line: &str) -> Option<Message> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match kind {
+            "HELLO" => {
+                let (version, name) = rest.split_once(' ').unwrap_or((rest, ""));
+                Some(Message::Hello {
+                    version: version.parse().ok()?,
+                    name: name.to_string(),
+                })
+            }
+            "WELCOME" => {
+                let mut parts = rest.splitn(3, ' ');
+                let version = parts.next()?.parse().ok()?;
+                let color = match parts.next()? {
+                    "white" => true,
+                    "black" => false,
+                    _ => return None,
+                };
+                Some(Message::Welcome {
+                    version,
+                    color,
+                    name: parts.next().unwrap_or("").to_string(),
+                })
+            }
+            "WATCH" => {
+                let mut parts = rest.splitn(3, ' ');
+                let version = parts.next()?.parse().ok()?;
+                let game = match parts.next()? {
+                    "*" => None,
+                    game => Some(game.parse().ok()?),
+                };
+                Some(Message::Watch {
+                    version,
+                    game,
+                    name: parts.next().unwrap_or("").to_string(),
+                })
+            }
+            "WATCHING" => {
+                let (version, names) = rest.split_once(' ')?;
+                let (white, black) = names.split_once('\t')?;
+                Some(Message::Watching {
+                    version: version.parse().ok()?,
+                    white: white.to_string(),
+                    black: black.to_string(),
+                })
+            }
+            "POSITION" => {
+                let moves = rest
+                    .split_whitespace()
+                    .map(parse_move)
+                    .collect::<Option<_>>()?;
+                Some(Message::Position { moves })
+            }
+            "SPECTATORS" => Some(Message::Spectators(rest.parse().ok()?)),
+            "SESSION" => Some(Message::Session(rest.parse().ok()?)),
+            "RESUME" => {
+                let (version, session) = rest.split_once(' ')?;
+                Some(Message::Resume {
+                    version: version.parse().ok()?,
+                    session: session.parse().ok()?,
+                })
+            }
+            "AWAY" => Some(Message::Away {
+                grace: Duration::from_secs(rest.parse().ok()?),
+            }),
+            "BACK" => Some(Message::Back),
+            "MOVE" => parse_move(rest).map(|(src, dst)| Message::Move { src, dst }),
+            "MOVED" => parse_move(rest).map(|(src, dst)| Message::Moved { src, dst }),
+            "REJECT" => parse_move(rest).map(|(src, dst)| Message::Reject { src, dst }),
+            "CLOCK" => {
+                let (white, black) = rest.split_once(' ')?;
+                Some(Message::Clock {
+                    white: Duration::from_millis(white.parse().ok()?),
+                    black: Duration::from_millis(black.parse().ok()?),
+                })
+            }
+            "RESULT" => {
+                let (result, reason) = rest.split_once(' ')?;
+                Some(Message::GameOver {
+                    result: GameResult::parse(result)?,
+                    reason: GameEndReason::parse(reason)?,
+                })
+            }
+            "CHAT" => {
+                let (name, text) = rest.split_once('\t')?;
+                Some(Message::Chat {
+                    name: name.to_string(),
+                    content: ChatContent::Text(text.to_string()),
+                })
+            }
+            "EMOTE" => {
+                let (name, emote) = rest.split_once('\t')?;
+                Some(Message::Chat {
+                    name: name.to_string(),
+                    content: ChatContent::Emote(Emote::parse(emote)?),
+                })
+            }
+            "ERROR" => Some(Message::Error(rest.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_message_round_trips() {
+        let mut messages = vec![
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                name: "Alice Smith".to_string(),
+            },
+            Message::Welcome {
+                version: PROTOCOL_VERSION,
+                color: false,
+                name: "Bob".to_string(),
+            },
+            Message::Watch {
+                version: PROTOCOL_VERSION,
+                game: None,
+                name: "Carol".to_string(),
+            },
+            Message::Watch {
+                version: PROTOCOL_VERSION,
+                game: Some(42),
+                name: "Carol".to_string(),
+            },
+            Message::Watching {
+                version: PROTOCOL_VERSION,
+                white: "Alice Smith".to_string(),
+                black: "Bob".to_string(),
+            },
+            Message::Position { moves: Vec::new() },
+            Message::Position {
+                moves: vec![parse_move("e3e5").unwrap(), parse_move("e14e12").unwrap()],
+            },
+            Message::Spectators(3),
+            Message::Session(u64::MAX),
+            Message::Resume {
+                version: PROTOCOL_VERSION,
+                session: 7,
+            },
+            Message::Away {
+                grace: Duration::from_secs(60),
+            },
+            Message::Back,
+            Message::Move { src: 34, dst: 66 },
+            Message::Moved { src: 34, dst: 66 },
+            Message::Reject { src: 34, dst: 66 },
+            Message::Clock {
+                white: Duration::from_millis(599_999),
+                black: Duration::ZERO,
+            },
+            Message::Chat {
+                name: "Alice Smith".to_string(),
+                content: ChatContent::Text("good luck, ça va? ♞".to_string()),
+            },
+            Message::Error("the boards are out of sync".to_string()),
+        ];
+        for result in [
+            GameResult::Win(true),
+            GameResult::Win(false),
+            GameResult::Draw,
+        ] {
+            for reason in GameEndReason::ALL {
+                messages.push(Message::GameOver { result, reason });
+            }
+        }
+        for emote in Emote::ALL {
+            messages.push(Message::Chat {
+                name: "Bob".to_string(),
+                content: ChatContent::Emote(emote),
+            });
+        }
+
+        for message in messages {
+            let line = message.to_line();
+            assert_eq!(Message::parse(&line), Some(message.clone()), "{}", line);
+            assert_eq!(Message::parse(&format!("{}\r\n", line)), Some(message));
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_none() {
+        for line in [
+            "",
+            "HELLO",
+            "HELLO one Alice",
+            "WELCOME 5 purple Bob",
+            "WATCH 5 latest Carol",
+            "WATCHING 5 Alice Bob",
+            "POSITION e3e5 e99e12",
+            "SPECTATORS -1",
+            "RESUME 5",
+            "AWAY soon",
+            "MOVE e3",
+            "MOVED",
+            "REJECT z3e5",
+            "CLOCK 1000",
+            "CLOCK 1000 -5",
+            "RESULT 1-0",
+            "RESULT 2-0 checkmate",
+            "RESULT 1-0 boredom",
+            "CHAT Alice good luck",
+            "EMOTE Bob\tdance",
+            "hello 5 Alice",
+            "TELEPORT e3e5",
+        ] {
+            assert_eq!(Message::parse(line), None, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+    net::TcpListener,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::logic::{
+    board::{BoardRecource, GameEndReason, GameResult},
+    clock::GameClock,
+    record::GameRecord,
+};
+
+use super::{
+    chat::{ChatContent, MAX_CHAT_LENGTH},
+    connection::{Connection, NetEvent},
+    protocol::{Message, DEFAULT_PORT, PROTOCOL_VERSION},
+};
+
+// how long the server sleeps when there's nothing to do
+const IDLE: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub port: u16,
+    // finished games are saved here
+    pub games_dir: PathBuf,
+    pub base: Duration,
+    pub increment: Duration,
+    // how long a player who lost the connection has to come back
+    pub grace: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            port: DEFAULT_PORT,
+            games_dir: PathBuf::from("games"),
+            base: Duration::from_secs(10 * 60),
+            increment: Duration::from_secs(5),
+            grace: Duration::from_secs(60),
+        }
+    }
+}
+
+struct Player {
+    connection: Connection,
+    name: String,
+}
+
+struct Seat {
+    player: Player,
+    // lets the player get back into the game, see `Message::Resume`
+    session: u64,
+    // when the player lost the connection, if they did
+    away_since: Option<Instant>,
+}
+
+impl Seat {
+    fn new(player: Player) -> Self {
+        // random enough that nobody can guess the seat of someone else
+        let session = RandomState::new().build_hasher().finish();
+        Seat {
+            player,
+            session,
+            away_since: None,
+        }
+    }
+}
+
+struct ServerGame {
+    id: u64,
+    // white first
+    players: [Seat; 2],
+    spectators: Vec<Player>,
+    board: BoardRecource,
+    clock: GameClock,
+    record: GameRecord,
+    last_tick: Instant,
+    grace: Duration,
+}
+
+impl ServerGame {
+    fn player(&self, color: bool) -> &Seat {
+        &self.players[usize::from(!color)]
+    }
+
+    fn player_mut(&mut self, color: bool) -> &mut Seat {
+        &mut self.players[usize::from(!color)]
+    }
+
+    fn broadcast(&self, message: Message) {
+        let players = self.players.iter().map(|seat| &seat.player);
+        for player in players.chain(&self.spectators) {
+            player.connection.send(message.clone());
+        }
+    }
+
+    // everything a player needs to see the game as it is now, after `Welcome`
+    fn send_position(&self, connection: &Connection) {
+        connection.send(Message::Position {
+            moves: self.record.moves.clone(),
+        });
+        connection.send(Message::Clock {
+            white: self.clock.white,
+            black: self.clock.black,
+        });
+        connection.send(Message::Spectators(self.spectators.len() as u32));
+    }
+
+    fn add_spectator(&mut self, spectator: Player) {
+        println!("game {}: {} is watching", self.id, spectator.name);
+        spectator.connection.send(Message::Watching {
+            version: PROTOCOL_VERSION,
+            white: self.players[0].player.name.clone(),
+            black: self.players[1].player.name.clone(),
+        });
+        self.send_position(&spectator.connection);
+        self.spectators.push(spectator);
+        self.broadcast(Message::Spectators(self.spectators.len() as u32));
+    }
+
+    // spectators don't send anything, they can only leave
+    fn receive_spectators(&mut self) {
+        let count = self.spectators.len();
+        self.spectators
+            .retain(|spectator| spectator.connection.receive().is_none());
+        if self.spectators.len() != count {
+            self.broadcast(Message::Spectators(self.spectators.len() as u32));
+        }
+    }
+
+    fn finish(&mut self, result: GameResult, reason: GameEndReason) {
+        self.board.result = Some(result);
+        self.record.set_result(result, reason);
+        self.broadcast(Message::GameOver { result, reason });
+    }
+
+    // handles everything one of the players sent
+    fn receive(&mut self, color: bool) {
+        while let Some(event) = self.player(color).player.connection.receive() {
+            if self.board.result.is_some() {
+                return;
+            }
+            match event {
+                NetEvent::Received(Message::Move { src, dst }) => self.play(color, src, dst),
+                NetEvent::Received(Message::Chat { content, .. }) => self.chat(color, content),
+                // the seat is kept for a while, so the player can come back
+                NetEvent::Disconnected => {
+                    println!(
+                        "game {}: {} lost the connection",
+                        self.id,
+                        self.player(color).player.name
+                    );
+                    self.player_mut(color).away_since = Some(Instant::now());
+                    self.player(!color)
+                        .player
+                        .connection
+                        .send(Message::Away { grace: self.grace });
+                }
+                NetEvent::Received(_) => {
+                    self.finish(GameResult::Win(!color), GameEndReason::Abandoned);
+                }
+            }
+        }
+    }
+
+    // gives the seat back to a player who lost the connection, returns the connection if the
+    // session isn't one of this game
+    fn resume(&mut self, session: u64, connection: Connection) -> Result<(), Connection> {
+        let Some(color) = [true, false]
+            .into_iter()
+            .find(|&color| self.player(color).session == session)
+        else {
+            return Err(connection);
+        };
+        println!(
+            "game {}: {} is back",
+            self.id,
+            self.player(color).player.name
+        );
+        connection.send(Message::Welcome {
+            version: PROTOCOL_VERSION,
+            color,
+            name: self.player(!color).player.name.clone(),
+        });
+        self.send_position(&connection);
+        let seat = self.player_mut(color);
+        seat.player.connection = connection;
+        seat.away_since = None;
+        self.player(!color).player.connection.send(Message::Back);
+        Ok(())
+    }
+
+    // passes a chat message on to everyone else and keeps it with the game
+    fn chat(&mut self, color: bool, content: ChatContent) {
+        let content = match content {
+            ChatContent::Text(text) => {
+                ChatContent::Text(text.chars().take(MAX_CHAT_LENGTH).collect())
+            }
+            emote => emote,
+        };
+        let name = self.player(color).player.name.clone();
+        self.record.push_chat(&name, &content.text());
+        let message = Message::Chat { name, content };
+        let others = [&self.player(!color).player].into_iter();
+        for player in others.chain(&self.spectators) {
+            player.connection.send(message.clone());
+        }
+    }
+
+    fn play(&mut self, color: bool, src: usize, dst: usize) {
+        // the time the mover took is charged while it's still their turn, a move that comes after
+        // their time ran out is too late
+        self.tick();
+        if self.board.result.is_some() {
+            return;
+        }
+        // a player can only move their own pieces, the board already checks whose turn it is
+        let own_piece = self.board.grid[src].is_some_and(|piece| piece.get_color() == color);
+        if !own_piece || self.board.make_move(src, dst).is_none() {
+            self.player(color)
+                .player
+                .connection
+                .send(Message::Reject { src, dst });
+            return;
+        }
+        self.clock.add_increment(color);
+        self.record.push_move(src, dst);
+        self.broadcast(Message::Moved { src, dst });
+        self.broadcast(Message::Clock {
+            white: self.clock.white,
+            black: self.clock.black,
+        });
+        if let Some((result, reason)) = self.board.game_status() {
+            self.finish(result, reason);
+        }
+    }
+
+    fn tick(&mut self) {
+        let now = Instant::now();
+        let color = self.board.turn;
+        let elapsed = now - self.last_tick;
+        self.last_tick = now;
+        if self.board.result.is_some() {
+            return;
+        }
+        // the clock of a player who lost the connection waits for them during the grace period
+        if self.player(color).away_since.is_none() && self.clock.tick(color, elapsed).is_zero() {
+            self.finish(GameResult::Win(!color), GameEndReason::Timeout);
+            return;
+        }
+        for color in [true, false] {
+            let away_since = self.player(color).away_since;
+            if away_since.is_some_and(|since| now - since > self.grace) {
+                self.finish(GameResult::Win(!color), GameEndReason::Abandoned);
+                return;
+            }
+        }
+    }
+}
+
+// Runs the games of everyone who connects, players are paired in the order they arrive. The
+// server holds the only board that counts, the players send the moves they want and get back
+// the moves that were played, just like with a player who hosts
+pub struct GameServer {
+    config: ServerConfig,
+    listener: TcpListener,
+    // connected but didn't say hello yet
+    connecting: Vec<Connection>,
+    // said hello, waiting for an opponent
+    waiting: Option<Player>,
+    games: Vec<ServerGame>,
+    next_id: u64,
+}
+
+impl GameServer {
+    pub fn bind(config: ServerConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", config.port))?;
+        listener.set_nonblocking(true)?;
+        Ok(GameServer {
+            config,
+            listener,
+            connecting: Vec::new(),
+            waiting: None,
+            games: Vec::new(),
+            next_id: 1,
+        })
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        println!("listening on port {}", self.config.port);
+        loop {
+            if !self.update()? {
+                thread::sleep(IDLE);
+            }
+        }
+    }
+
+    // does one round of work, returns false if there was nothing to do
+    pub fn update(&mut self) -> io::Result<bool> {
+        let mut busy = self.accept()?;
+        busy |= self.greet();
+        self.check_waiting();
+        for game in &mut self.games {
+            game.receive(true);
+            game.receive(false);
+            game.receive_spectators();
+            game.tick();
+        }
+        busy |= self.save_finished_games();
+        Ok(busy)
+    }
+
+    fn accept(&mut self) -> io::Result<bool> {
+        match self.listener.accept() {
+            Ok((stream, address)) => {
+                stream.set_nonblocking(false)?;
+                match Connection::new(stream) {
+                    Ok(connection) => self.connecting.push(connection),
+                    Err(err) => eprintln!("{}: {}", address, err),
+                }
+                Ok(true)
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn greet(&mut self) -> bool {
+        let mut busy = false;
+        for connection in std::mem::take(&mut self.connecting) {
+            match connection.receive() {
+                None => self.connecting.push(connection),
+                Some(NetEvent::Received(Message::Hello { version, name })) => {
+                    busy = true;
+                    if version != PROTOCOL_VERSION {
+                        connection.send(Message::Error(format!(
+                            "the server uses protocol version {}",
+                            PROTOCOL_VERSION
+                        )));
+                        continue;
+                    }
+                    let player = Player { connection, name };
+                    match self.waiting.take() {
+                        Some(opponent) => self.start_game(opponent, player),
+                        None => self.waiting = Some(player),
+                    }
+                }
+                Some(NetEvent::Received(Message::Watch {
+                    version,
+                    game,
+                    name,
+                })) => {
+                    busy = true;
+                    if version != PROTOCOL_VERSION {
+                        connection.send(Message::Error(format!(
+                            "the server uses protocol version {}",
+                            PROTOCOL_VERSION
+                        )));
+                        continue;
+                    }
+                    let watched = match game {
+                        Some(id) => self.games.iter_mut().find(|game| game.id == id),
+                        None => self.games.last_mut(),
+                    };
+                    match watched {
+                        Some(watched) => watched.add_spectator(Player { connection, name }),
+                        None => connection.send(Message::Error("no game to watch".to_string())),
+                    }
+                }
+                Some(NetEvent::Received(Message::Resume { version, session })) => {
+                    busy = true;
+                    if version != PROTOCOL_VERSION {
+                        connection.send(Message::Error(format!(
+                            "the server uses protocol version {}",
+                            PROTOCOL_VERSION
+                        )));
+                        continue;
+                    }
+                    let mut connection = Some(connection);
+                    for game in &mut self.games {
+                        match game.resume(session, connection.take().unwrap()) {
+                            Ok(()) => break,
+                            Err(unused) => connection = Some(unused),
+                        }
+                    }
+                    if let Some(connection) = connection {
+                        connection.send(Message::Error("the game is over".to_string()));
+                    }
+                }
+                // anything else means it's not one of our players
+                Some(_) => busy = true,
+            }
+        }
+        busy
+    }
+
+    // the player waiting for an opponent may leave before one shows up
+    fn check_waiting(&mut self) {
+        let Some(player) = &self.waiting else {
+            return;
+        };
+        if let Some(NetEvent::Disconnected) | Some(NetEvent::Received(_)) =
+            player.connection.receive()
+        {
+            self.waiting = None;
+        }
+    }
+
+    fn start_game(&mut self, white: Player, black: Player) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let players = [Seat::new(white), Seat::new(black)];
+        for (seat, color, opponent) in [
+            (&players[0], true, &players[1]),
+            (&players[1], false, &players[0]),
+        ] {
+            let connection = &seat.player.connection;
+            connection.send(Message::Welcome {
+                version: PROTOCOL_VERSION,
+                color,
+                name: opponent.player.name.clone(),
+            });
+            connection.send(Message::Session(seat.session));
+            connection.send(Message::Position { moves: Vec::new() });
+        }
+        let [white, black] = &players;
+        println!(
+            "game {}: {} vs {}",
+            id, white.player.name, black.player.name
+        );
+
+        let mut record = GameRecord::new(&white.player.name, &black.player.name);
+        record.set_tag(
+            "TimeControl",
+            &format!(
+                "{}+{}",
+                self.config.base.as_secs(),
+                self.config.increment.as_secs()
+            ),
+        );
+        self.games.push(ServerGame {
+            id,
+            players,
+            spectators: Vec::new(),
+            board: BoardRecource::new_game(),
+            clock: GameClock::new(self.config.base, self.config.increment),
+            record,
+            last_tick: Instant::now(),
+            grace: self.config.grace,
+        });
+    }
+
+    // the players are told about the result when the game ends, after that the game is only
+    // kept until it's saved
+    fn save_finished_games(&mut self) -> bool {
+        let (finished, playing) = std::mem::take(&mut self.games)
+            .into_iter()
+            .partition::<Vec<_>, _>(|game| game.board.result.is_some());
+        self.games = playing;
+        for game in &finished {
+            let finished_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs());
+            let name = format!("{}-{}", finished_at, game.id);
+            match game.record.save(&self.config.games_dir, &name) {
+                Ok(path) => println!("game {}: saved to {}", game.id, path.display()),
+                Err(err) => eprintln!("game {}: failed to save: {}", game.id, err),
+            }
+        }
+        !finished.is_empty()
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+};
+
+use crate::{
+    logic::board::{BoardChanged, BoardRecource, GameEnded, LowTime, PieceResult},
+    states::GameVolue,
+};
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SoundData>()
+            .add_systems(Startup, load_sounds)
+            .add_systems(
+                Update,
+                (
+                    play_move_sounds,
+                    play_game_end_sounds,
+                    play_low_time_sounds,
+                    preview_volume,
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEffect {
+    Move,
+    Capture,
+    Check,
+    Promotion,
+    GameEnd,
+    LowTime,
+}
+
+impl SoundEffect {
+    const ALL: [SoundEffect; 6] = [
+        SoundEffect::Move,
+        SoundEffect::Capture,
+        SoundEffect::Check,
+        SoundEffect::Promotion,
+        SoundEffect::GameEnd,
+        SoundEffect::LowTime,
+    ];
+
+    fn path(&self) -> &'static str {
+        match self {
+            SoundEffect::Move => "sounds/move.wav",
+            SoundEffect::Capture => "sounds/capture.wav",
+            SoundEffect::Check => "sounds/check.wav",
+            SoundEffect::Promotion => "sounds/promotion.wav",
+            SoundEffect::GameEnd => "sounds/game_end.wav",
+            SoundEffect::LowTime => "sounds/low_time.wav",
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SoundData {
+    sounds: Vec<Handle<AudioSource>>, // in the order of `SoundEffect::ALL`
+}
+
+fn load_sounds(asset_server: Res<AssetServer>, mut sound_data: ResMut<SoundData>) {
+    sound_data.sounds = SoundEffect::ALL
+        .iter()
+        .map(|effect| asset_server.load(effect.path()))
+        .collect();
+}
+
+// Spawns a sound that despawns itself when it's done, scaled by the volume setting
+pub fn play_sound(
+    commands: &mut Commands,
+    sound_data: &SoundData,
+    volume: GameVolue,
+    effect: SoundEffect,
+) {
+    if volume.0 == 0 {
+        return;
+    }
+    let Some(source) = sound_data.sounds.get(effect as usize) else {
+        return;
+    };
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            volume: Volume::new(volume.0 as f32 / GameVolue::MAX as f32),
+            ..default()
+        },
+    });
+}
+
+// one sound per move, the most important thing that happened in it
+fn play_move_sounds(
+    mut commands: Commands,
+    mut board_events: EventReader<BoardChanged>,
+    board: Res<BoardRecource>,
+    sound_data: Res<SoundData>,
+    volume: Res<GameVolue>,
+) {
+    for ev in board_events.read() {
+        let promoted = ev
+            .results
+            .iter()
+            .any(|(_, result)| matches!(result, PieceResult::Promoted(..)));
+        let captured = ev
+            .results
+            .iter()
+            .any(|(_, result)| matches!(result, PieceResult::Captured()));
+        let effect = if board.is_in_check(board.turn) {
+            SoundEffect::Check
+        } else if promoted {
+            SoundEffect::Promotion
+        } else if captured {
+            SoundEffect::Capture
+        } else {
+            SoundEffect::Move
+        };
+        play_sound(&mut commands, &sound_data, *volume, effect);
+    }
+}
+
+fn play_game_end_sounds(
+    mut commands: Commands,
+    mut game_ended: EventReader<GameEnded>,
+    sound_data: Res<SoundData>,
+    volume: Res<GameVolue>,
+) {
+    for _ in game_ended.read() {
+        play_sound(&mut commands, &sound_data, *volume, SoundEffect::GameEnd);
+    }
+}
+
+fn play_low_time_sounds(
+    mut commands: Commands,
+    mut low_time: EventReader<LowTime>,
+    sound_data: Res<SoundData>,
+    volume: Res<GameVolue>,
+) {
+    for _ in low_time.read() {
+        play_sound(&mut commands, &sound_data, *volume, SoundEffect::LowTime);
+    }
+}
+
+// lets the player hear the new volume when it's changed in the sound settings
+fn preview_volume(mut commands: Commands, sound_data: Res<SoundData>, volume: Res<GameVolue>) {
+    if volume.is_changed() && !volume.is_added() {
+        play_sound(&mut commands, &sound_data, *volume, SoundEffect::Move);
+    }
+}
@@ -1,9 +1,16 @@
-use bevy::ecs::{component::Component, schedule::States, system::Resource};
+use bevy::{
+    ecs::{component::Component, schedule::States, system::Resource},
+    render::color::Color,
+    window::WindowMode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::engine::{book::Variety, mcts::Playout, player::AiStyle};
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AppState {
-    Menu,
     #[default]
+    Menu,
     InGame,
     LoadingScreen,
     BoardSetup,
@@ -15,7 +22,430 @@ pub enum GameModeState {
     NotInGame,
     Singleplayer,
     Multiplayer,
+    Network,
+    // both sides are moved by the player while the AI looks at the position
+    Analysis,
+    // the player solves puzzles, the opponent's moves come from their solutions
+    Puzzle,
+    // every piece is shown on its own with small tasks to learn how it moves
+    Tutorial,
 }
 
-#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
+#[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct GameVolue(pub u32);
+
+impl GameVolue {
+    pub const MAX: u32 = 9;
+}
+
+impl Default for GameVolue {
+    fn default() -> Self {
+        GameVolue(5)
+    }
+}
+
+impl StoredSetting for GameVolue {
+    const KEY: &'static str = "volume";
+}
+
+// the color the human plays in singleplayer, true is white
+#[derive(Resource, Component, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PlayerColor(pub bool);
+
+impl Default for PlayerColor {
+    fn default() -> Self {
+        PlayerColor(true)
+    }
+}
+
+// a setting that is saved in the `PkvStore` and restored on the next launch
+pub trait StoredSetting: Resource + Default + Serialize + DeserializeOwned {
+    const KEY: &'static str;
+}
+
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub enum DisplayWindowMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+impl DisplayWindowMode {
+    pub fn window_mode(&self) -> WindowMode {
+        match self {
+            DisplayWindowMode::Windowed => WindowMode::Windowed,
+            DisplayWindowMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            DisplayWindowMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+impl StoredSetting for DisplayWindowMode {
+    const KEY: &'static str = "display_window_mode";
+}
+
+// the window size in windowed mode
+#[derive(Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct DisplayResolution(pub u32, pub u32);
+
+impl DisplayResolution {
+    pub const OPTIONS: [DisplayResolution; 3] = [
+        DisplayResolution(1280, 940),
+        DisplayResolution(1600, 1000),
+        DisplayResolution(1920, 1080),
+    ];
+}
+
+impl Default for DisplayResolution {
+    fn default() -> Self {
+        DisplayResolution::OPTIONS[0]
+    }
+}
+
+impl StoredSetting for DisplayResolution {
+    const KEY: &'static str = "display_resolution";
+}
+
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub enum BoardTheme {
+    #[default]
+    Green,
+    Brown,
+    Blue,
+    Gray,
+}
+
+impl BoardTheme {
+    // the (light, dark) colors of the squares
+    pub fn colors(&self) -> (Color, Color) {
+        let (light, dark) = match self {
+            BoardTheme::Green => ("eeeed2", "769656"),
+            BoardTheme::Brown => ("f0d9b5", "b58863"),
+            BoardTheme::Blue => ("dee3e6", "8ca2ad"),
+            BoardTheme::Gray => ("e0e0e0", "8a8a8a"),
+        };
+        (Color::hex(light).unwrap(), Color::hex(dark).unwrap())
+    }
+}
+
+impl StoredSetting for BoardTheme {
+    const KEY: &'static str = "board_theme";
+}
+
+// how the possible moves of the selected piece are shown
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub enum MoveIndicatorStyle {
+    #[default]
+    Dots,
+    Squares,
+    Hidden,
+}
+
+impl StoredSetting for MoveIndicatorStyle {
+    const KEY: &'static str = "move_indicator_style";
+}
+
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub enum AnimationSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+    Instant,
+}
+
+impl AnimationSpeed {
+    // how long a piece takes to move, in seconds
+    pub fn move_duration(&self) -> f32 {
+        match self {
+            AnimationSpeed::Slow => 0.5,
+            AnimationSpeed::Normal => 0.25,
+            AnimationSpeed::Fast => 0.12,
+            AnimationSpeed::Instant => 0.0,
+        }
+    }
+}
+
+impl StoredSetting for AnimationSpeed {
+    const KEY: &'static str = "animation_speed";
+}
+
+// the names of the two players of a local multiplayer game
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerNames {
+    pub white: String,
+    pub black: String,
+}
+
+impl PlayerNames {
+    pub const MAX_LENGTH: usize = 16;
+
+    pub fn get(&self, color: bool) -> &str {
+        if color {
+            &self.white
+        } else {
+            &self.black
+        }
+    }
+
+    pub fn get_mut(&mut self, color: bool) -> &mut String {
+        if color {
+            &mut self.white
+        } else {
+            &mut self.black
+        }
+    }
+}
+
+impl Default for PlayerNames {
+    fn default() -> Self {
+        PlayerNames {
+            white: "White".to_string(),
+            black: "Black".to_string(),
+        }
+    }
+}
+
+impl StoredSetting for PlayerNames {
+    const KEY: &'static str = "player_names";
+}
+
+// in local multiplayer, turn the board after every move so the side to move is at the bottom
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct AutoFlipBoard(pub bool);
+
+impl StoredSetting for AutoFlipBoard {
+    const KEY: &'static str = "auto_flip_board";
+}
+
+// the address last used to join or host a network game
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkAddress(pub String);
+
+impl NetworkAddress {
+    pub const MAX_LENGTH: usize = 64;
+}
+
+impl Default for NetworkAddress {
+    fn default() -> Self {
+        NetworkAddress("127.0.0.1:7878".to_string())
+    }
+}
+
+impl StoredSetting for NetworkAddress {
+    const KEY: &'static str = "network_address";
+}
+
+// the UCI engine the computer plays with in singleplayer, the built-in AI is used if it's empty
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnginePath(pub String);
+
+impl EnginePath {
+    pub const MAX_LENGTH: usize = 256;
+}
+
+impl StoredSetting for EnginePath {
+    const KEY: &'static str = "engine_path";
+}
+
+// how many threads the built-in AI searches with, more threads find better moves in the same
+// time but only a single one always plays the same moves
+#[derive(Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct SearchThreads(pub usize);
+
+impl SearchThreads {
+    pub const OPTIONS: [usize; 4] = [1, 2, 4, 8];
+}
+
+impl Default for SearchThreads {
+    fn default() -> Self {
+        SearchThreads(1)
+    }
+}
+
+impl StoredSetting for SearchThreads {
+    const KEY: &'static str = "search_threads";
+}
+
+// how the built-in AI thinks, MCTS plays new pieces better than the tuned search
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct SearchStyle(pub AiStyle);
+
+impl SearchStyle {
+    pub fn label(self) -> &'static str {
+        match self.0 {
+            AiStyle::AlphaBeta => "Alpha-beta",
+            AiStyle::Mcts(Playout::Guided) => "MCTS",
+            AiStyle::Mcts(Playout::Random) => "MCTS random",
+        }
+    }
+}
+
+impl StoredSetting for SearchStyle {
+    const KEY: &'static str = "search_style";
+}
+
+// how often the built-in AI leaves the main line of the opening book
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct BookVariety(pub Variety);
+
+impl BookVariety {
+    pub fn label(self) -> &'static str {
+        match self.0 {
+            Variety::Off => "Off",
+            Variety::MainLine => "Main line",
+            Variety::Weighted => "Weighted",
+            Variety::Wide => "Wide",
+        }
+    }
+}
+
+impl StoredSetting for BookVariety {
+    const KEY: &'static str = "book_variety";
+}
+
+// how many hints the player can ask for in a singleplayer game, None for as many as they like
+#[derive(Resource, Component, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct HintLimit(pub Option<u32>);
+
+impl HintLimit {
+    pub const OPTIONS: [HintLimit; 5] = [
+        HintLimit(Some(0)),
+        HintLimit(Some(3)),
+        HintLimit(Some(5)),
+        HintLimit(Some(10)),
+        HintLimit(None),
+    ];
+
+    pub fn label(self) -> String {
+        match self.0 {
+            Some(0) => "Off".to_string(),
+            Some(limit) => limit.to_string(),
+            None => "Any".to_string(),
+        }
+    }
+}
+
+impl Default for HintLimit {
+    fn default() -> Self {
+        HintLimit(Some(3))
+    }
+}
+
+impl StoredSetting for HintLimit {
+    const KEY: &'static str = "hint_limit";
+}
+
+// the clock of local games and of network games hosted on this computer, as minutes and seconds
+// added after every move, None for games without a clock
+#[derive(
+    Resource, Component, Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct TimeControl(pub Option<(u64, u64)>);
+
+impl TimeControl {
+    pub const OPTIONS: [TimeControl; 4] = [
+        TimeControl(None),
+        TimeControl(Some((5, 3))),
+        TimeControl(Some((10, 5))),
+        TimeControl(Some((30, 0))),
+    ];
+
+    pub fn label(self) -> String {
+        match self.0 {
+            Some((minutes, increment)) => format!("{}+{}", minutes, increment),
+            None => "None".to_string(),
+        }
+    }
+}
+
+impl StoredSetting for TimeControl {
+    const KEY: &'static str = "time_control";
+}
+
+// while set the board doesn't react to clicks, e.g. while the handoff screen hides it
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct BoardInputBlocked(pub bool);
+
+// while set the typed keys go to a text field, e.g. the chat, so the keyboard shortcuts are off
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct TextInputFocused(pub bool);
+
+// hides swear words in the chat
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct ProfanityFilter(pub bool);
+
+impl Default for ProfanityFilter {
+    fn default() -> Self {
+        ProfanityFilter(true)
+    }
+}
+
+impl StoredSetting for ProfanityFilter {
+    const KEY: &'static str = "profanity_filter";
+}
+
+// the colors whose pieces can be moved with the mouse on this computer
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalControl {
+    pub white: bool,
+    pub black: bool,
+}
+
+impl LocalControl {
+    pub const BOTH: LocalControl = LocalControl {
+        white: true,
+        black: true,
+    };
+
+    // spectators can't move anything
+    pub const NONE: LocalControl = LocalControl {
+        white: false,
+        black: false,
+    };
+
+    pub fn only(color: bool) -> Self {
+        LocalControl {
+            white: color,
+            black: !color,
+        }
+    }
+
+    pub fn controls(&self, color: bool) -> bool {
+        if color {
+            self.white
+        } else {
+            self.black
+        }
+    }
+}
+
+impl Default for LocalControl {
+    fn default() -> Self {
+        LocalControl::BOTH
+    }
+}
+
+// who decides whether a move is legal, in a network game joined from another computer it's the
+// host and the moves are only played once it sends them back
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveAuthority {
+    #[default]
+    Local,
+    Remote,
+}
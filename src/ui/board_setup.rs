@@ -1,6 +1,6 @@
 use crate::{
     logic,
-    states::{AppState, GameVolue},
+    states::{AppState, BoardTheme, GameVolue},
 };
 use bevy::{app::AppExit, prelude::*};
 use bevy_pkv::PkvStore;
@@ -15,6 +15,7 @@ pub fn board_setup_menu_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut pkv: ResMut<PkvStore>,
+    theme: Res<BoardTheme>,
 ) {
     // Common style for all buttons on the screen
     let button_style = Style {
@@ -66,8 +67,7 @@ pub fn board_setup_menu_setup(
                     ..default()
                 })
                 .with_children(|parent| {
-                    let white = Color::hex("eeeed2").unwrap();
-                    let black = Color::hex("769656").unwrap();
+                    let (white, black) = theme.colors();
                     for y in 0..4 {
                         for x in 0..4 {
                             let color = if (x + y) % 2 == 0 { white } else { black };
@@ -0,0 +1,274 @@
+use crate::{
+    logic::OnGameScreen,
+    network::{
+        chat::{
+            filter_profanity, ChatContent, ChatEntry, ChatLog, ChatSent, Emote, MAX_CHAT_LENGTH,
+        },
+        NetworkRole, NetworkSession,
+    },
+    states::{ProfanityFilter, TextInputFocused},
+};
+use bevy::{input::keyboard::KeyCode, prelude::*, window::ReceivedCharacter};
+
+use super::{NORMAL_BUTTON, TEXT_COLOR};
+
+// how many of the latest messages are shown
+const CHAT_LINES: usize = 8;
+const FOCUSED_INPUT: Color = Color::rgb(0.35, 0.35, 0.55);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum ChatButton {
+    Mute,
+    Filter,
+    Emote(Emote),
+    Input,
+}
+
+#[derive(Component)]
+pub struct ChatHistoryText;
+
+// The message being typed
+#[derive(Resource, Default, Debug)]
+pub struct ChatDraft(pub String);
+
+fn chat_line(entry: &ChatEntry, filter: bool) -> String {
+    let line = format!("{}: {}", entry.name, entry.content.text());
+    if filter {
+        filter_profanity(&line)
+    } else {
+        line
+    }
+}
+
+fn history_text(chat: &ChatLog, filter: bool) -> String {
+    let lines: Vec<String> = chat
+        .visible()
+        .map(|entry| chat_line(entry, filter))
+        .collect();
+    lines[lines.len().saturating_sub(CHAT_LINES)..].join("\n")
+}
+
+fn button_text(
+    button: ChatButton,
+    chat: &ChatLog,
+    filter: bool,
+    draft: &str,
+    focused: bool,
+) -> String {
+    match button {
+        ChatButton::Mute if chat.muted => "Unmute".to_string(),
+        ChatButton::Mute => "Mute".to_string(),
+        ChatButton::Filter if filter => "Filter: on".to_string(),
+        ChatButton::Filter => "Filter: off".to_string(),
+        ChatButton::Emote(emote) => emote.text().to_string(),
+        ChatButton::Input if focused => format!("> {}_", draft),
+        ChatButton::Input if draft.is_empty() => "Press Enter to chat".to_string(),
+        ChatButton::Input => format!("> {}", draft),
+    }
+}
+
+// The chat sits in the bottom right corner, spectators only get to read it
+pub fn spawn_chat_panel(
+    mut commands: Commands,
+    session: Option<Res<NetworkSession>>,
+    chat: Res<ChatLog>,
+    filter: Res<ProfanityFilter>,
+) {
+    let can_write = session.is_some_and(|session| session.role != NetworkRole::Spectator);
+    let text_style = TextStyle {
+        font_size: 20.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let button_style = Style {
+        margin: UiRect::all(Val::Px(3.0)),
+        padding: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let spawn_button = |parent: &mut ChildBuilder, button: ChatButton, style: &Style| {
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: style.clone(),
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                },
+                button,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    button_text(button, &chat, filter.0, "", false),
+                    text_style.clone(),
+                ));
+            });
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    width: Val::Px(360.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                ..default()
+            },
+            // lets the board know the mouse is over the chat, see `select_piece`
+            Interaction::default(),
+            OnGameScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(NodeBundle::default()).with_children(|parent| {
+                spawn_button(parent, ChatButton::Mute, &button_style);
+                spawn_button(parent, ChatButton::Filter, &button_style);
+            });
+            parent.spawn((
+                TextBundle::from_section(history_text(&chat, filter.0), text_style.clone())
+                    .with_style(Style {
+                        min_height: Val::Px(CHAT_LINES as f32 * 24.0),
+                        ..default()
+                    }),
+                ChatHistoryText,
+            ));
+            if !can_write {
+                return;
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_wrap: FlexWrap::Wrap,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for emote in Emote::ALL {
+                        spawn_button(parent, ChatButton::Emote(emote), &button_style);
+                    }
+                });
+            spawn_button(
+                parent,
+                ChatButton::Input,
+                &Style {
+                    justify_content: JustifyContent::FlexStart,
+                    ..button_style.clone()
+                },
+            );
+        });
+}
+
+pub fn chat_action(
+    interaction_query: Query<(&Interaction, &ChatButton), Changed<Interaction>>,
+    mut chat: ResMut<ChatLog>,
+    mut filter: ResMut<ProfanityFilter>,
+    mut text_input: ResMut<TextInputFocused>,
+    mut sent: EventWriter<ChatSent>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            ChatButton::Mute => chat.muted = !chat.muted,
+            ChatButton::Filter => filter.0 = !filter.0,
+            ChatButton::Emote(emote) => {
+                sent.send(ChatSent(ChatContent::Emote(*emote)));
+            }
+            ChatButton::Input => text_input.0 = true,
+        }
+    }
+}
+
+// Enter starts a message and sends it, escape or a click somewhere else gives the keyboard back
+// to the game
+pub fn type_chat(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    inputs: Query<(&Interaction, &ChatButton)>,
+    mut text_input: ResMut<TextInputFocused>,
+    mut draft: ResMut<ChatDraft>,
+    mut sent: EventWriter<ChatSent>,
+) {
+    let Some(input) = inputs
+        .iter()
+        .find_map(|(interaction, button)| (*button == ChatButton::Input).then_some(interaction))
+    else {
+        // spectators can't write
+        characters.clear();
+        return;
+    };
+    if !text_input.0 {
+        characters.clear();
+        if keys.just_pressed(KeyCode::Enter) {
+            text_input.0 = true;
+        }
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape)
+        || (mouse.just_pressed(MouseButton::Left) && *input == Interaction::None)
+    {
+        characters.clear();
+        text_input.0 = false;
+        return;
+    }
+    if keys.just_pressed(KeyCode::Enter) {
+        characters.clear();
+        sent.send(ChatSent(ChatContent::Text(std::mem::take(&mut draft.0))));
+        text_input.0 = false;
+        return;
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        draft.0.pop();
+    }
+    for ev in characters.read() {
+        for c in ev.char.chars() {
+            if !c.is_control() && draft.0.chars().count() < MAX_CHAT_LENGTH {
+                draft.0.push(c);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_chat_panel(
+    chat: Res<ChatLog>,
+    filter: Res<ProfanityFilter>,
+    draft: Res<ChatDraft>,
+    text_input: Res<TextInputFocused>,
+    mut history_query: Query<&mut Text, With<ChatHistoryText>>,
+    mut button_query: Query<(&ChatButton, &Children, &mut BackgroundColor)>,
+    mut text_query: Query<&mut Text, Without<ChatHistoryText>>,
+) {
+    if !(chat.is_changed() || filter.is_changed() || draft.is_changed() || text_input.is_changed())
+    {
+        return;
+    }
+    for mut text in &mut history_query {
+        text.sections[0].value = history_text(&chat, filter.0);
+    }
+    for (button, children, mut color) in &mut button_query {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = button_text(*button, &chat, filter.0, &draft.0, text_input.0);
+        }
+        if *button == ChatButton::Input {
+            *color = if text_input.0 {
+                FOCUSED_INPUT.into()
+            } else {
+                NORMAL_BUTTON.into()
+            };
+        }
+    }
+}
+
+pub fn close_chat(mut text_input: ResMut<TextInputFocused>, mut draft: ResMut<ChatDraft>) {
+    text_input.0 = false;
+    draft.0.clear();
+}
@@ -0,0 +1,223 @@
+use crate::states::{
+    AnimationSpeed, BoardTheme, DisplayResolution, DisplayWindowMode, MoveIndicatorStyle,
+};
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use super::{
+    MenuButtonAction, OnDisplaySettingsMenuScreen, SelectedOption, NORMAL_BUTTON, TEXT_COLOR,
+};
+
+const TITLE_WIDTH: f32 = 150.0;
+const BUTTON_WIDTH: f32 = 140.0;
+
+pub fn display_settings_menu_setup(
+    mut commands: Commands,
+    window_mode: Res<DisplayWindowMode>,
+    resolution: Res<DisplayResolution>,
+    theme: Res<BoardTheme>,
+    indicator_style: Res<MoveIndicatorStyle>,
+    animation_speed: Res<AnimationSpeed>,
+) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    let option_text_style = TextStyle {
+        font_size: 24.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnDisplaySettingsMenuScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::CRIMSON.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_setting_row(
+                        parent,
+                        "Window",
+                        &[
+                            (DisplayWindowMode::Windowed, "Windowed"),
+                            (DisplayWindowMode::BorderlessFullscreen, "Borderless"),
+                            (DisplayWindowMode::Fullscreen, "Fullscreen"),
+                        ],
+                        *window_mode,
+                        &option_text_style,
+                        TITLE_WIDTH,
+                        BUTTON_WIDTH,
+                    );
+                    let resolution_names = DisplayResolution::OPTIONS
+                        .map(|option| format!("{}x{}", option.0, option.1));
+                    let resolutions: Vec<(DisplayResolution, &str)> = DisplayResolution::OPTIONS
+                        .into_iter()
+                        .zip(resolution_names.iter().map(String::as_str))
+                        .collect();
+                    spawn_setting_row(
+                        parent,
+                        "Resolution",
+                        &resolutions,
+                        *resolution,
+                        &option_text_style,
+                        TITLE_WIDTH,
+                        BUTTON_WIDTH,
+                    );
+                    spawn_setting_row(
+                        parent,
+                        "Board",
+                        &[
+                            (BoardTheme::Green, "Green"),
+                            (BoardTheme::Brown, "Brown"),
+                            (BoardTheme::Blue, "Blue"),
+                            (BoardTheme::Gray, "Gray"),
+                        ],
+                        *theme,
+                        &option_text_style,
+                        TITLE_WIDTH,
+                        BUTTON_WIDTH,
+                    );
+                    spawn_setting_row(
+                        parent,
+                        "Moves",
+                        &[
+                            (MoveIndicatorStyle::Dots, "Dots"),
+                            (MoveIndicatorStyle::Squares, "Squares"),
+                            (MoveIndicatorStyle::Hidden, "Hidden"),
+                        ],
+                        *indicator_style,
+                        &option_text_style,
+                        TITLE_WIDTH,
+                        BUTTON_WIDTH,
+                    );
+                    spawn_setting_row(
+                        parent,
+                        "Animation",
+                        &[
+                            (AnimationSpeed::Slow, "Slow"),
+                            (AnimationSpeed::Normal, "Normal"),
+                            (AnimationSpeed::Fast, "Fast"),
+                            (AnimationSpeed::Instant, "Instant"),
+                        ],
+                        *animation_speed,
+                        &option_text_style,
+                        TITLE_WIDTH,
+                        BUTTON_WIDTH,
+                    );
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style,
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            MenuButtonAction::BackToSettings,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Back", button_text_style));
+                        });
+                });
+        });
+}
+
+// Spawns a row with a title and a button for every value of the setting, the buttons are picked
+// up by `setting_button::<T>`
+pub(super) fn spawn_setting_row<T: Component + PartialEq + Copy>(
+    parent: &mut ChildBuilder,
+    title: &str,
+    options: &[(T, &str)],
+    current: T,
+    text_style: &TextStyle,
+    title_width: f32,
+    button_width: f32,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceBetween,
+                margin: UiRect::horizontal(Val::Px(20.0)),
+                ..default()
+            },
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(title, text_style.clone()).with_style(Style {
+                    width: Val::Px(title_width),
+                    ..default()
+                }),
+            );
+            for &(option, text) in options {
+                let mut entity = parent.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(button_width),
+                            height: Val::Px(45.0),
+                            margin: UiRect::all(Val::Px(8.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    option,
+                ));
+                entity.with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(text, text_style.clone()));
+                });
+                if current == option {
+                    entity.insert(SelectedOption);
+                }
+            }
+        });
+}
+
+// Applies the window mode and resolution as soon as they are changed
+pub fn apply_window_settings(
+    window_mode: Res<DisplayWindowMode>,
+    resolution: Res<DisplayResolution>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !window_mode.is_changed() && !resolution.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    window.mode = window_mode.window_mode();
+    window
+        .resolution
+        .set(resolution.0 as f32, resolution.1 as f32);
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
-use crate::states::AppState;
+use crate::states::{AppState, GameModeState, LocalControl, MoveAuthority};
+
+use super::{despawn_screen, MenuState, NORMAL_BUTTON};
 
 pub struct GameMenuUI<S: States> {
     pub state: S,
@@ -9,23 +11,48 @@ pub struct GameMenuUI<S: States> {
 impl<S: States> Plugin for GameMenuUI<S> {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(self.state.clone()), setup_ui)
-            .add_systems(Update, button_system.run_if(in_state(self.state.clone())));
+            .add_systems(
+                Update,
+                game_mode_action.run_if(in_state(self.state.clone())),
+            )
+            .add_systems(
+                OnExit(self.state.clone()),
+                despawn_screen::<OnGameMenuScreen>,
+            );
     }
 }
 
+// Tag component used to tag entities added on the game menu screen
+#[derive(Component)]
+struct OnGameMenuScreen;
+
+#[derive(Component, Clone, Copy)]
+enum GameModeButton {
+    Singleplayer,
+    Multiplayer,
+    Network,
+    Analysis,
+    Puzzles,
+    Tutorial,
+    Back,
+}
+
 fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::SpaceEvenly,
-                flex_direction: FlexDirection::Column,
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceEvenly,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+            OnGameMenuScreen,
+        ))
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
                 "Game Menu",
@@ -35,91 +62,95 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                     color: Color::rgb(0.9, 0.9, 0.9),
                 },
             ));
-            parent
-                .spawn(ButtonBundle {
-                    style: Style {
-                        width: Val::Percent(50.0),
-                        height: Val::Percent(10.0),
-                        border: UiRect::all(Val::Px(3.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    border_color: BorderColor(Color::GRAY),
-                    background_color: Color::hex("353b45").unwrap().into(),
-                    ..default()
-                })
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "SINGLEPLAYER",
-                        TextStyle {
-                            font: asset_server.load("fonts\\citizen-carto.otf"),
-                            font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
+            for (action, text) in [
+                (GameModeButton::Singleplayer, "SINGLEPLAYER"),
+                (GameModeButton::Multiplayer, "MULTIPLAYER"),
+                (GameModeButton::Network, "NETWORK"),
+                (GameModeButton::Analysis, "ANALYSIS"),
+                (GameModeButton::Puzzles, "PUZZLES"),
+                (GameModeButton::Tutorial, "TUTORIAL"),
+                (GameModeButton::Back, "BACK"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Percent(50.0),
+                                height: Val::Percent(10.0),
+                                border: UiRect::all(Val::Px(3.0)),
+                                // horizontally center child text
+                                justify_content: JustifyContent::Center,
+                                // vertically center child text
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(Color::GRAY),
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
                         },
-                    ));
-                });
-            parent
-                .spawn(ButtonBundle {
-                    style: Style {
-                        width: Val::Percent(50.0),
-                        height: Val::Percent(10.0),
-                        border: UiRect::all(Val::Px(3.0)),
-                        // horizontally center child text
-                        justify_content: JustifyContent::Center,
-                        // vertically center child text
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    border_color: BorderColor(Color::GRAY),
-                    background_color: Color::GRAY.into(),
-                    ..default()
-                })
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "MULTIPLAYER",
-                        TextStyle {
-                            font: asset_server.load("fonts\\citizen-carto.otf"),
-                            font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
-                        },
-                    ));
-                });
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            text,
+                            TextStyle {
+                                font: asset_server.load("fonts\\citizen-carto.otf"),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+            }
         });
 }
 
-#[allow(clippy::type_complexity)]
-fn button_system(
-    mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &mut BorderColor,
-            &Children,
-        ),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut text_query: Query<&mut Text>,
-    mut state: ResMut<State<AppState>>,
+// the game mode is picked here, each mode has its own setup screen before the game starts except
+// for the analysis and the puzzles, which start right away
+fn game_mode_action(
+    interaction_query: Query<(&Interaction, &GameModeButton), Changed<Interaction>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_mode: ResMut<NextState<GameModeState>>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut local_control: ResMut<LocalControl>,
+    mut authority: ResMut<MoveAuthority>,
 ) {
-    for (interaction, mut color, mut border_color, children) in &mut interaction_query {
-        let mut text = text_query.get_mut(children[0]).unwrap();
-        match *interaction {
-            Interaction::Pressed => {
-                text.sections[0].value = "Press".to_string();
-                *color = Color::GREEN.into();
-                border_color.0 = Color::RED;
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            GameModeButton::Singleplayer => {
+                game_mode.set(GameModeState::Singleplayer);
+                menu_state.set(MenuState::SingleplayerSetup);
+            }
+            GameModeButton::Multiplayer => {
+                game_mode.set(GameModeState::Multiplayer);
+                menu_state.set(MenuState::MultiplayerSetup);
+            }
+            GameModeButton::Network => {
+                game_mode.set(GameModeState::Network);
+                menu_state.set(MenuState::NetworkSetup);
+            }
+            GameModeButton::Analysis => {
+                game_mode.set(GameModeState::Analysis);
+                *local_control = LocalControl::BOTH;
+                *authority = MoveAuthority::Local;
+                app_state.set(AppState::InGame);
+                menu_state.set(MenuState::Disabled);
             }
-            Interaction::Hovered => {
-                text.sections[0].value = "Hover".to_string();
-                *color = Color::RED.into();
-                border_color.0 = Color::WHITE;
+            GameModeButton::Puzzles => {
+                game_mode.set(GameModeState::Puzzle);
+                *authority = MoveAuthority::Local;
+                app_state.set(AppState::InGame);
+                menu_state.set(MenuState::Disabled);
             }
-            Interaction::None => {
-                text.sections[0].value = "Button".to_string();
-                *color = Color::GRAY.into();
-                border_color.0 = Color::BLACK;
+            GameModeButton::Tutorial => {
+                game_mode.set(GameModeState::Tutorial);
+                *authority = MoveAuthority::Local;
+                app_state.set(AppState::InGame);
+                menu_state.set(MenuState::Disabled);
             }
+            GameModeButton::Back => menu_state.set(MenuState::Main),
         }
     }
 }
@@ -0,0 +1,416 @@
+use crate::{
+    engine::{book::Variety, player::AiStyle},
+    states::{
+        AutoFlipBoard, BookVariety, EnginePath, HintLimit, PlayerColor, PlayerNames, SearchStyle,
+        SearchThreads, TimeControl,
+    },
+};
+use bevy::{input::keyboard::KeyCode, prelude::*, window::ReceivedCharacter};
+
+use super::{display_settings::spawn_setting_row, MenuButtonAction, NORMAL_BUTTON, TEXT_COLOR};
+
+// Tag component used to tag entities added on the singleplayer setup screen
+#[derive(Component)]
+pub struct OnSingleplayerSetupScreen;
+
+// Tag component used to tag entities added on the multiplayer setup screen
+#[derive(Component)]
+pub struct OnMultiplayerSetupScreen;
+
+// A text field holding the name of the player of this color
+#[derive(Component)]
+pub struct NameInput {
+    color: bool,
+}
+
+// The text field holding the path of the engine the computer plays with
+#[derive(Component)]
+pub struct EngineInput;
+
+// shown instead of an empty engine path
+const BUILT_IN_ENGINE: &str = "Built-in";
+
+fn engine_path_text(path: &str) -> String {
+    if path.is_empty() {
+        BUILT_IN_ENGINE.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+// Tag component used to mark the name field that receives the typed characters
+#[derive(Component)]
+pub struct FocusedInput;
+
+const FOCUSED_INPUT: Color = Color::rgb(0.3, 0.3, 0.3);
+// the names in front of the rows, wide enough for all of them
+const TITLE_WIDTH: f32 = 150.0;
+
+pub fn singleplayer_setup_menu_setup(
+    mut commands: Commands,
+    player_color: Res<PlayerColor>,
+    engine_path: Res<EnginePath>,
+    style: Res<SearchStyle>,
+    threads: Res<SearchThreads>,
+    variety: Res<BookVariety>,
+    hint_limit: Res<HintLimit>,
+    time_control: Res<TimeControl>,
+) {
+    let (button_style, button_text_style) = setup_styles();
+    let option_text_style = option_text_style(&button_text_style);
+
+    commands
+        .spawn((setup_screen_root(), OnSingleplayerSetupScreen))
+        .with_children(|parent| {
+            parent.spawn(setup_panel()).with_children(|parent| {
+                spawn_setting_row(
+                    parent,
+                    "Play as",
+                    &[(PlayerColor(true), "White"), (PlayerColor(false), "Black")],
+                    *player_color,
+                    &option_text_style,
+                    TITLE_WIDTH,
+                    200.0,
+                );
+                // an external UCI engine can play instead of the built-in AI
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::CRIMSON.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Engine",
+                            button_text_style.clone(),
+                        ));
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(500.0),
+                                        ..button_style.clone()
+                                    },
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                EngineInput,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    engine_path_text(&engine_path.0),
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    });
+                // how the built-in AI thinks
+                let styles = AiStyle::ALL
+                    .map(|ai_style| (SearchStyle(ai_style), SearchStyle(ai_style).label()));
+                spawn_setting_row(
+                    parent,
+                    "Style",
+                    &styles,
+                    *style,
+                    &option_text_style,
+                    TITLE_WIDTH,
+                    240.0,
+                );
+                // how many threads the built-in AI thinks with
+                let thread_names = SearchThreads::OPTIONS.map(|count| count.to_string());
+                let thread_counts: Vec<(SearchThreads, &str)> = SearchThreads::OPTIONS
+                    .into_iter()
+                    .map(SearchThreads)
+                    .zip(thread_names.iter().map(String::as_str))
+                    .collect();
+                spawn_setting_row(
+                    parent,
+                    "Threads",
+                    &thread_counts,
+                    *threads,
+                    &option_text_style,
+                    TITLE_WIDTH,
+                    100.0,
+                );
+                // how the opening moves are picked from the book
+                let varieties = Variety::ALL
+                    .map(|variety| (BookVariety(variety), BookVariety(variety).label()));
+                spawn_setting_row(
+                    parent,
+                    "Book",
+                    &varieties,
+                    *variety,
+                    &option_text_style,
+                    TITLE_WIDTH,
+                    200.0,
+                );
+                // how many times the AI can be asked for the best move
+                let hint_names = HintLimit::OPTIONS.map(HintLimit::label);
+                let hint_limits: Vec<(HintLimit, &str)> = HintLimit::OPTIONS
+                    .into_iter()
+                    .zip(hint_names.iter().map(String::as_str))
+                    .collect();
+                spawn_setting_row(
+                    parent,
+                    "Hints",
+                    &hint_limits,
+                    *hint_limit,
+                    &option_text_style,
+                    TITLE_WIDTH,
+                    100.0,
+                );
+                spawn_time_control(parent, *time_control, &option_text_style);
+                spawn_action_buttons(parent, &button_style, &button_text_style);
+            });
+        });
+}
+
+pub fn multiplayer_setup_menu_setup(
+    mut commands: Commands,
+    names: Res<PlayerNames>,
+    auto_flip: Res<AutoFlipBoard>,
+    time_control: Res<TimeControl>,
+) {
+    let (button_style, button_text_style) = setup_styles();
+    let option_text_style = option_text_style(&button_text_style);
+
+    commands
+        .spawn((setup_screen_root(), OnMultiplayerSetupScreen))
+        .with_children(|parent| {
+            parent.spawn(setup_panel()).with_children(|parent| {
+                for (color, text) in [(true, "White"), (false, "Black")] {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: Color::CRIMSON.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(
+                                TextBundle::from_section(text, button_text_style.clone())
+                                    .with_style(Style {
+                                        width: Val::Px(TITLE_WIDTH),
+                                        ..default()
+                                    }),
+                            );
+                            parent
+                                .spawn((
+                                    ButtonBundle {
+                                        style: Style {
+                                            width: Val::Px(400.0),
+                                            ..button_style.clone()
+                                        },
+                                        background_color: NORMAL_BUTTON.into(),
+                                        ..default()
+                                    },
+                                    NameInput { color },
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        names.get(color),
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                        });
+                }
+                spawn_setting_row(
+                    parent,
+                    "Flip board",
+                    &[(AutoFlipBoard(true), "On"), (AutoFlipBoard(false), "Off")],
+                    *auto_flip,
+                    &option_text_style,
+                    TITLE_WIDTH,
+                    100.0,
+                );
+                spawn_time_control(parent, *time_control, &option_text_style);
+                spawn_action_buttons(parent, &button_style, &button_text_style);
+            });
+        });
+}
+
+pub fn setup_styles() -> (Style, TextStyle) {
+    let button_style = Style {
+        width: Val::Px(200.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        font_size: 40.0,
+        color: TEXT_COLOR,
+        ..default()
+    };
+    (button_style, button_text_style)
+}
+
+// smaller than the text of the other buttons, so a whole row of them fits
+fn option_text_style(button_text_style: &TextStyle) -> TextStyle {
+    TextStyle {
+        font_size: 30.0,
+        ..button_text_style.clone()
+    }
+}
+
+pub fn setup_screen_root() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+pub fn setup_panel() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::CRIMSON.into(),
+        ..default()
+    }
+}
+
+// the clock both players get, the same row on every setup screen
+fn spawn_time_control(
+    parent: &mut ChildBuilder,
+    time_control: TimeControl,
+    text_style: &TextStyle,
+) {
+    let labels = TimeControl::OPTIONS.map(TimeControl::label);
+    let options: Vec<(TimeControl, &str)> = TimeControl::OPTIONS
+        .into_iter()
+        .zip(labels.iter().map(String::as_str))
+        .collect();
+    spawn_setting_row(
+        parent,
+        "Clock",
+        &options,
+        time_control,
+        text_style,
+        TITLE_WIDTH,
+        130.0,
+    );
+}
+
+fn spawn_action_buttons(parent: &mut ChildBuilder, button_style: &Style, text_style: &TextStyle) {
+    parent
+        .spawn(NodeBundle {
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (action, text) in [
+                (MenuButtonAction::BackToGameMode, "Back"),
+                (MenuButtonAction::StartGame, "Start"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: button_style.clone(),
+                            background_color: NORMAL_BUTTON.into(),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(text, text_style.clone()));
+                    });
+            }
+        });
+}
+
+// Clicking a name field focuses it, only one field can be focused at a time
+#[allow(clippy::type_complexity)]
+pub fn focus_name_input(
+    mut commands: Commands,
+    interaction_query: Query<(Entity, &Interaction), (Changed<Interaction>, With<NameInput>)>,
+    mut focused_query: Query<(Entity, &mut BackgroundColor), With<FocusedInput>>,
+) {
+    for (entity, interaction) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        for (previous, mut color) in &mut focused_query {
+            *color = NORMAL_BUTTON.into();
+            commands.entity(previous).remove::<FocusedInput>();
+        }
+        commands.entity(entity).insert(FocusedInput);
+    }
+}
+
+// Types into the focused name field
+pub fn type_name(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut names: ResMut<PlayerNames>,
+    mut focused_query: Query<(&NameInput, &Children, &mut BackgroundColor), With<FocusedInput>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let Ok((input, children, mut color)) = focused_query.get_single_mut() else {
+        characters.clear();
+        return;
+    };
+    // the button system resets the color when the mouse leaves the field
+    *color = FOCUSED_INPUT.into();
+
+    let mut name = names.get(input.color).to_string();
+    if keys.just_pressed(KeyCode::Backspace) {
+        name.pop();
+    }
+    for ev in characters.read() {
+        for c in ev.char.chars() {
+            if !c.is_control() && name.chars().count() < PlayerNames::MAX_LENGTH {
+                name.push(c);
+            }
+        }
+    }
+    if name != names.get(input.color) {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = name.clone();
+        }
+        *names.get_mut(input.color) = name;
+    }
+}
+
+// The engine field is the only text field on the screen, so it always gets the typed characters
+pub fn type_engine_path(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut engine_path: ResMut<EnginePath>,
+    input_query: Query<&Children, With<EngineInput>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let mut path = engine_path.0.clone();
+    if keys.just_pressed(KeyCode::Backspace) {
+        path.pop();
+    }
+    for ev in characters.read() {
+        for c in ev.char.chars() {
+            if !c.is_control() && path.chars().count() < EnginePath::MAX_LENGTH {
+                path.push(c);
+            }
+        }
+    }
+    if path == engine_path.0 {
+        return;
+    }
+    for children in &input_query {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = engine_path_text(&path);
+        }
+    }
+    engine_path.0 = path;
+}
@@ -0,0 +1,133 @@
+use crate::{
+    logic::board::{BoardChanged, BoardOrientation, BoardRecource},
+    states::{AutoFlipBoard, BoardInputBlocked, PlayerNames},
+};
+use bevy::prelude::*;
+
+use super::TEXT_COLOR;
+
+// Tag component used to tag the entities shown during a local multiplayer game
+#[derive(Component)]
+pub struct OnHotSeatScreen;
+
+// The screen that hides the board until the next player is ready, it only reacts to clicks from
+// the frame after it was shown so the click of the move itself doesn't close it
+#[derive(Component, Default)]
+pub struct HandoffScreen {
+    ready: bool,
+}
+
+#[derive(Component)]
+pub struct TurnText;
+
+fn turn_text(names: &PlayerNames, turn: bool) -> String {
+    let color = if turn { "White" } else { "Black" };
+    format!("{} ({}) to move", names.get(turn), color)
+}
+
+pub fn spawn_turn_text(mut commands: Commands, names: Res<PlayerNames>) {
+    commands.spawn((
+        TextBundle::from_section(
+            turn_text(&names, true),
+            TextStyle {
+                font_size: 30.0,
+                color: TEXT_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        TurnText,
+        OnHotSeatScreen,
+    ));
+}
+
+// after every move the board is hidden, flipped if needed, and the next player is asked to take
+// over before it's shown again
+pub fn start_handoff(
+    mut commands: Commands,
+    mut board_events: EventReader<BoardChanged>,
+    board: Res<BoardRecource>,
+    names: Res<PlayerNames>,
+    auto_flip: Res<AutoFlipBoard>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut input_blocked: ResMut<BoardInputBlocked>,
+    mut texts: Query<&mut Text, With<TurnText>>,
+) {
+    if board_events.is_empty() {
+        return;
+    }
+    board_events.clear();
+
+    for mut text in &mut texts {
+        text.sections[0].value = turn_text(&names, board.turn);
+    }
+    if auto_flip.0 {
+        orientation.flipped = !board.turn;
+    }
+    if board.result.is_some() {
+        return;
+    }
+
+    input_blocked.0 = true;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::hex("353b45").unwrap().into(),
+                // drawn above the turn text
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            HandoffScreen::default(),
+            OnHotSeatScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                turn_text(&names, board.turn),
+                TextStyle {
+                    font_size: 60.0,
+                    color: TEXT_COLOR,
+                    ..default()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Click to continue",
+                TextStyle {
+                    font_size: 30.0,
+                    color: TEXT_COLOR,
+                    ..default()
+                },
+            ));
+        });
+}
+
+// runs after the board handled the input of this frame, so the click that hides the screen isn't
+// also taken as a move
+pub fn end_handoff(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut handoff_screens: Query<(Entity, &mut HandoffScreen)>,
+    mut input_blocked: ResMut<BoardInputBlocked>,
+) {
+    for (entity, mut handoff_screen) in &mut handoff_screens {
+        if !handoff_screen.ready {
+            handoff_screen.ready = true;
+        } else if mouse.just_released(MouseButton::Left) {
+            commands.entity(entity).despawn_recursive();
+            input_blocked.0 = false;
+        }
+    }
+}
@@ -1,11 +1,39 @@
-use self::{game_menu::GameMenuUI, main_menu::main_menu_setup, settings::settings_menu_setup};
-use crate::states::{AppState, GameVolue};
+use self::{
+    chat::{chat_action, close_chat, spawn_chat_panel, type_chat, update_chat_panel, ChatDraft},
+    display_settings::{apply_window_settings, display_settings_menu_setup},
+    game_menu::GameMenuUI,
+    game_setup::{
+        focus_name_input, multiplayer_setup_menu_setup, singleplayer_setup_menu_setup,
+        type_engine_path, type_name,
+    },
+    hot_seat::{end_handoff, spawn_turn_text, start_handoff},
+    main_menu::main_menu_setup,
+    network_game::{spawn_network_game_text, update_network_game_text},
+    network_setup::{
+        network_action, network_setup_menu_setup, type_address, update_network_status,
+    },
+    settings::settings_menu_setup,
+};
+use crate::states::{
+    AnimationSpeed, AppState, AutoFlipBoard, BoardTheme, BookVariety, DisplayResolution,
+    DisplayWindowMode, EnginePath, GameModeState, GameVolue, HintLimit, LocalControl,
+    MoveAuthority, MoveIndicatorStyle, NetworkAddress, PlayerColor, PlayerNames, ProfanityFilter,
+    SearchStyle, SearchThreads, StoredSetting, TextInputFocused, TimeControl,
+};
 use bevy::{app::AppExit, prelude::*};
+use bevy_pkv::PkvStore;
 use board_setup::board_setup_menu_setup;
+use std::marker::PhantomData;
 
 pub mod board_setup;
+pub mod chat;
+pub mod display_settings;
 pub mod game_menu;
+pub mod game_setup;
+pub mod hot_seat;
 pub mod main_menu;
+pub mod network_game;
+pub mod network_setup;
 pub mod settings;
 
 const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
@@ -20,12 +48,141 @@ impl Plugin for GameUI {
             // Current screen in the menu is handled by an independent state from `GameState`
             .init_state::<MenuState>()
             .add_systems(OnEnter(AppState::Menu), menu_setup)
+            // games can also be started from outside the menu, e.g. when a network player joins
+            .add_systems(OnExit(AppState::Menu), close_menu)
             // Systems to handle the main menu screen
             .add_systems(OnEnter(MenuState::Main), main_menu_setup)
             .add_systems(
                 OnExit(MenuState::Main),
                 despawn_screen::<main_menu::OnMainMenuScreen>,
             )
+            // Systems to handle the game mode screen and the setup screens of each mode
+            .add_plugins(GameMenuUI {
+                state: MenuState::GameMode,
+            })
+            .add_systems(
+                OnEnter(MenuState::SingleplayerSetup),
+                singleplayer_setup_menu_setup,
+            )
+            .add_plugins((
+                StoredSettingPlugin::<EnginePath>::default(),
+                StoredSettingPlugin::<SearchStyle>::default(),
+                StoredSettingPlugin::<BookVariety>::default(),
+                StoredSettingPlugin::<SearchThreads>::default(),
+                StoredSettingPlugin::<HintLimit>::default(),
+                StoredSettingPlugin::<TimeControl>::default(),
+            ))
+            .add_systems(
+                Update,
+                (
+                    setting_button::<PlayerColor>,
+                    setting_button::<SearchStyle>,
+                    setting_button::<SearchThreads>,
+                    setting_button::<BookVariety>,
+                    setting_button::<HintLimit>,
+                    setting_button::<TimeControl>,
+                    type_engine_path,
+                )
+                    .run_if(in_state(MenuState::SingleplayerSetup)),
+            )
+            .add_systems(
+                OnExit(MenuState::SingleplayerSetup),
+                despawn_screen::<game_setup::OnSingleplayerSetupScreen>,
+            )
+            .add_plugins((
+                StoredSettingPlugin::<PlayerNames>::default(),
+                StoredSettingPlugin::<AutoFlipBoard>::default(),
+            ))
+            .add_systems(
+                OnEnter(MenuState::MultiplayerSetup),
+                multiplayer_setup_menu_setup,
+            )
+            .add_systems(
+                Update,
+                (
+                    setting_button::<AutoFlipBoard>,
+                    setting_button::<TimeControl>,
+                    focus_name_input,
+                    type_name.after(focus_name_input),
+                )
+                    .run_if(in_state(MenuState::MultiplayerSetup)),
+            )
+            .add_systems(
+                OnExit(MenuState::MultiplayerSetup),
+                despawn_screen::<game_setup::OnMultiplayerSetupScreen>,
+            )
+            // Systems to handle the network game screen
+            .add_plugins(StoredSettingPlugin::<NetworkAddress>::default())
+            .add_systems(OnEnter(MenuState::NetworkSetup), network_setup_menu_setup)
+            .add_systems(
+                Update,
+                (
+                    setting_button::<PlayerColor>,
+                    network_action,
+                    type_address,
+                    update_network_status,
+                )
+                    .run_if(in_state(MenuState::NetworkSetup)),
+            )
+            .add_systems(
+                OnExit(MenuState::NetworkSetup),
+                despawn_screen::<network_setup::OnNetworkSetupScreen>,
+            )
+            // Systems to handle the texts shown during a network game
+            .add_systems(
+                OnEnter(AppState::InGame),
+                spawn_network_game_text.run_if(in_state(GameModeState::Network)),
+            )
+            .add_systems(
+                Update,
+                update_network_game_text
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Network)),
+            )
+            // Systems to handle the chat of a network game
+            .add_plugins(StoredSettingPlugin::<ProfanityFilter>::default())
+            .init_resource::<ChatDraft>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                spawn_chat_panel.run_if(in_state(GameModeState::Network)),
+            )
+            .add_systems(
+                Update,
+                (
+                    chat_action,
+                    type_chat.after(leave_game),
+                    update_chat_panel.after(chat_action).after(type_chat),
+                )
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Network)),
+            )
+            .add_systems(OnExit(AppState::InGame), close_chat)
+            // Systems to handle the turn handoff of a local multiplayer game
+            .add_systems(
+                OnEnter(AppState::InGame),
+                spawn_turn_text.run_if(in_state(GameModeState::Multiplayer)),
+            )
+            .add_systems(
+                Update,
+                start_handoff
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Multiplayer)),
+            )
+            .add_systems(
+                PostUpdate,
+                end_handoff
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(in_state(GameModeState::Multiplayer)),
+            )
+            .add_systems(
+                OnExit(AppState::InGame),
+                despawn_screen::<hot_seat::OnHotSeatScreen>,
+            )
+            .add_systems(
+                OnExit(GameModeState::Multiplayer),
+                despawn_screen::<hot_seat::OnHotSeatScreen>,
+            )
+            .add_systems(Update, leave_game.run_if(in_state(AppState::InGame)))
             // Systems to handle the board setup menu screen
             .add_systems(OnEnter(MenuState::BoardSetup), board_setup_menu_setup)
             .add_systems(
@@ -38,7 +195,36 @@ impl Plugin for GameUI {
                 OnExit(MenuState::Settings),
                 despawn_screen::<settings::OnSettingsMenuScreen>,
             )
+            // Systems to handle the display settings screen
+            .add_plugins((
+                StoredSettingPlugin::<DisplayWindowMode>::default(),
+                StoredSettingPlugin::<DisplayResolution>::default(),
+                StoredSettingPlugin::<BoardTheme>::default(),
+                StoredSettingPlugin::<MoveIndicatorStyle>::default(),
+                StoredSettingPlugin::<AnimationSpeed>::default(),
+            ))
+            .add_systems(
+                OnEnter(MenuState::SettingsDisplay),
+                display_settings_menu_setup,
+            )
+            .add_systems(
+                Update,
+                (
+                    setting_button::<DisplayWindowMode>,
+                    setting_button::<DisplayResolution>,
+                    setting_button::<BoardTheme>,
+                    setting_button::<MoveIndicatorStyle>,
+                    setting_button::<AnimationSpeed>,
+                )
+                    .run_if(in_state(MenuState::SettingsDisplay)),
+            )
+            .add_systems(
+                OnExit(MenuState::SettingsDisplay),
+                despawn_screen::<OnDisplaySettingsMenuScreen>,
+            )
+            .add_systems(Update, apply_window_settings)
             // Systems to handle the sound settings screen
+            .add_plugins(StoredSettingPlugin::<GameVolue>::default())
             .add_systems(OnEnter(MenuState::SettingsSound), sound_settings_menu_setup)
             .add_systems(
                 Update,
@@ -58,8 +244,12 @@ impl Plugin for GameUI {
 
 // State used for the current menu screen
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-enum MenuState {
+pub enum MenuState {
     Main,
+    GameMode,
+    SingleplayerSetup,
+    MultiplayerSetup,
+    NetworkSetup,
     Settings,
     SettingsDisplay,
     SettingsSound,
@@ -89,6 +279,8 @@ struct SelectedOption;
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    StartGame,
+    BackToGameMode,
     Settings,
     SettingsDisplay,
     SettingsSound,
@@ -121,7 +313,8 @@ fn button_system(
 #[allow(clippy::type_complexity)]
 fn setting_button<T: Resource + Component + PartialEq + Copy>(
     interaction_query: Query<(&Interaction, &T, Entity), (Changed<Interaction>, With<Button>)>,
-    mut selected_query: Query<(Entity, &mut BackgroundColor), With<SelectedOption>>,
+    // a screen can hold several settings, so only look at the selected button of this one
+    mut selected_query: Query<(Entity, &mut BackgroundColor), (With<SelectedOption>, With<T>)>,
     mut commands: Commands,
     mut setting: ResMut<T>,
 ) {
@@ -136,10 +329,43 @@ fn setting_button<T: Resource + Component + PartialEq + Copy>(
     }
 }
 
+// Loads a setting from the `PkvStore` on startup and saves it back whenever it changes
+pub struct StoredSettingPlugin<T: StoredSetting>(PhantomData<T>);
+
+impl<T: StoredSetting> Default for StoredSettingPlugin<T> {
+    fn default() -> Self {
+        StoredSettingPlugin(PhantomData)
+    }
+}
+
+impl<T: StoredSetting> Plugin for StoredSettingPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<T>()
+            .add_systems(Startup, load_setting::<T>)
+            .add_systems(Update, save_setting::<T>);
+    }
+}
+
+fn load_setting<T: StoredSetting>(mut setting: ResMut<T>, pkv: Res<PkvStore>) {
+    if let Ok(stored) = pkv.get::<T>(T::KEY) {
+        *setting = stored;
+    }
+}
+
+fn save_setting<T: StoredSetting>(setting: Res<T>, mut pkv: ResMut<PkvStore>) {
+    if setting.is_changed() && !setting.is_added() {
+        pkv.set(T::KEY, &*setting).expect("failed to store setting");
+    }
+}
+
 fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
     menu_state.set(MenuState::Main);
 }
 
+fn close_menu(mut menu_state: ResMut<NextState<MenuState>>) {
+    menu_state.set(MenuState::Disabled);
+}
+
 fn sound_settings_menu_setup(mut commands: Commands, volume: Res<GameVolue>) {
     let button_style = Style {
         width: Val::Px(200.0),
@@ -195,7 +421,7 @@ fn sound_settings_menu_setup(mut commands: Commands, volume: Res<GameVolue>) {
                                 "Volume",
                                 button_text_style.clone(),
                             ));
-                            for volume_setting in [0, 1, 2, 3, 4, 5, 6, 7, 8, 9] {
+                            for volume_setting in 0..=GameVolue::MAX {
                                 let mut entity = parent.spawn((
                                     ButtonBundle {
                                         style: Style {
@@ -238,6 +464,8 @@ fn menu_action(
     mut app_exit_events: EventWriter<AppExit>,
     mut menu_state: ResMut<NextState<MenuState>>,
     mut app_state: ResMut<NextState<AppState>>,
+    mut local_control: ResMut<LocalControl>,
+    mut authority: ResMut<MoveAuthority>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
@@ -245,10 +473,15 @@ fn menu_action(
                 MenuButtonAction::Quit => {
                     app_exit_events.send(AppExit);
                 }
-                MenuButtonAction::Play => {
+                MenuButtonAction::Play => menu_state.set(MenuState::GameMode),
+                MenuButtonAction::StartGame => {
+                    // local games are played with the mouse for both sides
+                    *local_control = LocalControl::BOTH;
+                    *authority = MoveAuthority::Local;
                     app_state.set(AppState::InGame);
                     menu_state.set(MenuState::Disabled);
                 }
+                MenuButtonAction::BackToGameMode => menu_state.set(MenuState::GameMode),
                 MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
                 MenuButtonAction::SettingsDisplay => menu_state.set(MenuState::SettingsDisplay),
                 MenuButtonAction::SettingsSound => menu_state.set(MenuState::SettingsSound),
@@ -260,6 +493,20 @@ fn menu_action(
     }
 }
 
+// Escape leaves the game and goes back to the main menu, unless a text field has the keyboard
+fn leave_game(
+    keys: Res<ButtonInput<KeyCode>>,
+    text_input: Res<TextInputFocused>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_mode: ResMut<NextState<GameModeState>>,
+) {
+    // the chat uses escape to give the keyboard back to the game
+    if keys.just_pressed(KeyCode::Escape) && !text_input.0 {
+        app_state.set(AppState::Menu);
+        game_mode.set(GameModeState::NotInGame);
+    }
+}
+
 // Generic system that takes a component as a parameter, and will despawn all entities with that component
 fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
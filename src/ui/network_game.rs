@@ -0,0 +1,63 @@
+use crate::{logic::OnGameScreen, network::NetworkSession};
+use bevy::prelude::*;
+
+use super::TEXT_COLOR;
+
+// Shows who is playing and how many people are watching a network game
+#[derive(Component)]
+pub struct NetworkGameText;
+
+fn network_game_text(session: &NetworkSession) -> String {
+    let players = match &session.watching {
+        Some((white, black)) => format!("{} vs {}", white, black),
+        None => format!("Playing against {}", session.opponent),
+    };
+    let mut text = format!("{}\nSpectators: {}", players, session.spectators);
+    if session.is_reconnecting() {
+        text += "\nReconnecting...";
+    } else if let Some(grace) = session.opponent_away {
+        text += &format!(
+            "\n{} lost the connection, waiting up to {} s",
+            session.opponent,
+            grace.as_secs()
+        );
+    }
+    text
+}
+
+pub fn spawn_network_game_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                color: TEXT_COLOR,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        NetworkGameText,
+        OnGameScreen,
+    ));
+}
+
+pub fn update_network_game_text(
+    session: Option<Res<NetworkSession>>,
+    mut texts: Query<&mut Text, With<NetworkGameText>>,
+) {
+    // the text stays as it was once the connection is closed at the end of the game
+    let Some(session) = session else {
+        return;
+    };
+    if !session.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = network_game_text(&session);
+    }
+}
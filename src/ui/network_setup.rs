@@ -0,0 +1,210 @@
+use crate::{
+    network::{
+        cancel_network_game, host_game, join_game, protocol::DEFAULT_PORT, watch_game,
+        NetworkStatus,
+    },
+    states::{NetworkAddress, PlayerColor},
+};
+use bevy::{input::keyboard::KeyCode, prelude::*, window::ReceivedCharacter};
+
+use super::{
+    game_setup::{setup_panel, setup_screen_root, setup_styles},
+    MenuState, SelectedOption, NORMAL_BUTTON,
+};
+
+// Tag component used to tag entities added on the network setup screen
+#[derive(Component)]
+pub struct OnNetworkSetupScreen;
+
+#[derive(Component, Clone, Copy)]
+pub enum NetworkButton {
+    Host,
+    Join,
+    Watch,
+    Back,
+}
+
+// The text field holding the address to join, the host listens on its port
+#[derive(Component)]
+pub struct AddressInput;
+
+#[derive(Component)]
+pub struct NetworkStatusText;
+
+pub fn network_setup_menu_setup(
+    mut commands: Commands,
+    player_color: Res<PlayerColor>,
+    address: Res<NetworkAddress>,
+    status: Res<NetworkStatus>,
+) {
+    let (button_style, button_text_style) = setup_styles();
+    let row = NodeBundle {
+        style: Style {
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::CRIMSON.into(),
+        ..default()
+    };
+
+    commands
+        .spawn((setup_screen_root(), OnNetworkSetupScreen))
+        .with_children(|parent| {
+            parent.spawn(setup_panel()).with_children(|parent| {
+                // only the host picks a color, the player who joins gets the other one
+                parent.spawn(row.clone()).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Host plays",
+                        button_text_style.clone(),
+                    ));
+                    for (color, text) in [(true, "White"), (false, "Black")] {
+                        let mut entity = parent.spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            PlayerColor(color),
+                        ));
+                        entity.with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(text, button_text_style.clone()));
+                        });
+                        if *player_color == PlayerColor(color) {
+                            entity.insert(SelectedOption);
+                        }
+                    }
+                });
+                parent.spawn(row.clone()).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Address",
+                        button_text_style.clone(),
+                    ));
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(500.0),
+                                    ..button_style.clone()
+                                },
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            AddressInput,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                address.0.clone(),
+                                button_text_style.clone(),
+                            ));
+                        });
+                });
+                parent.spawn((
+                    TextBundle::from_section(
+                        status.0.clone(),
+                        TextStyle {
+                            font_size: 30.0,
+                            ..button_text_style.clone()
+                        },
+                    ),
+                    NetworkStatusText,
+                ));
+                parent.spawn(row).with_children(|parent| {
+                    for (action, text) in [
+                        (NetworkButton::Back, "Back"),
+                        (NetworkButton::Host, "Host"),
+                        (NetworkButton::Join, "Join"),
+                        (NetworkButton::Watch, "Watch"),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    text,
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                });
+            });
+        });
+}
+
+// the port at the end of the address, e.g. `192.168.1.20:7878`
+fn address_port(address: &str) -> u16 {
+    address
+        .rsplit_once(':')
+        .and_then(|(_, port)| port.parse().ok())
+        .unwrap_or(DEFAULT_PORT)
+}
+
+pub fn network_action(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &NetworkButton), Changed<Interaction>>,
+    address: Res<NetworkAddress>,
+    mut status: ResMut<NetworkStatus>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            NetworkButton::Host => host_game(&mut commands, &mut status, address_port(&address.0)),
+            NetworkButton::Join => join_game(&mut commands, &mut status, address.0.clone()),
+            NetworkButton::Watch => watch_game(&mut commands, &mut status, address.0.clone()),
+            NetworkButton::Back => {
+                cancel_network_game(&mut commands, &mut status);
+                menu_state.set(MenuState::GameMode);
+            }
+        }
+    }
+}
+
+// The address field is the only text field on the screen, so it always gets the typed characters
+pub fn type_address(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut address: ResMut<NetworkAddress>,
+    input_query: Query<&Children, With<AddressInput>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let mut new_address = address.0.clone();
+    if keys.just_pressed(KeyCode::Backspace) {
+        new_address.pop();
+    }
+    for ev in characters.read() {
+        for c in ev.char.chars() {
+            if c.is_ascii_graphic() && new_address.len() < NetworkAddress::MAX_LENGTH {
+                new_address.push(c);
+            }
+        }
+    }
+    if new_address == address.0 {
+        return;
+    }
+    for children in &input_query {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = new_address.clone();
+        }
+    }
+    address.0 = new_address;
+}
+
+pub fn update_network_status(
+    status: Res<NetworkStatus>,
+    mut texts: Query<&mut Text, With<NetworkStatusText>>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = status.0.clone();
+    }
+}